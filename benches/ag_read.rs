@@ -0,0 +1,60 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Times `Client::ag_read` of 16 KB over the in-memory loopback transport,
+//! split in the requests of a 480 bytes PDU. The loopback answers right away,
+//! so the time is spent building the requests and copying the responses.
+//!
+//! ```text
+//! cargo bench --bench ag_read
+//! ```
+
+extern crate s7;
+
+use s7::client::Client;
+use s7::transport::{self, Loopback};
+use std::thread;
+use std::time::Instant;
+
+const READ_SIZE: usize = 16 * 1024;
+const ROUNDS: u32 = 500;
+
+/// answers the negotiation, then every read with the low byte of each offset
+fn plc(plc: Loopback) {
+    plc.recv().unwrap();
+    let mut negotiated = vec![0u8; 27];
+    negotiated[25..].copy_from_slice(&480u16.to_be_bytes());
+    plc.reply(&negotiated).unwrap();
+
+    while let Ok(request) = plc.recv() {
+        let size = u16::from_be_bytes([request[23], request[24]]) as usize;
+        let start = (u32::from_be_bytes([0, request[28], request[29], request[30]]) >> 3) as usize;
+
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend((start..start + size).map(|i| i as u8));
+        plc.reply(&response).unwrap();
+    }
+}
+
+fn main() {
+    let (client_end, plc_end) = transport::loopback();
+    let plc = thread::spawn(move || plc(plc_end));
+    let mut cl = Client::new(client_end).unwrap();
+
+    let mut buffer = vec![0u8; READ_SIZE];
+    cl.ag_read(888, 0, READ_SIZE as i32, &mut buffer).unwrap();
+    assert!(buffer.iter().enumerate().all(|(i, b)| *b == i as u8));
+    let round_trips = cl.last_round_trips();
+
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        cl.ag_read(888, 0, READ_SIZE as i32, &mut buffer).unwrap();
+    }
+    let took = started.elapsed() / ROUNDS;
+
+    println!("16 KB read in {} requests  {:?}", round_trips, took);
+    drop(cl);
+    plc.join().unwrap();
+}
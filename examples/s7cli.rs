@@ -0,0 +1,134 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Reads or writes a single address of a PLC from the command line
+//!
+//! ```text
+//! cargo run --example s7cli -- 192.168.0.1 read DB1.DBD0 real
+//! cargo run --example s7cli -- 192.168.0.1 write MW10 42
+//! cargo run --example s7cli -- 192.168.0.1 --slot 3 read Q0.1
+//! ```
+//!
+//! Addresses are `DB<n>.DBX<byte>.<bit>`, `DB<n>.DB<B|W|D><byte>`,
+//! `<M|I|E|Q|A><B|W|D><byte>` and `<M|I|E|Q|A><byte>.<bit>`.
+//! The type defaults to the size of the address: bool, byte, word or dword,
+//! `int`, `dint`, `real` and `string[n]` change how the value is read.
+
+extern crate s7;
+
+use s7::address::Address;
+use s7::client::Client;
+use s7::constant;
+use s7::field::{Kind, Value};
+use s7::tcp::{self, SlotConfig};
+use s7::transport::Connection;
+use std::env;
+use std::net::IpAddr;
+use std::process;
+
+const USAGE: &str = "usage: s7cli <ip> [--slot <n>] read <address> [type]
+       s7cli <ip> [--slot <n>] write <address> <value> [type]
+types: bool, byte, word, int, dword, dint, real, string[n]";
+
+/// the kind matching the width of the address
+fn default_kind(address: &Address) -> Option<Kind> {
+    match address.word_len {
+        constant::WL_BIT => Some(Kind::Bool),
+        constant::WL_BYTE => Some(Kind::Byte),
+        constant::WL_WORD => Some(Kind::Word),
+        constant::WL_DWORD => Some(Kind::DWord),
+        _ => None,
+    }
+}
+
+fn parse_value(kind: Kind, value: &str) -> Option<Value> {
+    Some(match kind {
+        Kind::Bool => match value {
+            "1" | "true" => Value::Bool(true),
+            "0" | "false" => Value::Bool(false),
+            _ => return None,
+        },
+        Kind::Byte => Value::Byte(value.parse().ok()?),
+        Kind::Word => Value::Word(value.parse().ok()?),
+        Kind::Int => Value::Int(value.parse().ok()?),
+        Kind::DWord => Value::DWord(value.parse().ok()?),
+        Kind::DInt => Value::DInt(value.parse().ok()?),
+        Kind::Real => Value::Real(value.parse().ok()?),
+        Kind::String(_) => Value::String(value.to_string()),
+    })
+}
+
+fn show(value: &Value) -> String {
+    match value {
+        Value::Bool(v) => v.to_string(),
+        Value::Byte(v) => v.to_string(),
+        Value::Word(v) => v.to_string(),
+        Value::Int(v) => v.to_string(),
+        Value::DWord(v) => v.to_string(),
+        Value::DInt(v) => v.to_string(),
+        Value::Real(v) => v.to_string(),
+        Value::String(v) => v.clone(),
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(2)
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    let mut slot = SlotConfig::S7_300.slot();
+    if let Some(i) = args.iter().position(|a| a == "--slot") {
+        if i + 1 >= args.len() {
+            fail("missing slot");
+        }
+        slot = args[i + 1].parse().unwrap_or_else(|_| fail("invalid slot"));
+        args.drain(i..i + 2);
+    }
+
+    if args.len() < 3 {
+        fail("missing arguments");
+    }
+
+    let ip: IpAddr = args[0].parse().unwrap_or_else(|_| fail("invalid ip"));
+    let address: Address = args[2].parse().unwrap_or_else(|_| fail("invalid address"));
+    if address.db_number == 0 && address.area == constant::Area::DataBausteine {
+        fail("missing DB number");
+    }
+
+    let (value, kind) = match args[1].as_str() {
+        "read" => (None, args.get(3)),
+        "write" if args.len() > 3 => (Some(args[3].clone()), args.get(4)),
+        _ => fail("expected read or write"),
+    };
+    let kind = match kind {
+        Some(kind) => kind.parse().unwrap_or_else(|_| fail("invalid type")),
+        None => {
+            default_kind(&address).unwrap_or_else(|| fail("timers and counters aren't supported"))
+        }
+    };
+    let value = value.map(|value| {
+        let value = parse_value(kind, &value).unwrap_or_else(|| fail("invalid value"));
+        kind.encode(&value).unwrap_or_else(|e| fail(&e.to_string()))
+    });
+
+    let opts = tcp::Options::new(ip, 0, slot, Connection::PG);
+    let result = tcp::Transport::connect(opts)
+        .and_then(Client::new)
+        .and_then(|mut cl| {
+            match value {
+                None => println!("{}", show(&cl.read_value(&address, kind)?)),
+                // a bool is written as a single bit, the rest of its byte is left alone
+                Some(bytes) => cl.write_optimized(&[(address, bytes)])?,
+            }
+            Ok(())
+        });
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,230 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Parses the addresses of the Siemens notation, like `DB1.DBD0` or `M10.3`
+
+use super::constant::{self, Area};
+use super::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A location in the PLC memory
+///
+/// Supported notations, English or German mnemonics, case insensitive:
+/// - `DB<n>.DBX<byte>.<bit>`, `DB<n>.DB<B|W|D><byte>`
+/// - `DBX<byte>.<bit>`, `DB<B|W|D><byte>` with no block number, where the DB is given elsewhere
+/// - `<M|I|E|Q|A><byte>.<bit>`, `<M|I|E|Q|A><B|W|D><byte>`
+/// - `P<B|W|D><byte>` peripherals, also as `PIW`, `PQW`, `PEW` or `PAW`, there is no bit access
+/// - `T<n>` timers, `C<n>` or `Z<n>` counters
+///
+/// # Examples
+///
+/// ```
+/// use s7::address::Address;
+/// use s7::constant::{Area, WL_BIT, WL_DWORD};
+///
+/// let real: Address = "DB1.DBD8".parse().unwrap();
+/// assert_eq!(real.area, Area::DataBausteine);
+/// assert_eq!((real.db_number, real.start, real.word_len), (1, 8, WL_DWORD));
+///
+/// let lights: Address = "Q0.1".parse().unwrap();
+/// assert_eq!((lights.start, lights.bit, lights.word_len), (0, 1, WL_BIT));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Address {
+    pub area: Area,
+    /// 0 outside the data blocks or when not given
+    pub db_number: i32,
+    /// byte offset, or the number of the timer or counter
+    pub start: i32,
+    /// bit in the byte, only for `WL_BIT`
+    pub bit: u8,
+    /// `WL_BIT`, `WL_BYTE`, `WL_WORD`, `WL_DWORD`, `WL_TIMER` or `WL_COUNTER`
+    pub word_len: i32,
+}
+
+impl Address {
+    /// size of the addressed value in bytes, a bit takes a whole byte
+    pub fn size(&self) -> i32 {
+        constant::data_size_byte(self.word_len)
+    }
+
+    /// `start` in the bit addressing of `WL_BIT` requests, `byte * 8 + bit`
+    pub fn bit_start(&self) -> i32 {
+        self.start * 8 + self.bit as i32
+    }
+}
+
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Address, Error> {
+        parse(&s.trim().to_uppercase()).ok_or_else(|| Error::InvalidInput {
+            input: format!("address {}", s),
+        })
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let prefix = match self.area {
+            Area::DataBausteine if self.db_number > 0 => format!("DB{}.DB", self.db_number),
+            Area::DataBausteine => "DB".to_string(),
+            Area::Merker => "M".to_string(),
+            Area::ProcessInput => "I".to_string(),
+            Area::ProcessOutput => "Q".to_string(),
+            Area::DirectPeripheral => "P".to_string(),
+            Area::Timer => return write!(f, "T{}", self.start),
+            Area::Counter => return write!(f, "C{}", self.start),
+            Area::Unknown => "?".to_string(),
+        };
+        match self.word_len {
+            constant::WL_BIT if self.area == Area::DataBausteine => {
+                write!(f, "{}X{}.{}", prefix, self.start, self.bit)
+            }
+            constant::WL_BIT => write!(f, "{}{}.{}", prefix, self.start, self.bit),
+            constant::WL_WORD => write!(f, "{}W{}", prefix, self.start),
+            constant::WL_DWORD => write!(f, "{}D{}", prefix, self.start),
+            _ => write!(f, "{}B{}", prefix, self.start),
+        }
+    }
+}
+
+fn parse(s: &str) -> Option<Address> {
+    let (area, db_number, rest) = if let Some(db) = s.strip_prefix("DB") {
+        match db.find('.') {
+            // DB1.DBX0.1
+            Some(dot) if db[..dot].chars().all(|c| c.is_ascii_digit()) => (
+                Area::DataBausteine,
+                db[..dot].parse().ok()?,
+                db[dot + 1..].strip_prefix("DB")?,
+            ),
+            // DBX0.1
+            _ => (Area::DataBausteine, 0, db),
+        }
+    } else {
+        let area = match s.chars().next()? {
+            'M' => Area::Merker,
+            'I' | 'E' => Area::ProcessInput,
+            'Q' | 'A' => Area::ProcessOutput,
+            'T' => Area::Timer,
+            'C' | 'Z' => Area::Counter,
+            // PIW256 and PQW256 are both the peripheral word 256
+            'P' => {
+                let rest = &s[1..];
+                let rest = rest
+                    .strip_prefix(|c| matches!(c, 'I' | 'E' | 'Q' | 'A'))
+                    .unwrap_or(rest);
+                return parse_peripheral(rest);
+            }
+            _ => return None,
+        };
+        (area, 0, &s[1..])
+    };
+
+    let (word_len, offset) = match (area, rest.chars().next()?) {
+        (Area::Timer, _) => (constant::WL_TIMER, rest),
+        (Area::Counter, _) => (constant::WL_COUNTER, rest),
+        (_, 'X') => (constant::WL_BIT, &rest[1..]),
+        (_, 'B') => (constant::WL_BYTE, &rest[1..]),
+        (_, 'W') => (constant::WL_WORD, &rest[1..]),
+        (_, 'D') => (constant::WL_DWORD, &rest[1..]),
+        (Area::DataBausteine, _) => return None,
+        _ => (constant::WL_BIT, rest),
+    };
+
+    let mut parts = offset.splitn(2, '.');
+    let start: i32 = parts.next()?.parse().ok()?;
+    let bit = match (word_len, parts.next()) {
+        (constant::WL_BIT, Some(bit)) => bit.parse().ok().filter(|b| *b < 8)?,
+        (constant::WL_BIT, None) => return None,
+        (_, None) => 0,
+        _ => return None,
+    };
+
+    Some(Address {
+        area,
+        db_number,
+        start,
+        bit,
+        word_len,
+    })
+}
+
+fn parse_peripheral(rest: &str) -> Option<Address> {
+    let word_len = match rest.chars().next()? {
+        'B' => constant::WL_BYTE,
+        'W' => constant::WL_WORD,
+        'D' => constant::WL_DWORD,
+        _ => return None,
+    };
+
+    Some(Address {
+        area: Area::DirectPeripheral,
+        db_number: 0,
+        start: rest[1..].parse().ok()?,
+        bit: 0,
+        word_len,
+    })
+}
+
+#[test]
+fn test_parse() {
+    let cases = [
+        ("DB1.DBX8.4", Area::DataBausteine, 1, 8, 4, constant::WL_BIT),
+        ("db888.dbw12", Area::DataBausteine, 888, 12, 0, constant::WL_WORD),
+        ("DBD4", Area::DataBausteine, 0, 4, 0, constant::WL_DWORD),
+        ("M10.3", Area::Merker, 0, 10, 3, constant::WL_BIT),
+        ("MW10", Area::Merker, 0, 10, 0, constant::WL_WORD),
+        ("EB2", Area::ProcessInput, 0, 2, 0, constant::WL_BYTE),
+        ("I0.7", Area::ProcessInput, 0, 0, 7, constant::WL_BIT),
+        ("AD4", Area::ProcessOutput, 0, 4, 0, constant::WL_DWORD),
+        ("T5", Area::Timer, 0, 5, 0, constant::WL_TIMER),
+        ("Z3", Area::Counter, 0, 3, 0, constant::WL_COUNTER),
+        ("PIW256", Area::DirectPeripheral, 0, 256, 0, constant::WL_WORD),
+        ("PQB4", Area::DirectPeripheral, 0, 4, 0, constant::WL_BYTE),
+        ("PAD8", Area::DirectPeripheral, 0, 8, 0, constant::WL_DWORD),
+        ("PW2", Area::DirectPeripheral, 0, 2, 0, constant::WL_WORD),
+    ];
+
+    for (s, area, db_number, start, bit, word_len) in cases.iter() {
+        let address: Address = s.parse().unwrap();
+        assert_eq!(
+            address,
+            Address {
+                area: *area,
+                db_number: *db_number,
+                start: *start,
+                bit: *bit,
+                word_len: *word_len,
+            },
+            "{}",
+            s
+        );
+    }
+
+    let wrong = [
+        "", "DB1", "DB1.DBX8", "DB1.DBX8.8", "M10", "MW10.1", "X1", "DB1.MW2", "P0.1", "PIX0.1",
+        "PW",
+    ];
+    for s in wrong.iter() {
+        assert!(s.parse::<Address>().is_err(), "{}", s);
+    }
+}
+
+#[test]
+fn test_display() {
+    let printed = ["DB1.DBX8.4", "DB888.DBW12", "DBD4", "M10.3", "IB2", "QD4", "T5", "C3", "PW256"];
+    for s in printed.iter() {
+        assert_eq!(s.parse::<Address>().unwrap().to_string(), *s);
+    }
+}
+
+#[test]
+fn test_display_parse() {
+    for s in ["DB1.DBX8.4", "MW10", "E0.7", "AD4", "Z3", "PIW256", "PQB4", "PED8"].iter() {
+        let address: Address = s.parse().unwrap();
+        assert_eq!(address.to_string().parse::<Address>().unwrap(), address, "{}", s);
+    }
+}
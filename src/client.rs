@@ -2,12 +2,31 @@
 // This software may be modified and distributed under the terms
 // of the BSD license. See the LICENSE file for details.
 
-use super::constant::{self, Area};
+use super::address::Address;
+use super::constant;
 use super::error::{self, Error};
+use super::field::{self, Kind, Value};
+use super::layout::DbLayout;
 use super::transport::{self, Transport};
-use crate::constant::CpuStatus;
 use byteorder::{BigEndian, ByteOrder};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::Write;
+use std::net::Ipv4Addr;
 use std::str;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// moved to `constant::Area`
+#[doc(hidden)]
+#[deprecated(note = "use s7::constant::Area")]
+pub type Area = constant::Area;
+
+/// moved to `constant::CpuStatus`
+#[doc(hidden)]
+#[deprecated(note = "use s7::constant::CpuStatus")]
+pub type CpuStatus = constant::CpuStatus;
 
 #[derive(Debug, Clone)]
 pub struct CpuInfo {
@@ -18,6 +37,28 @@ pub struct CpuInfo {
     module_name: String,
 }
 
+impl CpuInfo {
+    pub fn module_type_name(&self) -> &str {
+        &self.module_type_name
+    }
+
+    pub fn serial_number(&self) -> &str {
+        &self.serial_number
+    }
+
+    pub fn as_name(&self) -> &str {
+        &self.as_name
+    }
+
+    pub fn copyright(&self) -> &str {
+        &self.copyright
+    }
+
+    pub fn module_name(&self) -> &str {
+        &self.module_name
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CPInfo {
     max_pdu_length: u16,
@@ -26,15 +67,677 @@ pub struct CPInfo {
     max_bus_rate: u16,
 }
 
+impl CPInfo {
+    pub fn max_pdu_length(&self) -> u16 {
+        self.max_pdu_length
+    }
+
+    pub fn max_connections(&self) -> u16 {
+        self.max_connections
+    }
+
+    pub fn max_mpi_rate(&self) -> u16 {
+        self.max_mpi_rate
+    }
+
+    pub fn max_bus_rate(&self) -> u16 {
+        self.max_bus_rate
+    }
+}
+
+/// The current operating mode and the last mode transition, SZL 0x0424
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatingModeInfo {
+    /// the event that caused the last mode transition,
+    /// for instance `0x4562` is a STOP caused by a programming error
+    pub event_id: u16,
+    /// the mode the CPU was asked to go to
+    pub requested_mode: u8,
+    /// the mode before the transition
+    pub previous_mode: u8,
+    /// type of startup
+    pub startup_type: u8,
+    /// permitted startup
+    pub permitted_startup: u8,
+    /// the current mode: `0x03` STOP, `0x04` cold startup, `0x05` warm startup,
+    /// `0x06` hot startup, `0x08` RUN, `0x0A` HOLD, `0x0D` defect
+    pub current_mode: u8,
+    /// BCD timestamp of the transition
+    pub timestamp: [u8; 8],
+}
+
+/// Sizes of the work and load memory in bytes, SZL 0x0013
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct MemoryUsage {
+    pub work_total: u32,
+    pub work_used: u32,
+    /// largest contiguous free block of work memory
+    pub work_largest_free: u32,
+    /// integrated and plugged in load memory together
+    pub load_total: u32,
+    pub load_used: u32,
+    /// largest contiguous free block of load memory
+    pub load_largest_free: u32,
+}
+
+impl MemoryUsage {
+    pub fn work_free(&self) -> u32 {
+        self.work_total.saturating_sub(self.work_used)
+    }
+
+    pub fn load_free(&self) -> u32 {
+        self.load_total.saturating_sub(self.load_used)
+    }
+}
+
+/// OB1 cycle times, SZL 0x0222
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CycleTime {
+    /// duration of the last cycle
+    pub current: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+/// IP settings of the Ethernet interface, SZL 0x0037
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NetworkConfig {
+    pub ip_address: Ipv4Addr,
+    pub subnet_mask: Ipv4Addr,
+    /// the default router, the interface's own address or 0.0.0.0 when none is used
+    pub gateway: Ipv4Addr,
+    pub mac_address: [u8; 6],
+}
+
+/// The CPU clock and the time zone it's configured for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlcClock {
+    /// the local time of the CPU, `YYYY/MM/DD HH:MM:SS.mmm`
+    pub local: String,
+    /// daylight saving time is in effect, false when the CPU doesn't tell
+    pub dst_active: bool,
+    /// local time minus UTC, `None` for CPUs without the time system status
+    pub utc_offset_minutes: Option<i16>,
+}
+
+/// One request of a write split over several PDUs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WriteChunk {
+    /// address of the first element, the bit address for bits
+    pub start: i32,
+    pub elements: i32,
+    /// return code of the data item, `0xFF` when written
+    pub result: u8,
+}
+
+/// What a write sent, chunk by chunk, see `Client::write_reported`
+#[derive(Debug, Default)]
+pub struct WriteReport {
+    /// the chunks the PLC answered, in order
+    pub chunks: Vec<WriteChunk>,
+    /// why the write stopped early, the remaining data wasn't sent
+    pub error: Option<Error>,
+}
+
+impl WriteReport {
+    pub fn is_complete(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// elements the PLC confirmed as written
+    pub fn elements_written(&self) -> i32 {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.result == 0xFF)
+            .map(|chunk| chunk.elements)
+            .sum()
+    }
+}
+
+/// Block types as encoded by the PLC
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BlockType {
+    OB = 0x38,
+    DB = 0x41,
+    SDB = 0x42,
+    FC = 0x43,
+    SFC = 0x44,
+    FB = 0x45,
+    SFB = 0x46,
+}
+
+impl BlockType {
+    /// every block type, in the order the programming software lists them
+    pub const ALL: [BlockType; 7] = [
+        BlockType::OB,
+        BlockType::FB,
+        BlockType::FC,
+        BlockType::DB,
+        BlockType::SFB,
+        BlockType::SFC,
+        BlockType::SDB,
+    ];
+}
+
+/// A block stored in the PLC, see `Client::block_directory`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BlockDirEntry {
+    pub block_type: BlockType,
+    pub block_number: u16,
+    /// the load memory size in bytes
+    pub load_size: i32,
+    /// the MC7 code size in bytes, what the block takes in work memory
+    pub mc7_size: u16,
+}
+
+/// Decoded `S7BlockInfo::block_flags`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BlockFlags {
+    /// the block is linked in the program, unlinked blocks are only stored
+    pub linked: bool,
+    /// a standard block shipped by Siemens
+    pub standard_block: bool,
+    /// the values of the block are not retained after a power off
+    pub non_retain: bool,
+    /// the block is read only in the device
+    pub write_protected: bool,
+    /// the raw byte, for the bits without a meaning here
+    pub raw: u8,
+}
+
+impl From<u8> for BlockFlags {
+    fn from(flags: u8) -> Self {
+        BlockFlags {
+            linked: flags & 0x01 != 0,
+            standard_block: flags & 0x02 != 0,
+            non_retain: flags & 0x08 != 0,
+            write_protected: flags & 0x20 != 0,
+            raw: flags,
+        }
+    }
+}
+
+/// Block metadata as reported by the PLC.
+/// Two infos compare equal when nothing in the block header changed,
+/// which is handy to detect modified blocks between two snapshots.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct S7BlockInfo {
+    pub block_type: u8,
+    pub block_number: u16,
+    pub block_lang: u8,
+    pub block_flags: u8,
+    /// the MC7 code size in bytes, what runs in work memory.
+    /// 0 for a block only in load memory, see `has_code`
+    pub mc7_size: u16,
+    /// the load memory size in bytes, headers and code as downloaded
+    pub load_size: i32,
+    pub local_data: u16,
+    pub sbb_length: u16,
+    /// checksum of the block as computed by the PLC
+    pub checksum: u16,
+    pub version: u8,
+    /// date of the last code change as `YYYY/MM/DD HH:MM:SS.mmm`
+    pub code_date: String,
+    /// date of the last interface change as `YYYY/MM/DD HH:MM:SS.mmm`
+    pub interface_date: String,
+    /// the header fields of the block properties, 8 characters each.
+    /// The block title and comments aren't among them: they stay in the
+    /// engineering project and are never downloaded to the CPU, neither with the
+    /// block info nor with the uploaded block.
+    pub author: String,
+    pub family: String,
+    /// the block name of the properties, not its title
+    pub header: String,
+}
+
+impl S7BlockInfo {
+    /// the decoded `block_flags`
+    pub fn flags(&self) -> BlockFlags {
+        BlockFlags::from(self.block_flags)
+    }
+
+    /// the block has MC7 code the CPU can run or, for data blocks, data in work memory.
+    /// A block with a load size but no MC7 size is only stored in load memory.
+    pub fn has_code(&self) -> bool {
+        self.mc7_size > 0
+    }
+}
+
+/// Describes a variable in the PLC memory
 #[derive(Debug, Clone)]
+pub struct S7DataItem {
+    pub area: constant::Area,
+    /// one of the `WL_*` word lengths
+    pub word_len: i32,
+    /// only used for `Area::DataBausteine`
+    pub db_number: i32,
+    /// byte offset of the variable, `byte * 8 + bit` for `WL_BIT`
+    pub start: i32,
+    /// number of elements of `word_len` size
+    pub size: i32,
+    /// the data read by `Client::read_multi_vars`, refilled without reallocating
+    /// when its capacity is enough
+    pub buffer: Vec<u8>,
+    /// the error the PLC reported for this item in the last multi read
+    pub err: Option<Error>,
+}
+
+impl S7DataItem {
+    pub fn new(
+        area: constant::Area,
+        word_len: i32,
+        db_number: i32,
+        start: i32,
+        size: i32,
+    ) -> S7DataItem {
+        S7DataItem {
+            area,
+            word_len,
+            db_number,
+            start,
+            size,
+            buffer: Vec::new(),
+            err: None,
+        }
+    }
+
+    /// word length and number of elements sent to the PLC.
+    /// The word length is kept so the PLC answers with the matching transport size,
+    /// only a single bit can be transferred at a time.
+    fn transfer(&self) -> (i32, i32) {
+        match self.area {
+            constant::Area::Counter => (constant::WL_COUNTER, self.size),
+            constant::Area::Timer => (constant::WL_TIMER, self.size),
+            _ if self.word_len == constant::WL_BIT => (constant::WL_BIT, 1),
+            _ => (self.word_len, self.size),
+        }
+    }
+}
+
+/// Builds a `S7DataItem` checked before it reaches the PLC:
+/// the word length has to suit the area, the size be at least 1
+/// and the buffer hold `size` elements of the word length.
+/// Without a buffer, one of the right size is allocated.
+///
+/// # Examples
+///
+/// ```
+/// use s7::client::S7DataItemBuilder;
+/// use s7::constant::{Area, WL_WORD};
+///
+/// // DB888.DBW4 to DBW8
+/// let item = S7DataItemBuilder::new(Area::DataBausteine, WL_WORD, 888, 4, 3)
+///     .build()
+///     .unwrap();
+/// assert_eq!(item.buffer.len(), 6);
+///
+/// assert!(S7DataItemBuilder::new(Area::DataBausteine, WL_WORD, 888, 4, 3)
+///     .buffer(vec![0; 4])
+///     .build()
+///     .is_err());
+/// ```
+#[derive(Debug)]
+pub struct S7DataItemBuilder {
+    item: S7DataItem,
+    buffer: Option<Vec<u8>>,
+}
+
+impl S7DataItemBuilder {
+    pub fn new(
+        area: constant::Area,
+        word_len: i32,
+        db_number: i32,
+        start: i32,
+        size: i32,
+    ) -> S7DataItemBuilder {
+        S7DataItemBuilder {
+            item: S7DataItem::new(area, word_len, db_number, start, size),
+            buffer: None,
+        }
+    }
+
+    /// the data to write, or a buffer to read into
+    pub fn buffer(mut self, buffer: Vec<u8>) -> S7DataItemBuilder {
+        self.buffer = Some(buffer);
+        self
+    }
+
+    pub fn build(self) -> Result<S7DataItem, Error> {
+        let mut item = self.item;
+        check_address(item.area, item.word_len, item.db_number)
+            .map_err(|input| Error::InvalidInput { input })?;
+        if item.size < 1 {
+            return Err(Error::InvalidInput {
+                input: format!("size {}", item.size),
+            });
+        }
+
+        let (word_len, num_elements) = item.transfer();
+        match self.buffer {
+            Some(buffer) => {
+                check_buffer(word_len, num_elements, buffer.len())?;
+                item.buffer = buffer;
+            }
+            None => {
+                let size = num_elements * constant::data_size_byte(word_len);
+                item.buffer = vec![0u8; size.max(1) as usize];
+            }
+        }
+        Ok(item)
+    }
+}
+
+/// An address forced in the CPU and the value it's forced to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForceEntry {
+    pub area: constant::Area,
+    /// `WL_BYTE`, `WL_WORD` or `WL_DWORD`, `WL_TIMER` and `WL_COUNTER` for their areas
+    pub word_len: i32,
+    /// only set for `Area::DataBausteine`
+    pub db_number: i32,
+    /// byte offset of the forced address
+    pub start: i32,
+    pub value: Vec<u8>,
+}
+
+/// Client for a single connection to the PLC.
+///
+/// A connection carries one request at a time, so the client is not `Clone`:
+/// two copies writing to the same socket would interleave their telegrams.
+/// To share a connection between threads wrap the client in `Arc<Mutex<_>>`.
+///
+/// ```compile_fail
+/// use s7::{client::Client, transport};
+///
+/// let (client_end, _plc) = transport::loopback();
+/// let cl = Client::new(client_end).unwrap();
+/// let _copy = cl.clone();
+/// ```
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::{Ipv4Addr, IpAddr};
+/// use std::sync::{Arc, Mutex};
+/// use std::thread;
+/// use s7::{client, tcp, transport};
+///
+/// let addr = Ipv4Addr::new(127, 0, 0, 1);
+/// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+/// let t = tcp::Transport::connect(opts).unwrap();
+/// let cl = Arc::new(Mutex::new(client::Client::new(t).unwrap()));
+///
+/// let shared = Arc::clone(&cl);
+/// let poller = thread::spawn(move || {
+///     let buffer = &mut vec![0u8; 2];
+///     shared.lock().unwrap().ag_read(888, 0, 2, buffer).unwrap();
+/// });
+///
+/// let buffer = &mut vec![0u8; 2];
+/// cl.lock().unwrap().ag_read(888, 2, 2, buffer).unwrap();
+/// poller.join().unwrap();
+/// ```
+#[derive(Debug)]
 pub struct Client<T: Transport> {
     transport: T,
+    /// upper bound for reads and writes split over several requests
+    operation_timeout: Option<Duration>,
+    /// PDU reference of the last job sent, shared with the transport
+    pdu_ref: Arc<AtomicU16>,
+    /// requests sent by the last read or write
+    round_trips: usize,
+    /// how often reads check that the CPU is still in RUN
+    run_watchdog: Option<Duration>,
+    /// when the CPU was last seen in RUN by the watchdog
+    seen_running: Option<Instant>,
+    /// negotiate again and retry when the PLC refuses a request as larger than its PDU
+    renegotiate_pdu: bool,
 }
 
 impl<T: Transport> Client<T> {
     pub fn new(mut transport: T) -> Result<Client<T>, Error> {
-        transport.negotiate()?;
-        Ok(Client { transport })
+        let pdu_length = transport.negotiate()?.pdu_length as i32;
+        if pdu_length < transport::MIN_PDU_LENGTH {
+            return Err(Error::PduTooSmall(pdu_length));
+        }
+        Ok(Client {
+            pdu_ref: transport.pdu_ref_counter().unwrap_or_default(),
+            transport,
+            operation_timeout: None,
+            round_trips: 0,
+            run_watchdog: None,
+            seen_running: None,
+            renegotiate_pdu: false,
+        })
+    }
+
+    /// bounds the total time of reads and writes too large for a single PDU.
+    /// The socket timeouts only apply to each request, a read split over many requests
+    /// could otherwise take any of them times the number of requests.
+    /// Once exceeded no further request is sent and `Error::Timeout` is returned,
+    /// the buffer is then only partially updated. `None`, the default, means no bound.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use std::time::Duration;
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// cl.set_operation_timeout(Some(Duration::from_millis(500)));
+    /// let buffer = &mut vec![0u8; 65535];
+    ///
+    /// match cl.read_full_db(888, buffer) {
+    ///     Ok(size) => println!("DB888 {:?}", &buffer[..size]),
+    ///     Err(e) => println!("skipping this poll cycle: {}", e),
+    /// }
+    /// ```
+    pub fn set_operation_timeout(&mut self, timeout: Option<Duration>) {
+        self.operation_timeout = timeout;
+    }
+
+    fn check_operation_timeout(&self, started: Instant) -> Result<(), Error> {
+        match self.operation_timeout {
+            Some(timeout) if started.elapsed() >= timeout => Err(Error::Timeout),
+            _ => Ok(()),
+        }
+    }
+
+    /// makes reads check the CPU status at most once every `cadence`.
+    /// A CPU found out of RUN fails the read with `Error::NotRunning`
+    /// instead of returning the frozen or zeroed values of a stopped program,
+    /// and every following read checks again until it's back in RUN.
+    /// `None`, the default, never checks.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use std::time::Duration;
+    /// use s7::{client, tcp, transport};
+    /// use s7::error::Error;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// cl.set_run_watchdog(Some(Duration::from_secs(1)));
+    /// let buffer = &mut vec![0u8; 2];
+    ///
+    /// match cl.ag_read(888, 0, 2, buffer) {
+    ///     Ok(()) => println!("{:?}", buffer),
+    ///     Err(Error::NotRunning(status)) => println!("failing safe, PLC in {:?}", status),
+    ///     Err(e) => println!("{}", e),
+    /// }
+    /// ```
+    pub fn set_run_watchdog(&mut self, cadence: Option<Duration>) {
+        self.run_watchdog = cadence;
+        self.seen_running = None;
+    }
+
+    fn check_running(&mut self) -> Result<(), Error> {
+        let cadence = match self.run_watchdog {
+            Some(cadence) => cadence,
+            None => return Ok(()),
+        };
+        if let Some(seen) = self.seen_running {
+            if seen.elapsed() < cadence {
+                return Ok(());
+            }
+        }
+
+        let status = self.plc_status()?;
+        if !status.is_running() {
+            self.seen_running = None;
+            return Err(Error::NotRunning(status));
+        }
+        self.seen_running = Some(Instant::now());
+        Ok(())
+    }
+
+    /// makes reads, writes and `read_multi_vars` recover from a PLC refusing a request
+    /// as larger than its PDU, error `0x8500`. Some CPUs advertise another PDU after a
+    /// mode transition, the pdu length is then negotiated again over the same connection
+    /// and the request is retried, split to the new size. Only once per call,
+    /// a second refusal is returned. Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// cl.set_pdu_renegotiation(true);
+    /// let buffer = &mut vec![0u8; 1024];
+    /// cl.ag_read(888, 0, 1024, buffer).unwrap();
+    /// println!("PDU now {}", cl.negotiation_info().pdu_length);
+    /// ```
+    pub fn set_pdu_renegotiation(&mut self, enabled: bool) {
+        self.renegotiate_pdu = enabled;
+    }
+
+    /// negotiates the pdu length again if enabled and not already done by this call,
+    /// telling whether the refused request should be retried
+    fn renegotiate_pdu(&mut self, response: &[u8], renegotiated: &mut bool) -> Result<bool, Error> {
+        if !self.renegotiate_pdu || *renegotiated || !pdu_size_refused(response) {
+            return Ok(false);
+        }
+        *renegotiated = true;
+
+        let pdu_length = self.transport.renegotiate_pdu()?.pdu_length as i32;
+        if pdu_length < transport::MIN_PDU_LENGTH {
+            return Err(Error::PduTooSmall(pdu_length));
+        }
+        Ok(true)
+    }
+
+    /// sets the PDU reference of the next job, bytes 11 and 12 of the telegram.
+    /// Every job sent afterwards increments it, wrapping around at `u16::MAX`,
+    /// so the requests can be found by reference in a packet capture.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// cl.set_pdu_ref_base(0x7000);
+    /// let buffer = &mut vec![0u8; 4];
+    /// let took = cl.ag_read_timed(888, 8, 4, buffer).unwrap();
+    /// println!("pdu ref {:#06x} took {:?}", cl.last_pdu_ref(), took);
+    /// ```
+    pub fn set_pdu_ref_base(&mut self, base: u16) {
+        self.pdu_ref.store(base.wrapping_sub(1), Ordering::Relaxed);
+    }
+
+    /// PDU reference of the last job sent, a read split over several requests
+    /// used the references up to this one
+    pub fn last_pdu_ref(&self) -> u16 {
+        self.pdu_ref.load(Ordering::Relaxed)
+    }
+
+    /// how many requests the last read or write was split into, each one a PDU
+    /// round trip. Many round trips for a range read in every cycle mean a larger PDU
+    /// or a more compact DB layout would pay off. A failed operation counts the
+    /// requests sent before the failure.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let buffer = &mut vec![0u8; 4000];
+    /// let took = cl.ag_read_timed(888, 0, 4000, buffer).unwrap();
+    /// println!("{:?} in {} round trips", took, cl.last_round_trips());
+    /// ```
+    pub fn last_round_trips(&self) -> usize {
+        self.round_trips
+    }
+
+    fn next_pdu_ref(&mut self) -> u16 {
+        self.pdu_ref.fetch_add(1, Ordering::Relaxed).wrapping_add(1)
+    }
+
+    /// stamps the next PDU reference in the S7 header of the job and sends it
+    fn send_job(&mut self, request: &mut [u8]) -> Result<Vec<u8>, Error> {
+        let reference = self.next_pdu_ref();
+        BigEndian::write_u16(request[11..].as_mut(), reference);
+        self.transport.send(request)
+    }
+
+    /// the parameters agreed with the PLC when connecting:
+    /// pdu length and how many requests may be outstanding at the same time
+    pub fn negotiation_info(&self) -> transport::NegotiationInfo {
+        self.transport.negotiation_info()
+    }
+
+    /// the address of the PLC, the connection type and the negotiated parameters
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let info = cl.connection_info();
+    /// let buffer = &mut vec![0u8; 2];
+    /// if let Err(e) = cl.ag_read(888, 0, 2, buffer) {
+    ///     println!("[{:?} {:?}] {}", info.peer_addr, info.connection_type, e);
+    /// }
+    /// ```
+    pub fn connection_info(&self) -> transport::ConnectionInfo {
+        transport::ConnectionInfo {
+            peer_addr: self.transport.peer_addr(),
+            connection_type: self.transport.connection_type(),
+            negotiation: self.transport.negotiation_info(),
+        }
     }
 
     /// # Examples
@@ -77,16 +780,16 @@ impl<T: Transport> Client<T> {
         db_number: i32,
         start: i32,
         size: i32,
-        buffer: &mut Vec<u8>,
+        buffer: &mut [u8],
     ) -> Result<(), Error> {
-        return self.read(
-            Area::DataBausteine,
+        self.read(
+            constant::Area::DataBausteine,
             db_number,
             start,
             size,
             constant::WL_BYTE,
             buffer,
-        );
+        )
     }
 
     /// # Examples
@@ -129,69 +832,593 @@ impl<T: Transport> Client<T> {
         db_number: i32,
         start: i32,
         size: i32,
-        buffer: &mut Vec<u8>,
+        buffer: &mut [u8],
     ) -> Result<(), Error> {
-        return self.write(
-            Area::DataBausteine,
+        self.write(
+            constant::Area::DataBausteine,
             db_number,
             start,
             size,
             constant::WL_BYTE,
             buffer,
-        );
+        )
     }
 
+    /// writes the whole of `data` to the data block at `start`,
+    /// split in as many requests as the PDU length requires.
+    /// Unlike `ag_write` the data doesn't need to be mutable.
+    ///
     /// # Examples
     ///
     /// ```no_run
     /// use std::net::{Ipv4Addr, IpAddr};
     /// use s7::{client, tcp, transport};
-    /// use std::time::Duration;
     ///
     /// let addr = Ipv4Addr::new(127, 0, 0, 1);
-    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
-    ///
-    /// opts.read_timeout = Duration::from_secs(2);
-    /// opts.write_timeout = Duration::from_secs(2);
-    ///
-    ///
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
     /// let t = tcp::Transport::connect(opts).unwrap();
     /// let mut cl = client::Client::new(t).unwrap();
     ///
-    /// let buffer = &mut vec![0u8; 255];
-    ///
-    /// cl.mb_read(1, 3, buffer).unwrap();
+    /// const RECIPE: [u8; 6] = [0, 12, 0, 250, 1, 0];
+    /// cl.ag_write_all(888, 100, &RECIPE).unwrap();
     /// ```
-    pub fn mb_read(&mut self, start: i32, size: i32, buffer: &mut Vec<u8>) -> Result<(), Error> {
-        return self.read(Area::Merker, 0, start, size, constant::WL_BYTE, buffer);
+    pub fn ag_write_all(&mut self, db_number: i32, start: i32, data: &[u8]) -> Result<(), Error> {
+        if data.len() > i32::MAX as usize {
+            return Err(Error::InvalidInput {
+                input: format!("{} bytes to write", data.len()),
+            });
+        }
+        self.write(
+            constant::Area::DataBausteine,
+            db_number,
+            start,
+            data.len() as i32,
+            constant::WL_BYTE,
+            data,
+        )
     }
 
+    /// writes a set of values merging the ones that touch each other into a single write
+    /// per contiguous range of the same area and block. Bytes between two values are never
+    /// written, so the gaps keep whatever the PLC holds. Bits are written one by one
+    /// with bit writes rather than read-modify-write, the PLC may change the other bits
+    /// of the byte in between. Timers and counters can't be merged and are rejected,
+    /// each value has to be the size of its address and DB addresses need a block number.
+    ///
     /// # Examples
     ///
     /// ```no_run
     /// use std::net::{Ipv4Addr, IpAddr};
     /// use s7::{client, tcp, transport};
-    /// use std::time::Duration;
     ///
     /// let addr = Ipv4Addr::new(127, 0, 0, 1);
-    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
-    ///
-    /// opts.read_timeout = Duration::from_secs(2);
-    /// opts.write_timeout = Duration::from_secs(2);
-    ///
-    ///
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
     /// let t = tcp::Transport::connect(opts).unwrap();
     /// let mut cl = client::Client::new(t).unwrap();
     ///
-    /// let buffer = &mut vec![0u8; 255];
-    ///
-    /// cl.mb_write(1, 3, buffer).unwrap();
+    /// let recipe = vec![
+    ///     ("DB888.DBD0".parse().unwrap(), 55.5f32.to_be_bytes().to_vec()),
+    ///     ("DB888.DBD4".parse().unwrap(), 70.0f32.to_be_bytes().to_vec()),
+    ///     ("DB888.DBX12.0".parse().unwrap(), vec![1]),
+    /// ];
+    /// cl.write_optimized(&recipe).unwrap();
+    /// println!("{} round trips", cl.last_round_trips());
     /// ```
-    pub fn mb_write(&mut self, start: i32, size: i32, buffer: &mut Vec<u8>) -> Result<(), Error> {
-        return self.write(Area::Merker, 0, start, size, constant::WL_BYTE, buffer);
-    }
+    pub fn write_optimized(&mut self, items: &[(Address, Vec<u8>)]) -> Result<(), Error> {
+        let mut ranges: Vec<(constant::Area, i32, i32, &[u8])> = Vec::new();
+        let mut bits = Vec::new();
+        for (address, data) in items.iter() {
+            if let constant::Area::Timer | constant::Area::Counter | constant::Area::Unknown =
+                address.area
+            {
+                return Err(Error::InvalidInput {
+                    input: format!("{} can't be merged with other writes", address),
+                });
+            }
+            check_db_number(address)?;
+            if data.len() != address.size() as usize {
+                return Err(Error::InvalidInput {
+                    input: format!("{} bytes for {}", data.len(), address),
+                });
+            }
+            if address.word_len == constant::WL_BIT {
+                bits.push((address, data[0] != 0));
+            } else {
+                ranges.push((address.area, address.db_number, address.start, data));
+            }
+        }
+        ranges.sort_by_key(|(area, db_number, start, _)| (*area as u8, *db_number, *start));
 
-    /// # Examples
+        // area, block, start and data of each write
+        let mut spans: Vec<(constant::Area, i32, i32, Vec<u8>)> = Vec::new();
+        for (area, db_number, start, data) in ranges {
+            match spans.last_mut() {
+                Some(last) if last.0 == area && last.1 == db_number => {
+                    let end = last.2 + last.3.len() as i32;
+                    if start < end {
+                        return Err(Error::InvalidInput {
+                            input: format!("overlapping writes at byte {}", start),
+                        });
+                    }
+                    if start == end {
+                        last.3.extend_from_slice(data);
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            spans.push((area, db_number, start, data.to_vec()));
+        }
+
+        let mut round_trips = 0;
+        let mut result = Ok(());
+        for (area, db_number, start, data) in spans.iter() {
+            result = self.write(
+                *area,
+                *db_number,
+                *start,
+                data.len() as i32,
+                constant::WL_BYTE,
+                data,
+            );
+            round_trips += self.round_trips;
+            if result.is_err() {
+                break;
+            }
+        }
+        if result.is_ok() {
+            for (address, value) in bits {
+                result = self.write(
+                    address.area,
+                    address.db_number,
+                    address.bit_start(),
+                    1,
+                    constant::WL_BIT,
+                    &[value as u8],
+                );
+                round_trips += self.round_trips;
+                if result.is_err() {
+                    break;
+                }
+            }
+        }
+        self.round_trips = round_trips;
+        result
+    }
+
+    /// same as `ag_read` and returns how long the exchange with the PLC took,
+    /// large reads are split in several requests and the total is returned
+    /// and `last_pdu_ref` tells the reference of the last of them
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let buffer = &mut vec![0u8; 4];
+    /// let took = cl.ag_read_timed(888, 8, 4, buffer).unwrap();
+    /// println!("read {:?} in {:?}", buffer, took);
+    /// ```
+    pub fn ag_read_timed(
+        &mut self,
+        db_number: i32,
+        start: i32,
+        size: i32,
+        buffer: &mut [u8],
+    ) -> Result<Duration, Error> {
+        let started = Instant::now();
+        self.ag_read(db_number, start, size, buffer)?;
+        Ok(started.elapsed())
+    }
+
+    /// finds the largest read of `db_number` the PLC answers in a single request,
+    /// by binary search between 1 byte and `u16::MAX` minus the reply headers.
+    /// Some gateways misreport the negotiated PDU, the result tells the real usable payload.
+    /// Reads refused as larger than the PDU or answered with less data count as too large,
+    /// any other error is returned. The DB has to be at least as large as the result,
+    /// a shorter one fails with address out of range. Takes at most 16 reads.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let max = cl.probe_max_read(888).unwrap();
+    /// println!("negotiated {} reads of {} bytes", cl.negotiation_info().pdu_length, max);
+    /// ```
+    pub fn probe_max_read(&mut self, db_number: i32) -> Result<i32, Error> {
+        check_address(constant::Area::DataBausteine, constant::WL_BYTE, db_number)
+            .map_err(|input| Error::InvalidInput { input })?;
+
+        // largest size known to fit and smallest known not to
+        let (mut fits, mut too_large) = (0, transport::MAX_PROBE_READ + 1);
+        let mut round_trips = 0;
+        while too_large - fits > 1 {
+            let size = fits + (too_large - fits) / 2;
+            let mut request = read_request(
+                constant::Area::DataBausteine,
+                db_number,
+                0,
+                constant::WL_BYTE,
+                size,
+            );
+            let response = self.send_job(request.as_mut_slice());
+            round_trips += 1;
+            self.round_trips = round_trips;
+            let response = response?;
+
+            if response.len() >= 19 {
+                let result = BigEndian::read_u16(response[17..].as_ref()) as i32;
+                if result == error::CODE_7_DATA_OVER_PDU {
+                    too_large = size;
+                    continue;
+                }
+                if result != 0 {
+                    return Err(Error::CPU {
+                        code: result,
+                        bytes: response,
+                    });
+                }
+            }
+            if response.len() >= 22 && response[21] != 0xFF {
+                return Err(Error::CPU {
+                    code: response[21] as i32,
+                    bytes: response,
+                });
+            }
+            if response.len() < 25 + size as usize {
+                too_large = size;
+            } else {
+                fits = size;
+            }
+        }
+
+        if fits == 0 {
+            return Err(Error::Response {
+                code: error::CLI_SIZE_OVER_PDU,
+                bytes: Vec::new(),
+            });
+        }
+        Ok(fits)
+    }
+
+    /// streams consecutive `(db_number, start, size)` segments as one sequence of chunks,
+    /// each fitting in a single PDU. See `DbRangeReader`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // a ring buffer split over DB100 to DB120
+    /// let segments: Vec<(i32, i32, i32)> = (100..=120).map(|db| (db, 0, 4000)).collect();
+    /// let mut reader = cl.db_range_reader(&segments);
+    ///
+    /// while let Some(chunk) = reader.next() {
+    ///     match chunk {
+    ///         Ok(bytes) => println!("{} bytes", bytes.len()),
+    ///         Err(e) => println!("skipping DB{}: {}", segments[reader.segment()].0, e),
+    ///     }
+    /// }
+    /// ```
+    pub fn db_range_reader(&mut self, segments: &[(i32, i32, i32)]) -> DbRangeReader<'_, T> {
+        // 18 = Reply telegram header
+        let chunk_size = (self.transport.pdu_length() - 18).max(1);
+        DbRangeReader {
+            client: self,
+            segments: segments.to_vec(),
+            chunk_size,
+            current: 0,
+            offset: 0,
+            last: 0,
+        }
+    }
+
+    /// reads a whole data block, its size is taken from the block info.
+    /// Returns the size of the block, only that many bytes of `buffer` are written.
+    /// The buffer can be larger than the block but not smaller.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let buffer = &mut vec![0u8; 65535];
+    /// let size = cl.read_full_db(888, buffer).unwrap();
+    /// println!("DB888 {:?}", &buffer[..size]);
+    /// ```
+    pub fn read_full_db(&mut self, db_number: u16, buffer: &mut [u8]) -> Result<usize, Error> {
+        let info = self.get_ag_block_info(BlockType::DB, db_number)?;
+        let db_size = info.mc7_size as usize;
+
+        if db_size > buffer.len() {
+            return Err(Error::BufferSize {
+                expected: db_size,
+                got: buffer.len(),
+            });
+        }
+
+        self.ag_read(db_number as i32, 0, db_size as i32, &mut buffer[..db_size])?;
+        Ok(db_size)
+    }
+
+    /// same as `read_full_db` but each chunk is written to `sink` as soon as it arrives,
+    /// only a PDU worth of data is held in memory. Returns the number of bytes written.
+    /// A failed read or write leaves the chunks before it in the sink.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io::BufWriter;
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let mut archive = BufWriter::new(File::create("db888.bin").unwrap());
+    /// let size = cl.read_full_db_to(888, &mut archive).unwrap();
+    /// println!("archived {} bytes", size);
+    /// ```
+    pub fn read_full_db_to<W: Write>(
+        &mut self,
+        db_number: u16,
+        sink: &mut W,
+    ) -> Result<usize, Error> {
+        let info = self.get_ag_block_info(BlockType::DB, db_number)?;
+        let db_size = info.mc7_size as i32;
+
+        // 18 = Reply telegram header
+        let mut chunk = vec![0u8; (self.transport.pdu_length() - 18) as usize];
+        let (mut start, mut round_trips) = (0, 0);
+        while start < db_size {
+            let size = (db_size - start).min(chunk.len() as i32);
+            let result = self.ag_read(db_number as i32, start, size, &mut chunk[..size as usize]);
+            round_trips += self.round_trips;
+            self.round_trips = round_trips;
+            result?;
+
+            sink.write_all(&chunk[..size as usize])?;
+            start += size;
+        }
+        Ok(db_size as usize)
+    }
+
+    /// reads a whole data block along with its block info, for backups recording
+    /// the version and dates of the contents. Same round trips as `read_full_db`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let (info, data) = cl.read_full_db_with_info(888).unwrap();
+    /// println!("DB888 of {} saved {}: {:?}", info.author, info.code_date, data);
+    /// ```
+    pub fn read_full_db_with_info(
+        &mut self,
+        db_number: u16,
+    ) -> Result<(S7BlockInfo, Vec<u8>), Error> {
+        let info = self.get_ag_block_info(BlockType::DB, db_number)?;
+        let mut data = vec![0u8; info.mc7_size as usize];
+
+        self.ag_read(db_number as i32, 0, data.len() as i32, &mut data)?;
+        Ok((info, data))
+    }
+
+    /// reads a set of named addresses of a data block and returns the raw bytes of each name,
+    /// for tag lists that are only known at runtime. Neighbouring addresses are read together,
+    /// so a tight mapping costs about as many requests as reading its whole span.
+    /// A bit address returns the byte holding it. Names have to be unique.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use s7::field::{Bool, Field, Float};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let fields = vec![
+    ///     ("speed".to_string(), "DBD8".parse().unwrap()),
+    ///     ("lights".to_string(), "DBX12.4".parse().unwrap()),
+    /// ];
+    /// let values = cl.read_map(888, &fields).unwrap();
+    ///
+    /// let speed = Float::new(888, 8.0, values["speed"].clone()).unwrap();
+    /// let lights = Bool::new(888, 12.4, values["lights"].clone()).unwrap();
+    /// println!("{} {}", speed.value(), lights.value());
+    /// ```
+    pub fn read_map(
+        &mut self,
+        db_number: i32,
+        fields: &[(String, Address)],
+    ) -> Result<HashMap<String, Vec<u8>>, Error> {
+        let mut names = HashSet::with_capacity(fields.len());
+        for (name, address) in fields.iter() {
+            if !names.insert(name) {
+                return Err(Error::InvalidInput {
+                    input: format!("duplicate field name {}", name),
+                });
+            }
+            if address.area != constant::Area::DataBausteine
+                || (address.db_number != 0 && address.db_number != db_number)
+            {
+                return Err(Error::InvalidInput {
+                    input: format!("{} at {} is not in DB{}", name, address, db_number),
+                });
+            }
+        }
+
+        let ranges: Vec<(&str, i32, i32)> = fields
+            .iter()
+            .map(|(name, address)| (name.as_str(), address.start, address.size()))
+            .collect();
+        self.read_ranges(db_number, &ranges)
+    }
+
+    /// reads the named `(name, start, size)` ranges of a data block,
+    /// coalescing the ones close to each other into a single read
+    fn read_ranges(
+        &mut self,
+        db_number: i32,
+        fields: &[(&str, i32, i32)],
+    ) -> Result<HashMap<String, Vec<u8>>, Error> {
+        let mut ranges: Vec<(i32, i32)> = fields
+            .iter()
+            .map(|(_, start, size)| (*start, start + size))
+            .collect();
+        ranges.sort();
+
+        // a gap shorter than a reply header is cheaper to read than to skip with a new request
+        let mut spans: Vec<(i32, i32)> = Vec::new();
+        for (start, end) in ranges {
+            match spans.last_mut() {
+                Some(last) if start <= last.1 + 18 => last.1 = last.1.max(end),
+                _ => spans.push((start, end)),
+            }
+        }
+
+        let mut values = HashMap::with_capacity(fields.len());
+        for (start, end) in spans {
+            let mut buffer = vec![0u8; (end - start) as usize];
+            self.ag_read(db_number, start, end - start, &mut buffer)?;
+
+            for (name, field_start, size) in fields.iter() {
+                if *field_start >= start && *field_start < end {
+                    let offset = (field_start - start) as usize;
+                    let bytes = buffer[offset..offset + *size as usize].to_vec();
+                    values.insert(name.to_string(), bytes);
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    /// reads every variable of `layout` and decodes it by its kind, keyed by name.
+    /// The reads are coalesced like `read_map`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use s7::field::Value;
+    /// use s7::layout::DbLayout;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let csv = std::fs::read_to_string("db888.csv").unwrap();
+    /// let layout = DbLayout::from_csv(888, &csv).unwrap();
+    ///
+    /// for (name, value) in cl.read_layout(&layout).unwrap() {
+    ///     if let Value::Real(real) = value {
+    ///         println!("{} {}", name, real);
+    ///     }
+    /// }
+    /// ```
+    pub fn read_layout(&mut self, layout: &DbLayout) -> Result<HashMap<String, Value>, Error> {
+        // a STRING is longer than its byte address
+        let fields: Vec<(&str, i32, i32)> = layout
+            .entries()
+            .iter()
+            .map(|(name, address, kind)| (name.as_str(), address.start, kind.size()))
+            .collect();
+        let bytes = self.read_ranges(layout.db_number(), &fields)?;
+
+        let mut values = HashMap::with_capacity(fields.len());
+        for (name, address, kind) in layout.entries().iter() {
+            values.insert(name.clone(), kind.decode(&bytes[name], address.bit)?);
+        }
+        Ok(values)
+    }
+
+    /// reads the variable at `address` as a `kind` only known at runtime.
+    /// A `Bool` needs a bit address, other kinds start at the byte of the address
+    /// whatever its width, so `DBB10` read as `STRING[20]` reads 22 bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // from a configuration file
+    /// let (address, kind) = ("DB888.DBD8", "REAL");
+    /// let value = cl.read_value(&address.parse().unwrap(), kind.parse().unwrap()).unwrap();
+    /// println!("{} = {:?}", address, value);
+    /// ```
+    pub fn read_value(&mut self, address: &Address, kind: Kind) -> Result<Value, Error> {
+        let bit = address.word_len == constant::WL_BIT;
+        if bit != (kind == Kind::Bool)
+            || matches!(
+                address.area,
+                constant::Area::Counter | constant::Area::Timer
+            )
+        {
+            return Err(Error::InvalidInput {
+                input: format!("{} can't hold a {:?}", address, kind),
+            });
+        }
+
+        let mut buffer = vec![0u8; kind.size() as usize];
+        self.read(
+            address.area,
+            address.db_number,
+            address.start,
+            kind.size(),
+            constant::WL_BYTE,
+            &mut buffer,
+        )?;
+        kind.decode(&buffer, address.bit)
+    }
+
+    /// # Examples
     ///
     /// ```no_run
     /// use std::net::{Ipv4Addr, IpAddr};
@@ -210,17 +1437,17 @@ impl<T: Transport> Client<T> {
     ///
     /// let buffer = &mut vec![0u8; 255];
     ///
-    /// cl.eb_read(1, 3, buffer).unwrap();
+    /// cl.mb_read(1, 3, buffer).unwrap();
     /// ```
-    pub fn eb_read(&mut self, start: i32, size: i32, buffer: &mut Vec<u8>) -> Result<(), Error> {
-        return self.read(
-            Area::ProcessInput,
+    pub fn mb_read(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read(
+            constant::Area::Merker,
             0,
             start,
             size,
             constant::WL_BYTE,
             buffer,
-        );
+        )
     }
 
     /// # Examples
@@ -242,17 +1469,17 @@ impl<T: Transport> Client<T> {
     ///
     /// let buffer = &mut vec![0u8; 255];
     ///
-    /// cl.eb_write(1, 3, buffer).unwrap();
+    /// cl.mb_write(1, 3, buffer).unwrap();
     /// ```
-    pub fn eb_write(&mut self, start: i32, size: i32, buffer: &mut Vec<u8>) -> Result<(), Error> {
-        return self.write(
-            Area::ProcessInput,
+    pub fn mb_write(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.write(
+            constant::Area::Merker,
             0,
             start,
             size,
             constant::WL_BYTE,
             buffer,
-        );
+        )
     }
 
     /// # Examples
@@ -274,17 +1501,17 @@ impl<T: Transport> Client<T> {
     ///
     /// let buffer = &mut vec![0u8; 255];
     ///
-    /// cl.ab_read(1, 3, buffer).unwrap();
+    /// cl.eb_read(1, 3, buffer).unwrap();
     /// ```
-    pub fn ab_read(&mut self, start: i32, size: i32, buffer: &mut Vec<u8>) -> Result<(), Error> {
-        return self.read(
-            Area::ProcessOutput,
+    pub fn eb_read(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read(
+            constant::Area::ProcessInput,
             0,
             start,
             size,
             constant::WL_BYTE,
             buffer,
-        );
+        )
     }
 
     /// # Examples
@@ -306,514 +1533,5621 @@ impl<T: Transport> Client<T> {
     ///
     /// let buffer = &mut vec![0u8; 255];
     ///
-    /// cl.ab_write(1, 3, buffer).unwrap();
+    /// cl.eb_write(1, 3, buffer).unwrap();
     /// ```
-    pub fn ab_write(&mut self, start: i32, size: i32, buffer: &mut Vec<u8>) -> Result<(), Error> {
-        return self.write(
-            Area::ProcessOutput,
+    pub fn eb_write(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.write(
+            constant::Area::ProcessInput,
             0,
             start,
             size,
             constant::WL_BYTE,
             buffer,
-        );
+        )
     }
 
-    //read generic area, pass result into a buffer
-    fn read(
-        &mut self,
-        area: Area,
-        db_number: i32,
-        mut start: i32,
-        mut amount: i32,
-        mut word_len: i32,
-        buffer: &mut Vec<u8>,
-    ) -> Result<(), Error> {
-        // Some adjustment
-        match area {
-            Area::Counter => word_len = constant::WL_COUNTER,
-            Area::Timer => word_len = constant::WL_TIMER,
-            _ => {}
-        };
-
-        // Calc Word size
-        let mut word_size = constant::data_size_byte(word_len);
-
-        if word_size == 0 {
-            return Err(Error::Response {
-                code: error::ISO_INVALID_DATA_SIZE,
-            });
-        }
-
-        if word_len == constant::WL_BIT {
-            amount = 1; // Only 1 bit can be transferred at time
-        } else {
-            if word_len != constant::WL_COUNTER && word_len != constant::WL_TIMER {
-                amount = amount * word_size;
-                word_size = 1;
-                word_len = constant::WL_BYTE;
-            }
-        }
-
-        let pdu_length = self.transport.pdu_length();
-
-        if pdu_length == 0 {
-            return Err(Error::PduLength(pdu_length));
-        }
-
-        let max_elements = (pdu_length - 18) / word_size; // 18 = Reply telegram header //lth note here
-
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use std::time::Duration;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    ///
+    /// opts.read_timeout = Duration::from_secs(2);
+    /// opts.write_timeout = Duration::from_secs(2);
+    ///
+    ///
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let buffer = &mut vec![0u8; 255];
+    ///
+    /// cl.ab_read(1, 3, buffer).unwrap();
+    /// ```
+    pub fn ab_read(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read(
+            constant::Area::ProcessOutput,
+            0,
+            start,
+            size,
+            constant::WL_BYTE,
+            buffer,
+        )
+    }
+
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use std::time::Duration;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    ///
+    /// opts.read_timeout = Duration::from_secs(2);
+    /// opts.write_timeout = Duration::from_secs(2);
+    ///
+    ///
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let buffer = &mut vec![0u8; 255];
+    ///
+    /// cl.ab_write(1, 3, buffer).unwrap();
+    /// ```
+    pub fn ab_write(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.write(
+            constant::Area::ProcessOutput,
+            0,
+            start,
+            size,
+            constant::WL_BYTE,
+            buffer,
+        )
+    }
+
+    /// sets or clears a single bit of a data block with a bit write,
+    /// the other bits of its byte are left as they are in the PLC
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // DB888.DBX12.4
+    /// cl.db_set_bit(888, 12, 4, true).unwrap();
+    /// ```
+    pub fn db_set_bit(
+        &mut self,
+        db_number: i32,
+        byte: i32,
+        bit: u8,
+        value: bool,
+    ) -> Result<(), Error> {
+        self.set_bit(constant::Area::DataBausteine, db_number, byte, bit, value)
+    }
+
+    /// sets or clears a single bit of the markers, see `db_set_bit`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // handshake on M10.3
+    /// cl.m_set_bit(10, 3, true).unwrap();
+    /// ```
+    pub fn m_set_bit(&mut self, byte: i32, bit: u8, value: bool) -> Result<(), Error> {
+        self.set_bit(constant::Area::Merker, 0, byte, bit, value)
+    }
+
+    /// sets or clears a single bit of the process image of the inputs, see `db_set_bit`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // simulate I0.1
+    /// cl.e_set_bit(0, 1, true).unwrap();
+    /// ```
+    pub fn e_set_bit(&mut self, byte: i32, bit: u8, value: bool) -> Result<(), Error> {
+        self.set_bit(constant::Area::ProcessInput, 0, byte, bit, value)
+    }
+
+    /// sets or clears a single bit of the process image of the outputs, see `db_set_bit`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // Q4.0 off
+    /// cl.a_set_bit(4, 0, false).unwrap();
+    /// ```
+    pub fn a_set_bit(&mut self, byte: i32, bit: u8, value: bool) -> Result<(), Error> {
+        self.set_bit(constant::Area::ProcessOutput, 0, byte, bit, value)
+    }
+
+    fn set_bit(
+        &mut self,
+        area: constant::Area,
+        db_number: i32,
+        byte: i32,
+        bit: u8,
+        value: bool,
+    ) -> Result<(), Error> {
+        if bit > 7 || byte < 0 {
+            return Err(Error::InvalidInput {
+                input: format!("bit {}.{}", byte, bit),
+            });
+        }
+        self.write(
+            area,
+            db_number,
+            byte * 8 + bit as i32,
+            1,
+            constant::WL_BIT,
+            &[value as u8],
+        )
+    }
+
+    /// reads `size` input words of the process image starting at byte `start`,
+    /// the buffer holds them big endian
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // IW64 and IW66, two analog inputs
+    /// let buffer = &mut [0u8; 4];
+    /// cl.ew_read(64, 2, buffer).unwrap();
+    /// println!("{} {}", i16::from_be_bytes([buffer[0], buffer[1]]), i16::from_be_bytes([buffer[2], buffer[3]]));
+    /// ```
+    pub fn ew_read(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read(
+            constant::Area::ProcessInput,
+            0,
+            start,
+            size,
+            constant::WL_WORD,
+            buffer,
+        )
+    }
+
+    /// reads `size` output words of the process image starting at byte `start`,
+    /// the buffer holds them big endian
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // QW64
+    /// let buffer = &mut [0u8; 2];
+    /// cl.aw_read(64, 1, buffer).unwrap();
+    /// ```
+    pub fn aw_read(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read(
+            constant::Area::ProcessOutput,
+            0,
+            start,
+            size,
+            constant::WL_WORD,
+            buffer,
+        )
+    }
+
+    /// reads `size` input double words of the process image starting at byte `start`,
+    /// the buffer holds them big endian
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // ID100
+    /// let buffer = &mut [0u8; 4];
+    /// cl.ed_read(100, 1, buffer).unwrap();
+    /// println!("{}", u32::from_be_bytes(*buffer));
+    /// ```
+    pub fn ed_read(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read(
+            constant::Area::ProcessInput,
+            0,
+            start,
+            size,
+            constant::WL_DWORD,
+            buffer,
+        )
+    }
+
+    /// reads `size` output double words of the process image starting at byte `start`,
+    /// the buffer holds them big endian
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // QD100
+    /// let buffer = &mut [0u8; 4];
+    /// cl.ad_read(100, 1, buffer).unwrap();
+    /// ```
+    pub fn ad_read(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read(
+            constant::Area::ProcessOutput,
+            0,
+            start,
+            size,
+            constant::WL_DWORD,
+            buffer,
+        )
+    }
+
+    /// reads directly from the input modules, bypassing the process image,
+    /// for analog values more recent than the last cycle
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // PIW256
+    /// let buffer = &mut [0u8; 2];
+    /// cl.pe_read(256, 2, buffer).unwrap();
+    /// ```
+    pub fn pe_read(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read(
+            constant::Area::DirectPeripheral,
+            0,
+            start,
+            size,
+            constant::WL_BYTE,
+            buffer,
+        )
+    }
+
+    /// writes directly to the output modules, bypassing the process image
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // PQW256
+    /// cl.pe_write(256, 2, &mut [0x6C, 0x00]).unwrap();
+    /// ```
+    pub fn pe_write(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.write(
+            constant::Area::DirectPeripheral,
+            0,
+            start,
+            size,
+            constant::WL_BYTE,
+            buffer,
+        )
+    }
+
+    /// read counters, `size` is the number of counters
+    /// and the buffer needs 2 bytes for each of them.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use std::time::Duration;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    ///
+    /// opts.read_timeout = Duration::from_secs(2);
+    /// opts.write_timeout = Duration::from_secs(2);
+    ///
+    ///
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let buffer = &mut vec![0u8; 2 * 3];
+    ///
+    /// cl.ct_read(1, 3, buffer).unwrap();
+    /// ```
+    pub fn ct_read(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read(
+            constant::Area::Counter,
+            0,
+            start,
+            size,
+            constant::WL_COUNTER,
+            buffer,
+        )
+    }
+
+    /// write counters, `size` is the number of counters
+    /// and the buffer needs 2 bytes for each of them.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use std::time::Duration;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    ///
+    /// opts.read_timeout = Duration::from_secs(2);
+    /// opts.write_timeout = Duration::from_secs(2);
+    ///
+    ///
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let buffer = &mut vec![0u8; 2 * 3];
+    ///
+    /// cl.ct_write(1, 3, buffer).unwrap();
+    /// ```
+    pub fn ct_write(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.write(
+            constant::Area::Counter,
+            0,
+            start,
+            size,
+            constant::WL_COUNTER,
+            buffer,
+        )
+    }
+
+    /// read timers, `size` is the number of timers
+    /// and the buffer needs 2 bytes for each of them.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use std::time::Duration;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    ///
+    /// opts.read_timeout = Duration::from_secs(2);
+    /// opts.write_timeout = Duration::from_secs(2);
+    ///
+    ///
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let buffer = &mut vec![0u8; 2 * 3];
+    ///
+    /// cl.tm_read(1, 3, buffer).unwrap();
+    /// ```
+    pub fn tm_read(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read(
+            constant::Area::Timer,
+            0,
+            start,
+            size,
+            constant::WL_TIMER,
+            buffer,
+        )
+    }
+
+    /// write timers, `size` is the number of timers
+    /// and the buffer needs 2 bytes for each of them.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use std::time::Duration;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let mut opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    ///
+    /// opts.read_timeout = Duration::from_secs(2);
+    /// opts.write_timeout = Duration::from_secs(2);
+    ///
+    ///
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let buffer = &mut vec![0u8; 2 * 3];
+    ///
+    /// cl.tm_write(1, 3, buffer).unwrap();
+    /// ```
+    pub fn tm_write(&mut self, start: i32, size: i32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.write(
+            constant::Area::Timer,
+            0,
+            start,
+            size,
+            constant::WL_TIMER,
+            buffer,
+        )
+    }
+
+    /// reads and decodes the first `count` counters, see `field::counter_value`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 0, 2, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// for (i, value) in cl.read_all_counters(256).unwrap().iter().enumerate() {
+    ///     println!("C{} = {}", i, value);
+    /// }
+    /// ```
+    pub fn read_all_counters(&mut self, count: i32) -> Result<Vec<u16>, Error> {
+        let mut buffer = vec![0u8; count.max(0) as usize * 2];
+        self.ct_read(0, count, &mut buffer)?;
+        buffer
+            .chunks_exact(2)
+            .map(|word| field::counter_value(field::get_word(word)?))
+            .collect()
+    }
+
+    /// reads and decodes the first `count` timers, see `field::s5time`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 0, 2, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// for (i, value) in cl.read_all_timers(128).unwrap().iter().enumerate() {
+    ///     println!("T{} = {:?}", i, value);
+    /// }
+    /// ```
+    pub fn read_all_timers(&mut self, count: i32) -> Result<Vec<Duration>, Error> {
+        let mut buffer = vec![0u8; count.max(0) as usize * 2];
+        self.tm_read(0, count, &mut buffer)?;
+        buffer
+            .chunks_exact(2)
+            .map(|word| field::s5time(field::get_word(word)?))
+            .collect()
+    }
+
+    /// reads `count` consecutive words of a data block starting at byte `start`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 0, 2, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // DB888.DBW0 to DBW30
+    /// for (i, status) in cl.read_words(888, 0, 16).unwrap().iter().enumerate() {
+    ///     println!("DBW{} = {:#06x}", i * 2, status);
+    /// }
+    /// ```
+    pub fn read_words(
+        &mut self,
+        db_number: i32,
+        start: i32,
+        count: usize,
+    ) -> Result<Vec<u16>, Error> {
+        let amount = word_count(count)?;
+        let mut buffer = vec![0u8; count * 2];
+        self.read(
+            constant::Area::DataBausteine,
+            db_number,
+            start,
+            amount,
+            constant::WL_WORD,
+            &mut buffer,
+        )?;
+        Ok(buffer.chunks_exact(2).map(BigEndian::read_u16).collect())
+    }
+
+    /// writes consecutive words to a data block starting at byte `start`,
+    /// the inverse of `read_words`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 0, 2, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// cl.write_words(888, 0, &[0x0001, 0x8000]).unwrap();
+    /// ```
+    pub fn write_words(&mut self, db_number: i32, start: i32, words: &[u16]) -> Result<(), Error> {
+        let amount = word_count(words.len())?;
+        let mut buffer = vec![0u8; words.len() * 2];
+        BigEndian::write_u16_into(words, &mut buffer);
+        self.write(
+            constant::Area::DataBausteine,
+            db_number,
+            start,
+            amount,
+            constant::WL_WORD,
+            &buffer,
+        )
+    }
+
+    //read generic area, pass result into a buffer
+    fn read(
+        &mut self,
+        area: constant::Area,
+        db_number: i32,
+        mut start: i32,
+        mut amount: i32,
+        mut word_len: i32,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        // Some adjustment
+        match area {
+            constant::Area::Counter => word_len = constant::WL_COUNTER,
+            constant::Area::Timer => word_len = constant::WL_TIMER,
+            _ => {}
+        };
+
+        // Calc Word size
+        let mut word_size = constant::data_size_byte(word_len);
+
+        if word_size == 0 {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_DATA_SIZE,
+                bytes: Vec::new(),
+            });
+        }
+        check_address(area, word_len, db_number).map_err(|input| Error::InvalidInput { input })?;
+        check_range(word_len, start, amount).map_err(|input| Error::InvalidInput { input })?;
+        check_buffer(word_len, amount, buffer.len())?;
+
+        // nothing to transfer, no request is sent
+        if amount == 0 {
+            self.round_trips = 0;
+            return Ok(());
+        }
+        self.check_running()?;
+
+        if word_len == constant::WL_BIT {
+            amount = 1; // Only 1 bit can be transferred at time
+        } else {
+            if word_len != constant::WL_COUNTER && word_len != constant::WL_TIMER {
+                amount *= word_size;
+                word_size = 1;
+                word_len = constant::WL_BYTE;
+            }
+        }
+
+        let pdu_length = self.transport.pdu_length();
+
+        if pdu_length == 0 {
+            return Err(Error::PduLength(pdu_length));
+        }
+
+        let mut max_elements = (pdu_length - 18) / word_size; // 18 = Reply telegram header //lth note here
+
+        let mut tot_elements = amount;
+        let mut offset = 0;
+        let started = Instant::now();
+        self.round_trips = 0;
+        let mut renegotiated = false;
+
+        while tot_elements > 0 {
+            self.check_operation_timeout(started)?;
+            let mut num_elements = tot_elements;
+
+            if num_elements > max_elements {
+                num_elements = max_elements;
+            }
+
+            let size_requested = num_elements * word_size;
+            let mut request = read_request(area, db_number, start, word_len, num_elements);
+
+            let result = self.send_job(request.as_mut_slice());
+            self.round_trips += 1;
+
+            match result {
+                Ok(response) => {
+                    if self.renegotiate_pdu(&response, &mut renegotiated)? {
+                        max_elements = (self.transport.pdu_length() - 18) / word_size;
+                        continue;
+                    }
+                    if pdu_size_refused(&response) {
+                        return Err(Error::CPU {
+                            code: error::CODE_7_DATA_OVER_PDU,
+                            bytes: response,
+                        });
+                    }
+                    if response.len() < 25 {
+                        return Err(Error::Response {
+                            code: error::ISO_INVALID_DATA_SIZE,
+                            bytes: response.to_vec(),
+                        });
+                    }
+
+                    if response[21] != 0xFF {
+                        return Err(Error::CPU {
+                            code: response[21] as i32,
+                            bytes: response.to_vec(),
+                        });
+                    }
+                    let size = size_requested as usize;
+                    if response.len() < 25 + size {
+                        return Err(Error::Response {
+                            code: error::ISO_INVALID_DATA_SIZE,
+                            bytes: response.to_vec(),
+                        });
+                    }
+                    buffer[offset..offset + size].copy_from_slice(&response[25..25 + size]);
+                    offset += size;
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+
+            tot_elements -= num_elements;
+            start += num_elements * word_size
+        }
+        Ok(())
+    }
+
+    /// reads up to `transport::MAX_VARS` items with a single request.
+    /// The data of each item ends up in its `buffer`, which keeps its capacity
+    /// between calls, items the PLC couldn't read get their `err` set instead.
+    /// Unlike `read`, the word length of each item is sent as is and its `size`
+    /// counts elements of that length: 3 `WL_WORD` fill the buffer with 6 bytes.
+    /// The request and the answer both have to fit in the negotiated PDU,
+    /// see `MultiRead` to read any number of values.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use s7::client::S7DataItem;
+    /// use s7::constant::{Area, WL_BYTE, WL_REAL};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let mut items = [
+    ///     // MB10
+    ///     S7DataItem::new(Area::Merker, WL_BYTE, 0, 10, 1),
+    ///     // DB888.DBD8
+    ///     S7DataItem::new(Area::DataBausteine, WL_REAL, 888, 8, 1),
+    /// ];
+    ///
+    /// cl.read_multi_vars(&mut items).unwrap();
+    /// for item in items.iter() {
+    ///     match &item.err {
+    ///         None => println!("{:?}", item.buffer),
+    ///         Some(e) => println!("{}", e),
+    ///     }
+    /// }
+    /// ```
+    pub fn read_multi_vars(&mut self, items: &mut [S7DataItem]) -> Result<(), Error> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        if items.len() > transport::MAX_VARS {
+            return Err(Error::InvalidInput {
+                input: format!(
+                    "{} items, at most {} per request",
+                    items.len(),
+                    transport::MAX_VARS
+                ),
+            });
+        }
+        for (i, item) in items.iter().enumerate() {
+            check_address(item.area, item.word_len, item.db_number).map_err(|reason| {
+                Error::InvalidInput {
+                    input: format!("item {}: {}", i, reason),
+                }
+            })?;
+            if item.size < 1 {
+                return Err(Error::InvalidInput {
+                    input: format!("item {}: size {}", i, item.size),
+                });
+            }
+        }
+
+        let (request_size, response_size) = multi_read_sizes(items.iter());
+        let max_size = request_size.max(response_size);
+        if max_size > self.transport.pdu_length() as usize {
+            return Err(Error::Response {
+                code: error::CLI_SIZE_OVER_PDU,
+                bytes: Vec::new(),
+            });
+        }
+
+        let mut request = build_multi_read_request(items)?;
+        self.check_running()?;
+
+        let mut response = self.send_job(request.as_mut_slice())?;
+        if self.renegotiate_pdu(&response, &mut false)? {
+            if max_size > self.transport.pdu_length() as usize {
+                return Err(Error::Response {
+                    code: error::CLI_SIZE_OVER_PDU,
+                    bytes: Vec::new(),
+                });
+            }
+            response = self.send_job(request.as_mut_slice())?;
+        }
+
+        if response.len() < transport::READ_MULTI_MIN_RESPONSE {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+                bytes: response.to_vec(),
+            });
+        }
+        let result = BigEndian::read_u16(response[17..].as_ref());
+        if result != 0 {
+            return Err(Error::CPU {
+                code: result as i32,
+                bytes: response.to_vec(),
+            });
+        }
+        if response[20] as usize != items.len() {
+            return Err(Error::InvalidResponse {
+                reason: format!("{} items for {} requested", response[20], items.len()),
+                bytes: response,
+            });
+        }
+
+        let mut offset = transport::READ_MULTI_MIN_RESPONSE;
+        for item in items.iter_mut() {
+            let (code, data, next) =
+                data_item(response.as_ref(), offset).map_err(|reason| Error::InvalidResponse {
+                    reason,
+                    bytes: response.to_vec(),
+                })?;
+            offset = next;
+
+            if code != 0xFF {
+                item.buffer.clear();
+                item.err = Some(Error::CPU {
+                    code: code as i32,
+                    bytes: response.to_vec(),
+                });
+                continue;
+            }
+
+            let (word_len, num_elements) = item.transfer();
+            let requested = (num_elements * constant::data_size_byte(word_len)) as usize;
+            if data.len() > requested {
+                return Err(Error::InvalidResponse {
+                    reason: format!("item of {} bytes for {} requested", data.len(), requested),
+                    bytes: response.to_vec(),
+                });
+            }
+
+            // refilled in place, polling the same items doesn't allocate once warmed up
+            item.buffer.clear();
+            item.buffer.extend_from_slice(data);
+            item.err = None;
+        }
+        Ok(())
+    }
+
+    /// reads many bits with byte items covering their bytes instead of one item per bit.
+    /// Bits of the same area and block close to each other share an item spanning their
+    /// bytes, so 64 bits of the same DB cost a single item of at most their byte range.
+    /// Items are kept within the PDU and sent in as few requests as fit.
+    /// Every address has to be a bit address, the results keep their order.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use s7::address::Address;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let bits: Vec<Address> = (0..64)
+    ///     .map(|i| format!("DB888.DBX{}.{}", i / 8, i % 8).parse().unwrap())
+    ///     .collect();
+    /// for (address, value) in cl.read_bits(&bits).unwrap() {
+    ///     println!("{} {}", address, value);
+    /// }
+    /// ```
+    pub fn read_bits(&mut self, bits: &[Address]) -> Result<Vec<(Address, bool)>, Error> {
+        let mut bytes: Vec<(constant::Area, i32, i32)> = Vec::with_capacity(bits.len());
+        for address in bits.iter() {
+            if address.word_len != constant::WL_BIT {
+                return Err(Error::InvalidInput {
+                    input: format!("{} is not a bit address", address),
+                });
+            }
+            check_db_number(address)?;
+            bytes.push((address.area, address.db_number, address.start));
+        }
+        bytes.sort_by_key(|(area, db_number, start)| (*area as u8, *db_number, *start));
+        bytes.dedup();
+
+        // data of a single item answered in a PDU, items are padded to even sizes
+        let header = transport::READ_MULTI_MIN_RESPONSE - 7 + 4;
+        let pdu_length = self.transport.pdu_length();
+        let max_size = (pdu_length as usize).saturating_sub(header) as i32 & !1;
+        if max_size < 1 {
+            return Err(Error::PduTooSmall(pdu_length));
+        }
+
+        // area, block and byte range of each item, a gap shorter than
+        // an item header is cheaper to read than to skip with a new item
+        let mut spans: Vec<(constant::Area, i32, i32, i32)> = Vec::new();
+        for (area, db_number, start) in bytes {
+            match spans.last_mut() {
+                Some(last)
+                    if last.0 == area
+                        && last.1 == db_number
+                        && start - last.3 <= transport::READ_ITEM_SIZE as i32
+                        && start - last.2 < max_size =>
+                {
+                    last.3 = start
+                }
+                _ => spans.push((area, db_number, start, start)),
+            }
+        }
+
+        let mut items: Vec<S7DataItem> = spans
+            .iter()
+            .map(|(area, db_number, first, last)| {
+                S7DataItem::new(
+                    *area,
+                    constant::WL_BYTE,
+                    *db_number,
+                    *first,
+                    last - first + 1,
+                )
+            })
+            .collect();
+        self.read_multi_vars_batched(&mut items)?;
+        for item in items.iter_mut() {
+            if let Some(e) = item.err.take() {
+                return Err(e);
+            }
+        }
+
+        bits.iter()
+            .map(|address| {
+                let item = items
+                    .iter()
+                    .find(|item| {
+                        item.area == address.area
+                            && item.db_number == address.db_number
+                            && (item.start..item.start + item.size).contains(&address.start)
+                    })
+                    .expect("every address has an item");
+                let offset = (address.start - item.start) as usize;
+                let bytes = item.buffer.get(offset..).unwrap_or(&[]);
+                Ok((*address, field::get_bool(bytes, address.bit)?))
+            })
+            .collect()
+    }
+
+    /// `read_multi_vars` over as many requests as the PDU length and `MAX_VARS` require,
+    /// each request takes the most items that fit
+    fn read_multi_vars_batched(&mut self, items: &mut [S7DataItem]) -> Result<(), Error> {
+        let pdu_length = self.transport.pdu_length() as usize;
+
+        let mut start = 0;
+        while start < items.len() {
+            // largest batch fitting in a request
+            let mut end = start + 1;
+            while end < items.len() && end - start < transport::MAX_VARS {
+                let (request, response) = multi_read_sizes(items[start..=end].iter());
+                if request.max(response) > pdu_length {
+                    break;
+                }
+                end += 1;
+            }
+            self.read_multi_vars(&mut items[start..end])?;
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// reads several items keeping as many requests in flight as the PLC allows
+    /// (the negotiated max AMQ calling), instead of waiting for each answer.
+    /// On high latency links this saves most of the round trips.
+    ///
+    /// Each item has to fit in a single PDU. The results are in the same order as the items,
+    /// the outer error is for failures of the connection itself.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use s7::client::S7DataItem;
+    /// use s7::constant::Area;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let items: Vec<S7DataItem> = (0..20)
+    ///     .map(|i| S7DataItem::new(Area::DataBausteine, 0x02, 888, i * 10, 10))
+    ///     .collect();
+    ///
+    /// for result in cl.read_pipelined(&items).unwrap() {
+    ///     println!("{:?}", result);
+    /// }
+    /// ```
+    pub fn read_pipelined(
+        &mut self,
+        items: &[S7DataItem],
+    ) -> Result<Vec<Result<Vec<u8>, Error>>, Error> {
+        let pdu_length = self.transport.pdu_length();
+        if pdu_length == 0 {
+            return Err(Error::PduLength(pdu_length));
+        }
+        if items.len() > u16::MAX as usize {
+            return Err(Error::InvalidInput {
+                input: format!("{} pipelined items", items.len()),
+            });
+        }
+        self.check_running()?;
+
+        let mut results: Vec<Option<Result<Vec<u8>, Error>>> = Vec::with_capacity(items.len());
+        let mut requests = Vec::with_capacity(items.len());
+        // reference, index of the item and size of each request
+        let mut pending = Vec::with_capacity(items.len());
+
+        for (i, item) in items.iter().enumerate() {
+            if let Err(reason) = check_address(item.area, item.word_len, item.db_number) {
+                results.push(Some(Err(Error::InvalidInput {
+                    input: format!("item {}: {}", i, reason),
+                })));
+                continue;
+            }
+            let mut word_len = match item.area {
+                constant::Area::Counter => constant::WL_COUNTER,
+                constant::Area::Timer => constant::WL_TIMER,
+                _ => item.word_len,
+            };
+            let word_size = constant::data_size_byte(word_len);
+            let mut num_elements = item.size;
+
+            if word_len == constant::WL_BIT {
+                num_elements = 1;
+            } else if word_len != constant::WL_COUNTER && word_len != constant::WL_TIMER {
+                num_elements *= word_size;
+                word_len = constant::WL_BYTE;
+            }
+
+            let size = num_elements * constant::data_size_byte(word_len);
+            if word_size == 0 || num_elements < 1 {
+                results.push(Some(Err(Error::Response {
+                    code: error::ISO_INVALID_DATA_SIZE,
+                    bytes: Vec::new(),
+                })));
+                continue;
+            }
+            if size > pdu_length - 18 {
+                results.push(Some(Err(Error::Response {
+                    code: error::CLI_SIZE_OVER_PDU,
+                    bytes: Vec::new(),
+                })));
+                continue;
+            }
+
+            let mut request = read_request(
+                item.area,
+                item.db_number,
+                item.start,
+                word_len,
+                num_elements,
+            );
+            // PDU reference, echoed back by the PLC
+            let reference = self.next_pdu_ref();
+            BigEndian::write_u16(request[11..].as_mut(), reference);
+            requests.push(request);
+            pending.push((reference, i, size as usize));
+            results.push(None);
+        }
+
+        let max_outstanding = self.transport.negotiation_info().max_amq_calling as usize;
+        let responses = self
+            .transport
+            .send_pipelined(requests.as_slice(), max_outstanding)?;
+
+        for response in responses {
+            if response.len() < 25 {
+                return Err(Error::Response {
+                    code: error::ISO_INVALID_DATA_SIZE,
+                    bytes: response.to_vec(),
+                });
+            }
+
+            let reference = BigEndian::read_u16(response[11..].as_ref());
+            let (index, size) = match pending.iter().find(|(r, _, _)| *r == reference) {
+                Some((_, i, size)) => (*i, *size),
+                None => {
+                    return Err(Error::InvalidResponse {
+                        reason: format!("unexpected pdu reference {}", reference),
+                        bytes: response,
+                    })
+                }
+            };
+
+            results[index] = Some(if response[21] != 0xFF {
+                Err(Error::CPU {
+                    code: response[21] as i32,
+                    bytes: response.to_vec(),
+                })
+            } else if response.len() < 25 + size {
+                Err(Error::Response {
+                    code: error::ISO_INVALID_DATA_SIZE,
+                    bytes: response.to_vec(),
+                })
+            } else {
+                Ok(response[25..25 + size].to_vec())
+            });
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|result| {
+                result.unwrap_or_else(|| {
+                    Err(Error::InvalidResponse {
+                        reason: "no response received".to_string(),
+                        bytes: Vec::new(),
+                    })
+                })
+            })
+            .collect())
+    }
+
+    /// writes like the area functions and records every request it took,
+    /// with the elements it carried and the return code of the PLC.
+    /// Meant for audit trails of writes that can't be taken on faith.
+    /// Fails without a report when nothing was sent, invalid input for instance,
+    /// once sending started the report is returned with `error` set if a chunk failed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use s7::constant::{Area, WL_BYTE};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let setpoints = vec![0u8; 1000];
+    /// let report = cl
+    ///     .write_reported(Area::DataBausteine, 888, 0, 1000, WL_BYTE, &setpoints)
+    ///     .unwrap();
+    /// for chunk in report.chunks.iter() {
+    ///     println!("{} elements at {}: {:#04x}", chunk.elements, chunk.start, chunk.result);
+    /// }
+    /// if let Some(e) = &report.error {
+    ///     println!("only {} of 1000 written: {}", report.elements_written(), e);
+    /// }
+    /// ```
+    pub fn write_reported(
+        &mut self,
+        area: constant::Area,
+        db_number: i32,
+        start: i32,
+        amount: i32,
+        word_len: i32,
+        buffer: &[u8],
+    ) -> Result<WriteReport, Error> {
+        let mut report = WriteReport::default();
+        self.round_trips = 0;
+        let result = self.write_chunks(
+            area,
+            db_number,
+            start,
+            amount,
+            word_len,
+            buffer,
+            Some(&mut report.chunks),
+        );
+        match result {
+            Err(e) if self.round_trips == 0 => Err(e),
+            result => {
+                report.error = result.err();
+                Ok(report)
+            }
+        }
+    }
+
+    fn write(
+        &mut self,
+        area: constant::Area,
+        db_number: i32,
+        start: i32,
+        amount: i32,
+        word_len: i32,
+        buffer: &[u8],
+    ) -> Result<(), Error> {
+        self.write_chunks(area, db_number, start, amount, word_len, buffer, None)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn write_chunks(
+        &mut self,
+        area: constant::Area,
+        db_number: i32,
+        mut start: i32,
+        mut amount: i32,
+        mut word_len: i32,
+        buffer: &[u8],
+        mut report: Option<&mut Vec<WriteChunk>>,
+    ) -> Result<(), Error> {
+        // Some adjustment
+        word_len = match area {
+            constant::Area::Counter => constant::WL_COUNTER,
+            constant::Area::Timer => constant::WL_TIMER,
+            _ => word_len,
+        };
+
+        // Calc Word size
+        let mut word_size = constant::data_size_byte(word_len);
+
+        if word_size == 0 {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_DATA_SIZE,
+                bytes: Vec::new(),
+            });
+        }
+        check_address(area, word_len, db_number).map_err(|input| Error::InvalidInput { input })?;
+        check_range(word_len, start, amount).map_err(|input| Error::InvalidInput { input })?;
+        check_buffer(word_len, amount, buffer.len())?;
+
+        // nothing to transfer, no request is sent
+        if amount == 0 {
+            self.round_trips = 0;
+            return Ok(());
+        }
+
+        if word_len == constant::WL_BIT {
+            amount = 1; // Only 1 bit can be transferred at time
+        } else {
+            if word_len != constant::WL_COUNTER && word_len != constant::WL_TIMER {
+                amount *= word_size;
+                word_size = 1;
+                word_len = constant::WL_BYTE;
+            }
+        }
+
+        let mut offset: i32 = 0;
+        let pdu_length = self.transport.pdu_length();
+        let mut max_elements = (pdu_length - 35) / word_size; // 35 = Reply telegram header
         let mut tot_elements = amount;
-        let db_bytes = (db_number as u16).to_be_bytes();
-        let mut offset = 0;
+        let started = Instant::now();
+        self.round_trips = 0;
+        let mut renegotiated = false;
+
+        while tot_elements > 0 {
+            self.check_operation_timeout(started)?;
+            let mut num_elements = tot_elements;
+            if num_elements > max_elements {
+                num_elements = max_elements;
+            }
+            let data_size = num_elements * word_size;
+            let iso_size = constant::SIZE_HEADER_WRITE + data_size;
+
+            // Setup the telegram
+            let mut request_data = transport::READ_WRITE_TELEGRAM.to_vec();
+            // Whole telegram Size
+            BigEndian::write_u16(request_data[2..].as_mut(), iso_size as u16);
+            // Data length = data item header (4 bytes) + data size in bytes, for every word length
+            BigEndian::write_u16(request_data[15..].as_mut(), (data_size + 4) as u16);
+            // Function
+            request_data[17] = 0x05;
+            // Set DB Number
+            request_data[27] = area as u8;
+
+            if let constant::Area::DataBausteine = area {
+                BigEndian::write_u16(request_data[25..].as_mut(), db_number as u16)
+            }
+
+            // Adjusts start, word length, transport size and the data item length.
+            // The data item length is expressed in bits for bit and byte transport sizes
+            // and in bytes for octet strings (counters and timers).
+            let (mut address, transport_size, length) = match word_len {
+                constant::WL_BIT => {
+                    request_data[22] = word_len as u8;
+                    // start is the bit address, a single bit is transferred
+                    (start, constant::TS_RES_BIT, num_elements)
+                }
+                constant::WL_COUNTER | constant::WL_TIMER => {
+                    request_data[22] = word_len as u8;
+                    (start, constant::TS_RES_OCTET, data_size)
+                }
+                // byte/word/dword etc.
+                _ => (start << 3, constant::TS_RES_BYTE, data_size << 3),
+            };
+
+            // Num elements
+            BigEndian::write_u16(request_data[23..].as_mut(), num_elements as u16);
+            // address into the PLC
+            request_data[30] = (address & 0x0FF) as u8;
+            address >>= 8;
+            request_data[29] = (address & 0x0FF) as u8;
+            address >>= 8;
+            request_data[28] = (address & 0x0FF) as u8;
+
+            // Transport Size
+            request_data[32] = transport_size as u8;
+            // length
+            BigEndian::write_u16(request_data[33..].as_mut(), length as u16);
+
+            //expand values into array
+            request_data.splice(
+                35..35,
+                buffer[offset as usize..offset as usize + data_size as usize].to_vec(),
+            );
+            check_pdu_size(&request_data, self.transport.pdu_length())?;
+
+            let result = self.send_job(request_data.as_mut_slice());
+            self.round_trips += 1;
+
+            match result {
+                Ok(response) => {
+                    if self.renegotiate_pdu(&response, &mut renegotiated)? {
+                        max_elements = (self.transport.pdu_length() - 35) / word_size;
+                        continue;
+                    }
+                    if pdu_size_refused(&response) {
+                        return Err(Error::CPU {
+                            code: error::CODE_7_DATA_OVER_PDU,
+                            bytes: response,
+                        });
+                    }
+                    // some gateways pad the answer, only a short one is malformed
+                    if response.len() < 22 {
+                        return Err(Error::Response {
+                            code: error::ISO_INVALID_PDU,
+                            bytes: response.to_vec(),
+                        });
+                    }
+
+                    if let Some(chunks) = report.as_mut() {
+                        chunks.push(WriteChunk {
+                            start,
+                            elements: num_elements,
+                            result: response[21],
+                        });
+                    }
+                    if response[21] != 0xFF {
+                        return Err(Error::CPU {
+                            code: response[21] as i32,
+                            bytes: response.to_vec(),
+                        });
+                    }
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+
+            offset += data_size;
+            tot_elements -= num_elements;
+            start += num_elements * word_size;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// Starting the CPU from power off,Current configuration is discarded and program processing begins again with the initial values.
+    pub fn start(&mut self) -> Result<(), Error> {
+        self.cold_warm_start_stop(
+            &transport::control_telegram(transport::PDU_START, b"C ", b"P_PROGRAM"),
+            transport::PDU_START,
+            error::CLI_CANNOT_START_PLC,
+            transport::PDU_ALREADY_STARTED,
+            error::CLI_ALREADY_RUN,
+        )
+    }
+
+    /// Restarting the CPU without turning the power off, Program processing starts once again where Retentive data is retained.
+    pub fn restart(&mut self) -> Result<(), Error> {
+        self.cold_warm_start_stop(
+            &transport::control_telegram(transport::PDU_START, b"", b"P_PROGRAM"),
+            transport::PDU_START,
+            error::CLI_CANNOT_START_PLC,
+            transport::PDU_ALREADY_STARTED,
+            error::CLI_ALREADY_RUN,
+        )
+    }
+
+    /// Hot restart, supported by S7-400 CPUs only.
+    /// Program processing resumes at the point where it was interrupted, all the data is retained.
+    pub fn hot_restart(&mut self) -> Result<(), Error> {
+        self.cold_warm_start_stop(
+            &transport::control_telegram(transport::PDU_START, b"A ", b"P_PROGRAM"),
+            transport::PDU_START,
+            error::CLI_CANNOT_START_PLC,
+            transport::PDU_ALREADY_STARTED,
+            error::CLI_ALREADY_RUN,
+        )
+    }
+
+    /// Shut down
+    pub fn stop(&mut self) -> Result<(), Error> {
+        self.cold_warm_start_stop(
+            &transport::control_telegram(transport::PDU_STOP, b"", b"P_PROGRAM"),
+            transport::PDU_STOP,
+            error::CLI_CANNOT_STOP_PLC,
+            transport::PDU_ALREADY_STOPPED,
+            error::CLI_ALREADY_STOP,
+        )
+    }
+
+    /// get plc status
+    pub fn plc_status(&mut self) -> Result<constant::CpuStatus, Error> {
+        let response = self.send_job(transport::PLC_STATUS_TELEGRAM.to_vec().as_mut())?;
+
+        if response.len() < transport::PLC_STATUS_MIN_RESPONSE {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+                bytes: response.to_vec(),
+            });
+        }
+
+        let result = BigEndian::read_u16(response[27..29].as_ref());
+
+        if result != 0 {
+            return Err(Error::CPU {
+                code: result as i32,
+                bytes: response.to_vec(),
+            });
+        }
+
+        constant::CpuStatus::from_u8(response[44])
+    }
+
+    pub fn cp_info(&mut self) -> Result<CPInfo, Error> {
+        let szl = self.read_szl(0x0131, 0x000)?;
+
+        Ok(CPInfo {
+            max_pdu_length: BigEndian::read_u16(szl.data[2..].as_ref()),
+            max_connections: BigEndian::read_u16(szl.data[4..].as_ref()),
+            max_mpi_rate: BigEndian::read_u16(szl.data[6..].as_ref()),
+            max_bus_rate: BigEndian::read_u16(szl.data[10..].as_ref()),
+        })
+    }
+
+    /// get cpu info
+    pub fn cpu_info(&mut self) -> Result<CpuInfo, Error> {
+        let szl = self.read_szl(0x001C, 0x000)?;
+
+        if szl.data.len() < transport::SZL_MIN_RESPONSE {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+                bytes: szl.data.clone(),
+            });
+        }
+
+        let module_type_name = match str::from_utf8(szl.data[172..204].as_ref()) {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(Error::InvalidResponse {
+                    bytes: szl.data[172..204].to_vec(),
+                    reason: e.to_string(),
+                })
+            }
+        };
+
+        let serial_number = match str::from_utf8(szl.data[138..162].as_ref()) {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(Error::InvalidResponse {
+                    bytes: szl.data[138..162].to_vec(),
+                    reason: e.to_string(),
+                })
+            }
+        };
+
+        let as_name = match str::from_utf8(szl.data[2..26].as_ref()) {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(Error::InvalidResponse {
+                    bytes: szl.data[2..26].to_vec(),
+                    reason: e.to_string(),
+                })
+            }
+        };
+
+        let copyright = match str::from_utf8(szl.data[104..130].as_ref()) {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(Error::InvalidResponse {
+                    bytes: szl.data[104..130].to_vec(),
+                    reason: e.to_string(),
+                })
+            }
+        };
+
+        let module_name = match str::from_utf8(szl.data[36..60].as_ref()) {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(Error::InvalidResponse {
+                    bytes: szl.data[36..60].to_vec(),
+                    reason: e.to_string(),
+                })
+            }
+        };
+
+        Ok(CpuInfo {
+            module_type_name: module_type_name.to_string(),
+            serial_number: serial_number.to_string(),
+            as_name: as_name.to_string(),
+            copyright: copyright.to_string(),
+            module_name: module_name.to_string(),
+        })
+    }
+
+    /// get the metadata of a block stored in the PLC.
+    /// SDBs and system blocks share the layout of the user blocks, the fields the CPU
+    /// doesn't keep for them are zero or empty. An answer shorter than the layout or
+    /// about another block returns `Error::InvalidResponse`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let before = cl.get_ag_block_info(client::BlockType::DB, 888).unwrap();
+    /// let after = cl.get_ag_block_info(client::BlockType::DB, 888).unwrap();
+    ///
+    /// if before != after {
+    ///     println!("DB888 changed, checksum {} -> {}", before.checksum, after.checksum);
+    /// }
+    /// ```
+    pub fn get_ag_block_info(
+        &mut self,
+        block_type: BlockType,
+        block_number: u16,
+    ) -> Result<S7BlockInfo, Error> {
+        let mut request = transport::BLOCK_INFO_TELEGRAM.to_vec();
+
+        request[30] = block_type as u8;
+        // Block number as 5 ascii digits
+        let mut number = block_number;
+        for i in (31..36).rev() {
+            request[i] = (number % 10) as u8 + 0x30;
+            number /= 10;
+        }
+
+        let response = self.send_job(request.as_mut_slice())?;
+
+        if response.len() < transport::BLOCK_INFO_MIN_RESPONSE {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+                bytes: response.to_vec(),
+            });
+        }
+
+        let result = BigEndian::read_u16(response[27..].as_ref());
+
+        if result != 0 {
+            return Err(Error::CPU {
+                code: result as i32,
+                bytes: response.to_vec(),
+            });
+        }
+
+        // the data part starts after its length
+        let length = BigEndian::read_u16(response[31..].as_ref()) as usize;
+        if length < transport::BLOCK_INFO_MIN_RESPONSE - 33 {
+            return Err(Error::InvalidResponse {
+                reason: format!("block info of {} bytes", length),
+                bytes: response,
+            });
+        }
+
+        let number = BigEndian::read_u16(response[45..].as_ref());
+        if number != block_number {
+            return Err(Error::InvalidResponse {
+                reason: format!("block info of block {} for {}", number, block_number),
+                bytes: response,
+            });
+        }
+
+        Ok(S7BlockInfo {
+            block_flags: response[42],
+            block_lang: response[43],
+            block_type: response[44],
+            block_number: number,
+            load_size: BigEndian::read_i32(response[47..].as_ref()),
+            code_date: siemens_timestamp(response[55..61].as_ref()),
+            interface_date: siemens_timestamp(response[61..67].as_ref()),
+            sbb_length: BigEndian::read_u16(response[67..].as_ref()),
+            local_data: BigEndian::read_u16(response[71..].as_ref()),
+            mc7_size: BigEndian::read_u16(response[73..].as_ref()),
+            author: to_chars(response[75..83].as_ref())?,
+            family: to_chars(response[83..91].as_ref())?,
+            header: to_chars(response[91..99].as_ref())?,
+            version: response[99],
+            checksum: BigEndian::read_u16(response[101..].as_ref()),
+        })
+    }
+
+    /// uploads a system data block, the configuration the hardware and network tools
+    /// download to the CPU. The raw block is returned as stored, header included,
+    /// connection tables and the like are left to the caller to decode.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// for number in cl.list_blocks_of_type(client::BlockType::SDB).unwrap() {
+    ///     let sdb = cl.read_sdb(number).unwrap();
+    ///     println!("SDB{} {} bytes", number, sdb.len());
+    /// }
+    /// ```
+    pub fn read_sdb(&mut self, number: u16) -> Result<Vec<u8>, Error> {
+        self.upload(BlockType::SDB, number)
+    }
+
+    /// runs the upload sequence of a block: start upload, upload until the CPU
+    /// flags the last part, end upload. The upload is ended even when a part fails.
+    fn upload(&mut self, block_type: BlockType, block_number: u16) -> Result<Vec<u8>, Error> {
+        let mut request = transport::START_UPLOAD_TELEGRAM;
+
+        request[28] = block_type as u8;
+        // Block number as 5 ascii digits
+        let mut number = block_number;
+        for i in (29..34).rev() {
+            request[i] = (number % 10) as u8 + 0x30;
+            number /= 10;
+        }
+
+        let response = self.send_job(request.as_mut())?;
+        check_job_response(&response, 0x1D, transport::START_UPLOAD_MIN_RESPONSE)?;
+        let id = &response[23..27];
+
+        let mut data = Vec::new();
+        let mut request = transport::UPLOAD_TELEGRAM;
+        request[21..25].copy_from_slice(id);
+        let result = loop {
+            let response = match self.send_job(request.as_mut()) {
+                Ok(response) => response,
+                Err(e) => break Err(e),
+            };
+            if let Err(e) = check_job_response(&response, 0x1E, transport::UPLOAD_MIN_RESPONSE) {
+                break Err(e);
+            }
+            let length = BigEndian::read_u16(response[21..].as_ref()) as usize;
+            match response
+                .get(transport::UPLOAD_MIN_RESPONSE..)
+                .and_then(|part| part.get(..length))
+            {
+                Some(part) => data.extend_from_slice(part),
+                None => {
+                    break Err(Error::InvalidResponse {
+                        reason: format!("upload part of {} bytes out of bounds", length),
+                        bytes: response,
+                    })
+                }
+            }
+            // more parts follow
+            if response[20] & 0x01 == 0 {
+                break Ok(());
+            }
+        };
+
+        let mut request = transport::END_UPLOAD_TELEGRAM;
+        request[21..25].copy_from_slice(id);
+        let ended = self
+            .send_job(request.as_mut())
+            .and_then(|response| check_job_response(&response, 0x1F, 20));
+
+        result?;
+        ended?;
+        Ok(data)
+    }
+
+    /// lists the numbers of all the blocks of a type stored in the PLC
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// for number in cl.list_blocks_of_type(client::BlockType::DB).unwrap() {
+    ///     println!("DB{}", number);
+    /// }
+    /// ```
+    pub fn list_blocks_of_type(&mut self, block_type: BlockType) -> Result<Vec<u16>, Error> {
+        let mut request = transport::LIST_BLOCKS_TELEGRAM.to_vec();
+        request[30] = block_type as u8;
+
+        let mut res = self.send_job(request.as_mut())?;
+        let mut numbers = Vec::new();
+        let mut next = transport::LIST_BLOCKS_NEXT_TELEGRAM.to_vec();
+
+        loop {
+            if res.len() < transport::LIST_BLOCKS_MIN_RESPONSE {
+                return Err(Error::Response {
+                    code: error::ISO_INVALID_PDU,
+                    bytes: res.to_vec(),
+                });
+            }
+
+            // no block of this type
+            if res[29] == 0x0A {
+                return Ok(numbers);
+            }
+
+            if BigEndian::read_u16(res[27..].as_ref()) != 0 || res[29] != 0xFF {
+                return Err(Error::CPU {
+                    code: error::CLI_INVALID_PLC_ANSWER,
+                    bytes: res.to_vec(),
+                });
+            }
+
+            let length = BigEndian::read_u16(res[31..].as_ref()) as usize;
+            let end = transport::LIST_BLOCKS_MIN_RESPONSE + length;
+            if res.len() < end {
+                return Err(Error::Response {
+                    code: error::ISO_INVALID_PDU,
+                    bytes: res.to_vec(),
+                });
+            }
+
+            numbers.extend(
+                res[transport::LIST_BLOCKS_MIN_RESPONSE..end]
+                    .chunks_exact(transport::LIST_BLOCKS_ENTRY_SIZE)
+                    .map(BigEndian::read_u16),
+            );
+
+            if res[26] == 0x00 {
+                return Ok(numbers);
+            }
+
+            next[24] = res[24];
+            res = self.send_job(next.as_mut())?;
+        }
+    }
+
+    /// lists every block stored in the PLC with its sizes,
+    /// the same content as the blocks view of the programming software.
+    /// Costs a block info request per block on top of the listing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// for entry in cl.block_directory().unwrap() {
+    ///     println!(
+    ///         "{:?}{} load {} work {}",
+    ///         entry.block_type, entry.block_number, entry.load_size, entry.mc7_size
+    ///     );
+    /// }
+    /// ```
+    pub fn block_directory(&mut self) -> Result<Vec<BlockDirEntry>, Error> {
+        let mut entries = Vec::new();
+
+        for block_type in BlockType::ALL.iter() {
+            for block_number in self.list_blocks_of_type(*block_type)? {
+                let info = self.get_ag_block_info(*block_type, block_number)?;
+                entries.push(BlockDirEntry {
+                    block_type: *block_type,
+                    block_number,
+                    load_size: info.load_size,
+                    mc7_size: info.mc7_size,
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    /// lists the number and size of every data block, to size the buffers
+    /// and estimate the time of a full backup before reading any of them.
+    /// Costs a block info request per data block on top of the listing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let inventory = cl.db_inventory().unwrap();
+    /// let total: usize = inventory.iter().map(|(_, size)| *size as usize).sum();
+    /// println!("{} DBs, {} bytes", inventory.len(), total);
+    /// ```
+    pub fn db_inventory(&mut self) -> Result<Vec<(u16, u16)>, Error> {
+        let mut inventory = Vec::new();
+
+        for db_number in self.list_blocks_of_type(BlockType::DB)? {
+            let info = self.get_ag_block_info(BlockType::DB, db_number)?;
+            inventory.push((db_number, info.mc7_size));
+        }
+        Ok(inventory)
+    }
+
+    /// read the current values of a set of variables through the variable table service,
+    /// the one used by the watch tables of the programming software.
+    /// Unlike plain reads, forced addresses report the value the CPU actually works with.
+    /// The values are returned in the same order as the items.
+    ///
+    /// Each item is read as `size` elements of byte, word or double word size,
+    /// bits should be read as the byte containing them.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    /// use s7::client::S7DataItem;
+    /// use s7::constant::Area;
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let items = [
+    ///     // IB0
+    ///     S7DataItem::new(Area::ProcessInput, 0x02, 0, 0, 1),
+    ///     // DB888.DBW8
+    ///     S7DataItem::new(Area::DataBausteine, 0x04, 888, 8, 1),
+    /// ];
+    ///
+    /// let values = cl.read_var_table(&items).unwrap();
+    /// println!("IB0 {:?} DB888.DBW8 {:?}", values[0], values[1]);
+    /// ```
+    pub fn read_var_table(&mut self, items: &[S7DataItem]) -> Result<Vec<Vec<u8>>, Error> {
+        if items.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut request = transport::VAR_TABLE_TELEGRAM.to_vec();
+        // Var table header: unknown, request, byte count, unknown (4 bytes), item count
+        let byte_count = 6 + items.len() * transport::VAR_TABLE_ITEM_SIZE;
+        request.extend_from_slice(&[0x00, transport::VAR_TABLE_REQUEST]);
+        request.extend_from_slice(&(byte_count as u16).to_be_bytes());
+        request.extend_from_slice(&[0, 0, 0, 0]);
+        request.extend_from_slice(&(items.len() as u16).to_be_bytes());
+
+        for (i, item) in items.iter().enumerate() {
+            check_address(item.area, item.word_len, item.db_number).map_err(|reason| {
+                Error::InvalidInput {
+                    input: format!("item {}: {}", i, reason),
+                }
+            })?;
+            request.push(var_table_area(item)?);
+
+            if item.size < 1 || item.size > u8::MAX as i32 {
+                return Err(Error::InvalidInput {
+                    input: format!("var table item size {}", item.size),
+                });
+            }
+            // Repetition factor
+            request.push(item.size as u8);
+
+            let db_number = match item.area {
+                constant::Area::DataBausteine => item.db_number,
+                _ => 0,
+            };
+            if db_number < 0 || db_number > u16::MAX as i32 {
+                return Err(Error::InvalidInput {
+                    input: format!("var table item db number {}", item.db_number),
+                });
+            }
+            request.extend_from_slice(&(db_number as u16).to_be_bytes());
+
+            if item.start < 0 || item.start > u16::MAX as i32 {
+                return Err(Error::InvalidInput {
+                    input: format!("var table item start {}", item.start),
+                });
+            }
+            request.extend_from_slice(&(item.start as u16).to_be_bytes());
+        }
+
+        // Telegram, parameters and data length
+        let total = request.len() as u16;
+        BigEndian::write_u16(request[2..].as_mut(), total);
+        BigEndian::write_u16(
+            request[15..].as_mut(),
+            total - transport::VAR_TABLE_DATA_START as u16,
+        );
+        BigEndian::write_u16(
+            request[27..].as_mut(),
+            total - transport::VAR_TABLE_TELEGRAM.len() as u16,
+        );
+
+        let response = self.send_job(request.as_mut_slice())?;
+
+        let count = var_table_count(response.as_ref())?;
+        if count != items.len() {
+            return Err(Error::InvalidResponse {
+                reason: format!("expected {} var table items got {}", items.len(), count),
+                bytes: response,
+            });
+        }
+
+        let values = var_table_values(response.as_ref(), transport::VAR_TABLE_MIN_RESPONSE, count)?;
+
+        Ok(values)
+    }
+
+    /// read the addresses and values currently forced in the CPU.
+    /// An empty list means nothing is forced.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// for force in cl.read_forces().unwrap() {
+    ///     println!("{:?} {} forced to {:?}", force.area, force.start, force.value);
+    /// }
+    /// ```
+    pub fn read_forces(&mut self) -> Result<Vec<ForceEntry>, Error> {
+        let response = self.send_job(transport::FORCES_TELEGRAM.to_vec().as_mut())?;
+
+        let count = var_table_count(response.as_ref())?;
+
+        // The forced addresses come first, in the same layout as a var table request,
+        // then the forced values
+        let mut offset = transport::VAR_TABLE_MIN_RESPONSE;
+        let mut addresses = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            let item = match response.get(offset..offset + transport::VAR_TABLE_ITEM_SIZE) {
+                Some(item) => item,
+                None => {
+                    return Err(Error::InvalidResponse {
+                        reason: "force address out of bounds".to_string(),
+                        bytes: response,
+                    })
+                }
+            };
+
+            let (area, word_len) = match var_table_item_area(item[0]) {
+                Some(a) => a,
+                None => {
+                    return Err(Error::InvalidResponse {
+                        reason: format!("unknown force area {}", item[0]),
+                        bytes: response,
+                    })
+                }
+            };
+
+            addresses.push((
+                area,
+                word_len,
+                BigEndian::read_u16(item[2..].as_ref()) as i32,
+                BigEndian::read_u16(item[4..].as_ref()) as i32,
+            ));
+            offset += transport::VAR_TABLE_ITEM_SIZE;
+        }
+
+        let values = var_table_values(response.as_ref(), offset, count)?;
+
+        Ok(addresses
+            .into_iter()
+            .zip(values)
+            .map(|((area, word_len, db_number, start), value)| ForceEntry {
+                area,
+                word_len,
+                db_number,
+                start,
+                value,
+            })
+            .collect())
+    }
+
+    /// get the current operating mode and why the CPU got there
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let mode = cl.operating_mode_info().unwrap();
+    /// println!("mode {:#x} after event {:#x}", mode.current_mode, mode.event_id);
+    /// ```
+    pub fn operating_mode_info(&mut self) -> Result<OperatingModeInfo, Error> {
+        let szl = self.read_szl(0x0424, 0x0000)?;
+
+        if szl.data.len() < transport::OPERATING_MODE_RECORD_SIZE {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+                bytes: szl.data.clone(),
+            });
+        }
+
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(szl.data[12..20].as_ref());
+
+        Ok(OperatingModeInfo {
+            event_id: BigEndian::read_u16(szl.data[0..].as_ref()),
+            requested_mode: szl.data[3] & 0x0F,
+            previous_mode: szl.data[3] >> 4,
+            startup_type: szl.data[9],
+            permitted_startup: szl.data[10],
+            current_mode: szl.data[11],
+            timestamp,
+        })
+    }
+
+    /// get the size and usage of the work and load memory, to warn before a download fails.
+    /// CPUs without SZL 0x0013 return `Error::Response` with the function not available code.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let memory = cl.memory_usage().unwrap();
+    /// println!("{} of {} bytes work memory free", memory.work_free(), memory.work_total);
+    /// ```
+    pub fn memory_usage(&mut self) -> Result<MemoryUsage, Error> {
+        let szl = self.read_optional_szl(0x0013, 0x0000)?;
+
+        let mut usage = MemoryUsage::default();
+        let mut has_work_memory = false;
+
+        for record in szl.records() {
+            if record.len() < transport::MEMORY_AREA_RECORD_SIZE {
+                return Err(Error::Response {
+                    code: error::ISO_INVALID_PDU,
+                    bytes: szl.data.clone(),
+                });
+            }
+
+            // the RAM and the retentive part of the area, each with its size,
+            // used bytes and largest free block
+            let total = BigEndian::read_u32(record[4..].as_ref());
+            let used = BigEndian::read_u32(record[16..].as_ref())
+                .saturating_add(BigEndian::read_u32(record[28..].as_ref()));
+            let largest_free = BigEndian::read_u32(record[20..].as_ref())
+                .max(BigEndian::read_u32(record[32..].as_ref()));
+
+            match BigEndian::read_u16(record) {
+                // work memory
+                0x0001 => {
+                    has_work_memory = true;
+                    usage.work_total = total;
+                    usage.work_used = used;
+                    usage.work_largest_free = largest_free;
+                }
+                // integrated and plugged in load memory
+                0x0002 | 0x0003 => {
+                    usage.load_total = usage.load_total.saturating_add(total);
+                    usage.load_used = usage.load_used.saturating_add(used);
+                    usage.load_largest_free = usage.load_largest_free.max(largest_free);
+                }
+                _ => {}
+            }
+        }
+
+        if !has_work_memory {
+            return Err(Error::Response {
+                code: error::CLI_FUN_NOT_AVAILABLE,
+                bytes: szl.data,
+            });
+        }
+        Ok(usage)
+    }
+
+    /// get the last, shortest and longest OB1 cycle times, from the OB1 start information.
+    /// The CPU counts them in milliseconds. CPUs without SZL 0x0222 return `Error::Response`
+    /// with the function not available code.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let cycle = cl.cycle_time().unwrap();
+    /// println!("scan {:?}, between {:?} and {:?}", cycle.current, cycle.min, cycle.max);
+    /// ```
+    pub fn cycle_time(&mut self) -> Result<CycleTime, Error> {
+        // OB1
+        let szl = self.read_optional_szl(0x0222, 0x0001)?;
+
+        if szl.data.len() < transport::OB1_START_INFO_SIZE {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+                bytes: szl.data,
+            });
+        }
+
+        let millis = |offset: usize| {
+            Duration::from_millis(BigEndian::read_u16(szl.data[offset..].as_ref()) as u64)
+        };
+        Ok(CycleTime {
+            current: millis(6),
+            min: millis(8),
+            max: millis(10),
+        })
+    }
+
+    /// get the IP address, subnet mask and gateway of the integrated Ethernet interface,
+    /// from the Ethernet details of SZL 0x0037. The first interface is returned when
+    /// there are several. CPUs without an integrated PROFINET interface return
+    /// `Error::Response` with the function not available code.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let net = cl.network_config().unwrap();
+    /// println!("{}/{} via {}", net.ip_address, net.subnet_mask, net.gateway);
+    /// ```
+    pub fn network_config(&mut self) -> Result<NetworkConfig, Error> {
+        let szl = self.read_optional_szl(0x0037, 0x0000)?;
+
+        let record = match szl.records().next() {
+            Some(record) => record,
+            None => {
+                return Err(Error::Response {
+                    code: error::CLI_FUN_NOT_AVAILABLE,
+                    bytes: szl.data.clone(),
+                })
+            }
+        };
+        if record.len() < transport::ETHERNET_RECORD_SIZE {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+                bytes: szl.data.clone(),
+            });
+        }
+
+        // the logical address of the interface, then the addresses
+        let address =
+            |offset: usize| Ipv4Addr::from(BigEndian::read_u32(record[offset..].as_ref()));
+        let mut mac_address = [0u8; 6];
+        mac_address.copy_from_slice(&record[14..20]);
+        Ok(NetworkConfig {
+            ip_address: address(2),
+            subnet_mask: address(6),
+            gateway: address(10),
+            mac_address,
+        })
+    }
+
+    /// get the firmware version of the CPU as `(major, minor, patch)`,
+    /// from the firmware record of the module identification, SZL 0x0011.
+    /// Tuples compare in order, so `>= (4, 5, 0)` gates on firmware V4.5.
+    /// CPUs without a firmware record return `Error::Response`
+    /// with the function not available code.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// if cl.firmware_version().unwrap() >= (4, 5, 0) {
+    ///     println!("firmware supports it");
+    /// }
+    /// ```
+    pub fn firmware_version(&mut self) -> Result<(u8, u8, u8), Error> {
+        let szl = self.read_optional_szl(0x0011, 0x0000)?;
+
+        // the records of the module, the hardware and the firmware, in any order
+        let record = match szl
+            .records()
+            .find(|record| record.len() >= 2 && BigEndian::read_u16(record) == 0x0007)
+        {
+            Some(record) => record,
+            None => {
+                return Err(Error::Response {
+                    code: error::CLI_FUN_NOT_AVAILABLE,
+                    bytes: szl.data.clone(),
+                })
+            }
+        };
+        if record.len() < transport::MODULE_ID_RECORD_SIZE {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+                bytes: szl.data.clone(),
+            });
+        }
+
+        firmware_version(&record[24..28]).ok_or_else(|| Error::InvalidResponse {
+            reason: "firmware version doesn't start with V".to_string(),
+            bytes: record[24..28].to_vec(),
+        })
+    }
+
+    /// read the CPU clock. The time zone comes from the time status word
+    /// of the time system status, SZL 0x0132 index 8: bit 8 flags daylight saving time,
+    /// bits 10 to 14 hold the offset in half hours and bit 15 its sign.
+    /// CPUs without the list only report their local time.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let clock = cl.get_plc_clock().unwrap();
+    /// match clock.utc_offset_minutes {
+    ///     Some(offset) => println!("{} UTC{:+}min dst {}", clock.local, offset, clock.dst_active),
+    ///     None => println!("{} unknown time zone", clock.local),
+    /// }
+    /// ```
+    pub fn get_plc_clock(&mut self) -> Result<PlcClock, Error> {
+        let response = self.send_job(transport::GET_CLOCK_TELEGRAM.to_vec().as_mut())?;
+
+        if response.len() < transport::GET_CLOCK_MIN_RESPONSE {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+                bytes: response.to_vec(),
+            });
+        }
+        let result = BigEndian::read_u16(response[27..29].as_ref());
+        if result != 0 || response[29] != 0xFF {
+            return Err(Error::CPU {
+                code: if result != 0 {
+                    result as i32
+                } else {
+                    response[29] as i32
+                },
+                bytes: response.to_vec(),
+            });
+        }
+        let local = match clock_timestamp(&response[33..43]) {
+            Some(local) => local,
+            None => {
+                return Err(Error::InvalidResponse {
+                    reason: "clock is not BCD encoded".to_string(),
+                    bytes: response,
+                })
+            }
+        };
+
+        let status = match self.read_optional_szl(0x0132, 0x0008) {
+            Ok(szl) if szl.data.len() >= transport::TIME_SYSTEM_RECORD_SIZE => {
+                Some(BigEndian::read_u16(szl.data[34..].as_ref()))
+            }
+            Ok(_) => None,
+            Err(Error::Response { code, .. }) if code == error::CLI_FUN_NOT_AVAILABLE => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(PlcClock {
+            local,
+            dst_active: status.is_some_and(|status| status & 0x0100 != 0),
+            utc_offset_minutes: status.map(|status| {
+                let minutes = ((status >> 10) & 0x1F) as i16 * 30;
+                if status & 0x8000 != 0 {
+                    -minutes
+                } else {
+                    minutes
+                }
+            }),
+        })
+    }
+
+    /// the ids of the system status lists the CPU supports, from SZL 0x0000
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// for id in cl.list_szl_ids().unwrap() {
+    ///     println!("{:#06x}", id);
+    /// }
+    /// ```
+    pub fn list_szl_ids(&mut self) -> Result<Vec<u16>, Error> {
+        let szl = self.read_szl(0x0000, 0x0000)?;
+        Ok(szl
+            .records()
+            .filter(|record| record.len() >= 2)
+            .map(BigEndian::read_u16)
+            .collect())
+    }
+
+    /// reads every system status list the CPU supports with index 0,
+    /// the raw records keyed by id, to attach to a support request.
+    /// Lists the CPU refuses or answers malformed are left out,
+    /// losing the connection or a timeout still fails the whole dump.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let mut dump: Vec<(u16, Vec<u8>)> = cl.diagnostic_dump().unwrap().into_iter().collect();
+    /// dump.sort();
+    /// for (id, data) in dump {
+    ///     println!("{:04X}: {:02X?}", id, data);
+    /// }
+    /// ```
+    pub fn diagnostic_dump(&mut self) -> Result<HashMap<u16, Vec<u8>>, Error> {
+        let ids = self.list_szl_ids()?;
+
+        let mut dump = HashMap::with_capacity(ids.len());
+        for id in ids {
+            match self.read_szl(id, 0x0000) {
+                Ok(szl) => {
+                    dump.insert(id, szl.data);
+                }
+                Err(Error::CPU { .. })
+                | Err(Error::Response { .. })
+                | Err(Error::InvalidResponse { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(dump)
+    }
+
+    /// `read_szl` for lists not every CPU has, an unknown list or index
+    /// is reported as the function not being available, other errors as they are
+    fn read_optional_szl(&mut self, id: u16, index: u16) -> Result<transport::S7SZL, Error> {
+        match self.read_szl(id, index) {
+            Err(Error::CPU { bytes, .. }) if szl_not_available(&bytes) => Err(Error::Response {
+                code: error::CLI_FUN_NOT_AVAILABLE,
+                bytes,
+            }),
+            result => result,
+        }
+    }
+
+    /// read a system status list (SZL) by id and index.
+    /// The returned header describes how the data is split into records.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// // module identification
+    /// let szl = cl.read_szl(0x0011, 0x0000).unwrap();
+    ///
+    /// for record in szl.records() {
+    ///     println!("{:?}", record);
+    /// }
+    /// ```
+    pub fn read_szl(&mut self, id: u16, index: u16) -> Result<transport::S7SZL, Error> {
+        let mut data = Vec::new();
+        let header = self.read_szl_fragments(id, index, |fragment| {
+            data.extend_from_slice(fragment);
+            Ok(())
+        })?;
+        Ok(transport::S7SZL { header, data })
+    }
+
+    /// same as `read_szl` but the data is copied into `buffer`, for callers polling
+    /// lists often that want to avoid allocating the data every time.
+    /// Returns the number of bytes written and the header,
+    /// fails with `Error::BufferSize` when the list doesn't fit.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// let addr = Ipv4Addr::new(127, 0, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+    /// let t = tcp::Transport::connect(opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    ///
+    /// let buffer = &mut [0u8; 1024];
+    /// // CPU operating mode
+    /// let (size, header) = cl.read_szl_into(0x0424, 0x0000, buffer).unwrap();
+    /// println!("{} records {:?}", header.number_of_data_record, &buffer[..size]);
+    /// ```
+    pub fn read_szl_into(
+        &mut self,
+        id: u16,
+        index: u16,
+        buffer: &mut [u8],
+    ) -> Result<(usize, transport::SZLHeader), Error> {
+        let mut size = 0;
+        let header = self.read_szl_fragments(id, index, |fragment| {
+            let end = size + fragment.len();
+            if end > buffer.len() {
+                return Err(Error::BufferSize {
+                    expected: end,
+                    got: buffer.len(),
+                });
+            }
+            buffer[size..end].copy_from_slice(fragment);
+            size = end;
+            Ok(())
+        })?;
+        Ok((size, header))
+    }
+
+    /// requests every fragment of a SZL and hands their data over to `consume`, in order
+    fn read_szl_fragments<F>(
+        &mut self,
+        id: u16,
+        index: u16,
+        mut consume: F,
+    ) -> Result<transport::SZLHeader, Error>
+    where
+        F: FnMut(&[u8]) -> Result<(), Error>,
+    {
+        let mut s7_szlfirst = transport::SZL_FIRST_TELEGRAM;
+
+        BigEndian::write_u16(s7_szlfirst[29..].as_mut(), id);
+        BigEndian::write_u16(s7_szlfirst[31..].as_mut(), index);
+
+        let mut res = self.send_job(s7_szlfirst.as_mut())?;
+
+        // size is the minimum length of the whole response.
+        // An error answer stops after the parameters and an empty data header,
+        // its code is checked before it could be taken for a truncated one.
+        let validate = |res: &[u8], size: usize| -> Result<(), Error> {
+            if res.len() >= 29
+                && BigEndian::read_u16(res[27..].as_ref()) != 0
+                && res.get(29) != Some(&0xFF)
+            {
+                return Err(Error::CPU {
+                    code: error::CLI_INVALID_PLC_ANSWER,
+                    bytes: res.to_vec(),
+                });
+            }
+
+            if res.len() < size {
+                return Err(Error::Response {
+                    code: error::ISO_INVALID_PDU,
+                    bytes: res.to_vec(),
+                });
+            }
+            Ok(())
+        };
+
+        validate(res.as_ref(), transport::MIN_SZL_FIRST_TELEGRAM)?;
+
+        // Skips extra params (ID, Index ...)
+        let mut data_szl = BigEndian::read_u16(res[31..].as_ref()).saturating_sub(8);
+
+        validate(
+            res.as_ref(),
+            transport::MIN_SZL_FIRST_TELEGRAM + data_szl as usize,
+        )?;
+
+        let mut done = res[26] == 0x00;
+        // Slice sequence
+        let mut seq_in: u8 = res[24];
+        let header = transport::SZLHeader {
+            length_header: BigEndian::read_u16(res[37..].as_ref()),
+            number_of_data_record: BigEndian::read_u16(res[39..].as_ref()),
+        };
+
+        consume(res[41..41 + data_szl as usize].as_ref())?;
+
+        let mut s7szlnext = transport::SZL_NEXT_TELEGRAM;
+
+        while !done {
+            s7szlnext[24] = seq_in;
+
+            res = self.send_job(s7szlnext.as_mut())?;
+
+            validate(res.as_ref(), transport::MIN_SZL_NEXT_TELEGRAM)?;
+
+            data_szl = BigEndian::read_u16(res[31..].as_ref());
+
+            validate(
+                res.as_ref(),
+                transport::MIN_SZL_NEXT_TELEGRAM + data_szl as usize,
+            )?;
+
+            done = res[26] == 0x00;
+            seq_in = res[24];
+
+            // Next frames carry no ID/Index header, the data starts right away
+            consume(res[37..37 + data_szl as usize].as_ref())?;
+        }
+        Ok(header)
+    }
+
+    fn cold_warm_start_stop(
+        &mut self,
+        req: &[u8],
+        start_cmp: u8,
+        start: i32,
+        already_cmp: u8,
+        already: i32,
+    ) -> Result<(), Error> {
+        let response = self.send_job(req.to_vec().as_mut())?;
+
+        if response.len() < transport::TELEGRAM_MIN_RESPONSE {
+            return Err(Error::Response {
+                code: error::ISO_INVALID_PDU,
+                bytes: response.to_vec(),
+            });
+        }
+
+        if response[17] != start_cmp {
+            return Err(Error::Response {
+                code: start,
+                bytes: response.to_vec(),
+            });
+        }
+        if response[18] == already_cmp {
+            return Err(Error::Response {
+                code: already,
+                bytes: response.to_vec(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// catches a telegram assembled larger than the negotiated PDU before the CPU
+/// refuses it, `Error::PduLength` holds the size of its S7 PDU
+fn check_pdu_size(telegram: &[u8], pdu_length: i32) -> Result<(), Error> {
+    // TPKT and COTP headers aren't part of the PDU
+    let size = telegram.len() as i32 - 7;
+    if size > pdu_length {
+        return Err(Error::PduLength(size));
+    }
+    Ok(())
+}
+
+/// the SZL answer is an error for an unknown list id or index, 0xD401 or 0xD402
+fn szl_not_available(response: &[u8]) -> bool {
+    response.len() >= 29
+        && matches!(
+            BigEndian::read_u16(response[27..].as_ref()) as i32,
+            error::CODE_7_INVALID_SZL_ID | error::CODE_7_INVALID_SZL_INDEX
+        )
+}
+
+/// the job was refused as larger than the PDU, error 0x8500 in the header
+fn pdu_size_refused(response: &[u8]) -> bool {
+    response.len() >= 19
+        && BigEndian::read_u16(response[17..].as_ref()) as i32 == error::CODE_7_DATA_OVER_PDU
+}
+
+/// checks the answer to a job is at least `min_size` long, carries no error
+/// and is about `function`
+fn check_job_response(response: &[u8], function: u8, min_size: usize) -> Result<(), Error> {
+    if response.len() < min_size {
+        return Err(Error::Response {
+            code: error::ISO_INVALID_PDU,
+            bytes: response.to_vec(),
+        });
+    }
+    let result = BigEndian::read_u16(response[17..].as_ref());
+    if result != 0 {
+        return Err(Error::CPU {
+            code: result as i32,
+            bytes: response.to_vec(),
+        });
+    }
+    if response[19] != function {
+        return Err(Error::InvalidResponse {
+            reason: format!(
+                "function {:#04x} answered for {:#04x}",
+                response[19], function
+            ),
+            bytes: response.to_vec(),
+        });
+    }
+    Ok(())
+}
+
+/// rejects the word lengths that make no sense in the area,
+/// the CPU answers those with a vague error code.
+/// Counters and timers accept any word length as it's replaced by their own.
+fn check_word_len(area: constant::Area, word_len: i32) -> Result<(), String> {
+    match (area, word_len) {
+        (constant::Area::Unknown, _) => Err("unknown area".to_string()),
+        (constant::Area::Counter, _) | (constant::Area::Timer, _) => Ok(()),
+        (constant::Area::DirectPeripheral, constant::WL_BIT) => {
+            Err("single bits can't be accessed in the peripheral area".to_string())
+        }
+        (_, constant::WL_COUNTER) | (_, constant::WL_TIMER) => Err(format!(
+            "word length {:#04x} outside the counter and timer areas, area {:?}",
+            word_len, area
+        )),
+        _ if constant::data_size_byte(word_len) == 0 => {
+            Err(format!("unknown word length {:#04x}", word_len))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Reads several data block segments chunk by chunk, created by `Client::db_range_reader`.
+/// A failed chunk is yielded as an error and the rest of its segment is skipped,
+/// the iteration goes on with the next segment.
+pub struct DbRangeReader<'a, T: Transport> {
+    client: &'a mut Client<T>,
+    segments: Vec<(i32, i32, i32)>,
+    chunk_size: i32,
+    /// segment being read and the offset reached in it
+    current: usize,
+    offset: i32,
+    /// segment of the last yielded item
+    last: usize,
+}
+
+impl<'a, T: Transport> DbRangeReader<'a, T> {
+    /// index of the segment the last yielded chunk or error belongs to
+    pub fn segment(&self) -> usize {
+        self.last
+    }
+}
+
+impl<'a, T: Transport> Iterator for DbRangeReader<'a, T> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (db_number, start, size) = *self.segments.get(self.current)?;
+            if self.offset >= size {
+                self.current += 1;
+                self.offset = 0;
+                continue;
+            }
+
+            let length = (size - self.offset).min(self.chunk_size);
+            let mut chunk = vec![0u8; length as usize];
+            self.last = self.current;
+
+            let result = self
+                .client
+                .ag_read(db_number, start + self.offset, length, &mut chunk);
+
+            return match result {
+                Ok(()) => {
+                    self.offset += length;
+                    Some(Ok(chunk))
+                }
+                Err(e) => {
+                    self.current += 1;
+                    self.offset = 0;
+                    Some(Err(e))
+                }
+            };
+        }
+    }
+}
+
+/// Reads typed values anywhere in the data blocks with as few requests as possible.
+/// The values are returned in the order they were added.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::{Ipv4Addr, IpAddr};
+/// use s7::{client, tcp, transport};
+/// use s7::client::MultiRead;
+/// use s7::field::Value;
+///
+/// let addr = Ipv4Addr::new(127, 0, 0, 1);
+/// let opts = tcp::Options::new(IpAddr::from(addr), 5, 5, transport::Connection::PG);
+/// let t = tcp::Transport::connect(opts).unwrap();
+/// let mut cl = client::Client::new(t).unwrap();
+///
+/// let values = MultiRead::new()
+///     .bool(888, 8.4)
+///     .real(888, 12)
+///     .int(889, 0)
+///     .execute(&mut cl)
+///     .unwrap();
+///
+/// if let Value::Real(cooling) = values[1] {
+///     println!("cooling {}", cooling);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct MultiRead {
+    items: Vec<S7DataItem>,
+}
+
+impl MultiRead {
+    pub fn new() -> MultiRead {
+        MultiRead::default()
+    }
+
+    /// a bit at a `byte.bit` offset, see `field::split_offset`
+    pub fn bool(mut self, db_number: i32, offset: f32) -> MultiRead {
+        let (byte, bit) = field::split_offset(offset);
+        let start = byte as i32 * 8 + bit as i32;
+        self.items.push(S7DataItem::new(
+            constant::Area::DataBausteine,
+            constant::WL_BIT,
+            db_number,
+            start,
+            1,
+        ));
+        self
+    }
+
+    /// a 16 bit signed integer
+    pub fn int(mut self, db_number: i32, offset: i32) -> MultiRead {
+        self.items.push(S7DataItem::new(
+            constant::Area::DataBausteine,
+            constant::WL_INT,
+            db_number,
+            offset,
+            1,
+        ));
+        self
+    }
+
+    /// a 32 bit float
+    pub fn real(mut self, db_number: i32, offset: i32) -> MultiRead {
+        self.items.push(S7DataItem::new(
+            constant::Area::DataBausteine,
+            constant::WL_REAL,
+            db_number,
+            offset,
+            1,
+        ));
+        self
+    }
+
+    /// reads all the values, in as many requests as the PDU length requires.
+    /// Fails with the error of the first value the PLC couldn't read.
+    pub fn execute<T: Transport>(mut self, client: &mut Client<T>) -> Result<Vec<Value>, Error> {
+        client.read_multi_vars_batched(&mut self.items)?;
+
+        let mut values = Vec::with_capacity(self.items.len());
+        for item in self.items {
+            if let Some(e) = item.err {
+                return Err(e);
+            }
+            let expected = constant::data_size_byte(item.word_len) as usize;
+            if item.buffer.len() != expected {
+                return Err(Error::InvalidResponse {
+                    reason: format!("expected {} bytes", expected),
+                    bytes: item.buffer,
+                });
+            }
+            values.push(match item.word_len {
+                constant::WL_BIT => Value::Bool(item.buffer[0] != 0),
+                constant::WL_INT => Value::Int(field::get_int(&item.buffer)?),
+                _ => Value::Real(field::get_real(&item.buffer)?),
+            });
+        }
+        Ok(values)
+    }
+}
+
+/// builds the read var telegram for `items`, as sent by `Client::read_multi_vars`.
+/// The item count is a single byte, more than 255 items are rejected.
+/// PLCs usually accept at most `transport::MAX_VARS` items and the telegram
+/// must fit in the negotiated PDU, neither is checked here.
+///
+/// # Examples
+///
+/// ```
+/// use s7::client::{build_multi_read_request, S7DataItem};
+/// use s7::constant::{Area, WL_BYTE};
+///
+/// let items = [
+///     S7DataItem::new(Area::Merker, WL_BYTE, 0, 10, 1),
+///     S7DataItem::new(Area::DataBausteine, WL_BYTE, 888, 0, 4),
+/// ];
+/// let request = build_multi_read_request(&items).unwrap();
+///
+/// // item count
+/// assert_eq!(request[18], 2);
+/// assert_eq!(request.len(), 19 + 2 * 12);
+/// ```
+pub fn build_multi_read_request(items: &[S7DataItem]) -> Result<Vec<u8>, Error> {
+    if items.len() > u8::MAX as usize {
+        return Err(Error::InvalidInput {
+            input: format!("{} items, the count is a single byte", items.len()),
+        });
+    }
+
+    let mut request =
+        transport::READ_WRITE_TELEGRAM[..transport::READ_MULTI_MIN_RESPONSE - 2].to_vec();
+    for item in items.iter() {
+        let (word_len, num_elements) = item.transfer();
+        let spec = read_request(
+            item.area,
+            item.db_number,
+            item.start,
+            word_len,
+            num_elements,
+        );
+        request.extend_from_slice(&spec[19..19 + transport::READ_ITEM_SIZE]);
+    }
+    let length = request.len() as u16;
+    BigEndian::write_u16(request[2..].as_mut(), length);
+    BigEndian::write_u16(
+        request[13..].as_mut(),
+        (2 + items.len() * transport::READ_ITEM_SIZE) as u16,
+    );
+    request[18] = items.len() as u8;
+    Ok(request)
+}
+
+/// sizes of the PDUs of the request and the response reading all `items` at once
+fn multi_read_sizes<'a, I>(items: I) -> (usize, usize)
+where
+    I: Iterator<Item = &'a S7DataItem>,
+{
+    // headers without TPKT and COTP
+    let header = transport::READ_MULTI_MIN_RESPONSE - 7;
+    let (mut request, mut response) = (header, header);
+
+    for item in items {
+        let (word_len, num_elements) = item.transfer();
+        let size = (num_elements * constant::data_size_byte(word_len)) as usize;
+        request += transport::READ_ITEM_SIZE;
+        response += 4 + size + size % 2;
+    }
+    (request, response)
+}
+
+/// `check_word_len` plus the data block number, which is sent as 16 bits
+/// and would silently address another block once truncated
+fn check_address(area: constant::Area, word_len: i32, db_number: i32) -> Result<(), String> {
+    check_word_len(area, word_len)?;
+    if area == constant::Area::DataBausteine && !(0..=u16::MAX as i32).contains(&db_number) {
+        return Err(format!("DB number {} out of range", db_number));
+    }
+    Ok(())
+}
+
+/// block-less `DBX`/`DBW` addresses take their DB from elsewhere, on their own
+/// they would address DB0
+fn check_db_number(address: &Address) -> Result<(), Error> {
+    if address.area == constant::Area::DataBausteine && address.db_number == 0 {
+        return Err(Error::InvalidInput {
+            input: format!("{} has no DB number", address),
+        });
+    }
+    Ok(())
+}
+
+/// number of words as the amount of a read or write, whose size in bytes fits an `i32`
+fn word_count(count: usize) -> Result<i32, Error> {
+    match i32::try_from(count) {
+        Ok(amount) if amount <= i32::MAX / 2 => Ok(amount),
+        _ => Err(Error::InvalidInput {
+            input: format!("{} words", count),
+        }),
+    }
+}
+
+/// rejects negative starts and ranges past the 24 bit addresses of the protocol,
+/// byte addresses are sent multiplied by 8
+fn check_range(word_len: i32, start: i32, amount: i32) -> Result<(), String> {
+    if start < 0 {
+        return Err(format!("negative start {}", start));
+    }
+    // last address the request reaches
+    let last = match word_len {
+        constant::WL_BIT => start as i64,
+        constant::WL_COUNTER | constant::WL_TIMER => start as i64 + amount.max(1) as i64 - 1,
+        _ => {
+            let size = amount.max(1) as i64 * constant::data_size_byte(word_len) as i64;
+            (start as i64 + size) * 8 - 1
+        }
+    };
+    if last > 0xFF_FFFF {
+        return Err(format!(
+            "start {} and size {} past the addressable memory",
+            start, amount
+        ));
+    }
+    Ok(())
+}
+
+/// rejects negative amounts and buffers shorter than `amount` elements of `word_len`,
+/// a single bit takes a byte
+fn check_buffer(word_len: i32, amount: i32, buffer_len: usize) -> Result<(), Error> {
+    if amount < 0 {
+        return Err(Error::InvalidInput {
+            input: format!("negative size {}", amount),
+        });
+    }
+    let size = match (word_len, amount) {
+        (constant::WL_BIT, 0) => 0,
+        (constant::WL_BIT, _) => 1,
+        _ => amount as usize * constant::data_size_byte(word_len) as usize,
+    };
+    if buffer_len < size {
+        return Err(Error::BufferSize {
+            expected: size,
+            got: buffer_len,
+        });
+    }
+    Ok(())
+}
+
+/// builds the telegram reading `num_elements` of `word_len` at `start`
+fn read_request(
+    area: constant::Area,
+    db_number: i32,
+    start: i32,
+    word_len: i32,
+    num_elements: i32,
+) -> Vec<u8> {
+    // Setup the telegram
+    let mut request =
+        transport::READ_WRITE_TELEGRAM[..constant::SIZE_HEADER_READ as usize].to_vec();
+
+    // Set DB Number
+    let db_bytes = (db_number as u16).to_be_bytes();
+    request[25] = db_bytes[0];
+    request[26] = db_bytes[1];
+
+    // Set Area
+    request[27] = area as u8;
+
+    // Adjusts Start and word length
+    request[22] = word_len as u8;
+    let mut address = match word_len {
+        constant::WL_BIT | constant::WL_COUNTER | constant::WL_TIMER => start,
+        _ => start << 3,
+    };
+
+    // Num elements
+    let num_elements_bytes = (num_elements as u16).to_be_bytes();
+    request[23] = num_elements_bytes[0];
+    request[24] = num_elements_bytes[1];
+
+    // Address into the PLC (only 3 bytes)
+    request[30] = (address & 0x0FF) as u8;
+    address >>= 8;
+    request[29] = (address & 0x0FF) as u8;
+    address >>= 8;
+    request[28] = (address & 0x0FF) as u8;
+    request
+}
+
+/// area code of an item in the variable table service,
+/// the area is in the high nibble and the element size in the low nibble
+fn var_table_area(item: &S7DataItem) -> Result<u8, Error> {
+    let size = match item.word_len {
+        constant::WL_BIT | constant::WL_BYTE | constant::WL_CHAR => 0x01,
+        constant::WL_WORD | constant::WL_INT => 0x02,
+        constant::WL_DWORD | constant::WL_DINT | constant::WL_REAL => 0x03,
+        _ => 0x00,
+    };
+
+    match item.area {
+        constant::Area::Timer => Ok(0x54),
+        constant::Area::Counter => Ok(0x64),
+        _ if size == 0 => Err(Error::InvalidInput {
+            input: format!("var table word length {}", item.word_len),
+        }),
+        constant::Area::Merker => Ok(size),
+        constant::Area::ProcessInput => Ok(0x10 | size),
+        constant::Area::ProcessOutput => Ok(0x20 | size),
+        constant::Area::DataBausteine => Ok(0x70 | size),
+        constant::Area::DirectPeripheral => Err(Error::InvalidInput {
+            input: "var table area peripheral".to_string(),
+        }),
+        constant::Area::Unknown => Err(Error::InvalidInput {
+            input: "var table area unknown".to_string(),
+        }),
+    }
+}
+
+/// inverse of `var_table_area`
+fn var_table_item_area(code: u8) -> Option<(constant::Area, i32)> {
+    match code {
+        0x54 => return Some((constant::Area::Timer, constant::WL_TIMER)),
+        0x64 => return Some((constant::Area::Counter, constant::WL_COUNTER)),
+        _ => {}
+    }
+
+    let word_len = match code & 0x0F {
+        0x01 => constant::WL_BYTE,
+        0x02 => constant::WL_WORD,
+        0x03 => constant::WL_DWORD,
+        _ => return None,
+    };
+
+    let area = match code >> 4 {
+        0x0 => constant::Area::Merker,
+        0x1 => constant::Area::ProcessInput,
+        0x2 => constant::Area::ProcessOutput,
+        0x7 => constant::Area::DataBausteine,
+        _ => return None,
+    };
+    Some((area, word_len))
+}
+
+/// validates a variable table response and returns the number of items in it
+fn var_table_count(response: &[u8]) -> Result<usize, Error> {
+    if response.len() < transport::VAR_TABLE_MIN_RESPONSE {
+        return Err(Error::Response {
+            code: error::ISO_INVALID_PDU,
+            bytes: response.to_vec(),
+        });
+    }
+
+    let result = BigEndian::read_u16(response[27..].as_ref());
+    if result != 0 {
+        return Err(Error::CPU {
+            code: result as i32,
+            bytes: response.to_vec(),
+        });
+    }
+    if response[29] != 0xFF {
+        return Err(Error::CPU {
+            code: error::CLI_INVALID_PLC_ANSWER,
+            bytes: response.to_vec(),
+        });
+    }
+
+    Ok(BigEndian::read_u16(response[41..].as_ref()) as usize)
+}
+
+/// reads `count` value items starting at `offset`
+fn var_table_values(
+    response: &[u8],
+    mut offset: usize,
+    count: usize,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let mut values = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let (code, data, next) =
+            data_item(response, offset).map_err(|reason| Error::InvalidResponse {
+                reason: format!("var table {}", reason),
+                bytes: response.to_vec(),
+            })?;
+        if code != 0xFF {
+            return Err(Error::CPU {
+                code: code as i32,
+                bytes: response.to_vec(),
+            });
+        }
+        values.push(data.to_vec());
+        offset = next;
+    }
+
+    Ok(values)
+}
+
+/// splits the data item at `offset` of a read or var table response in its return code
+/// and its data, which is empty unless the code is 0xFF, success.
+/// Also returns the offset of the next item.
+fn data_item(response: &[u8], offset: usize) -> Result<(u8, &[u8], usize), String> {
+    if response.len() < offset + 4 {
+        return Err("item header out of bounds".to_string());
+    }
+    let code = response[offset];
+    if code != 0xFF {
+        return Ok((code, &[], offset + 4));
+    }
+
+    let mut size = BigEndian::read_u16(response[offset + 2..].as_ref()) as usize;
+    // Length in bits, except for reals and octet strings
+    match response[offset + 1] as i32 {
+        constant::TS_RES_BIT | constant::TS_RES_BYTE | constant::TS_RES_INT => {
+            size = size.div_ceil(8)
+        }
+        constant::TS_RES_REAL | constant::TS_RES_OCTET => {}
+        _ => {}
+    }
+
+    let start = offset + 4;
+    if response.len() < start + size {
+        return Err("item data out of bounds".to_string());
+    }
+    // Items are aligned to even offsets
+    Ok((
+        code,
+        &response[start..start + size],
+        start + size + size % 2,
+    ))
+}
+
+/// converts the block timestamps, milliseconds of the day followed by the days
+/// since 1984/01/01, to `YYYY/MM/DD HH:MM:SS.mmm`
+fn siemens_timestamp(bytes: &[u8]) -> String {
+    let ms = BigEndian::read_u32(bytes) as i64;
+    let days = BigEndian::read_u16(&bytes[4..]) as i64 + ms / 86_400_000;
+    let ms = ms % 86_400_000;
+
+    // days from 1970/01/01, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 5113 + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}/{:02}/{:02} {:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// converts the version bytes of a module identification record,
+/// `V` then the major, minor and patch numbers as plain bytes
+fn firmware_version(bytes: &[u8]) -> Option<(u8, u8, u8)> {
+    if bytes[0] != b'V' {
+        return None;
+    }
+    Some((bytes[1], bytes[2], bytes[3]))
+}
+
+/// converts the 10 bytes BCD clock: a reserved byte, the century, year, month, day,
+/// hours, minutes, seconds, then 3 digits of milliseconds followed by the weekday
+fn clock_timestamp(bytes: &[u8]) -> Option<String> {
+    let bcd = |byte: u8| {
+        let (high, low) = (byte >> 4, byte & 0x0F);
+        if high > 9 || low > 9 {
+            None
+        } else {
+            Some(high as u32 * 10 + low as u32)
+        }
+    };
+    let digits: Vec<u32> = bytes[1..8].iter().map(|b| bcd(*b)).collect::<Option<_>>()?;
+    let ms = bcd(bytes[8])? * 10 + bcd(bytes[9] >> 4)?;
+
+    let year = match digits[0] {
+        // only 2 digits, S7 dates run from 1990 to 2089
+        0 if digits[1] >= 90 => 1900 + digits[1],
+        0 => 2000 + digits[1],
+        century => century * 100 + digits[1],
+    };
+    Some(format!(
+        "{:04}/{:02}/{:02} {:02}:{:02}:{:02}.{:03}",
+        year, digits[2], digits[3], digits[4], digits[5], digits[6], ms
+    ))
+}
+
+/// reads a fixed size, zero padded, ascii field
+fn to_chars(bytes: &[u8]) -> Result<String, Error> {
+    match str::from_utf8(bytes) {
+        Ok(s) => Ok(s.trim_end_matches(char::from(0)).to_string()),
+        Err(e) => Err(Error::InvalidResponse {
+            bytes: bytes.to_vec(),
+            reason: e.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+fn block_info_response(checksum: u16) -> Vec<u8> {
+    let mut response = vec![0u8; transport::BLOCK_INFO_MIN_RESPONSE];
+    response[29] = 0xFF;
+    BigEndian::write_u16(&mut response[31..], 70);
+    response[42] = 0x21;
+    response[44] = BlockType::DB as u8;
+    BigEndian::write_u16(&mut response[45..], 888);
+    BigEndian::write_i32(&mut response[47..], 164);
+    // 2019/01/01 13:45:30.250
+    BigEndian::write_u32(&mut response[55..], 49_530_250);
+    BigEndian::write_u16(&mut response[59..], 12784);
+    BigEndian::write_u16(&mut response[65..], 0);
+    BigEndian::write_u16(&mut response[73..], 64);
+    response[75..79].copy_from_slice(b"PETR");
+    response[99] = 0x11;
+    BigEndian::write_u16(&mut response[101..], checksum);
+    response
+}
+
+#[cfg(test)]
+fn loopback_client<F>(plc: F) -> (Client<transport::Loopback>, std::thread::JoinHandle<()>)
+where
+    F: FnOnce(transport::Loopback) + Send + 'static,
+{
+    let (client_end, plc_end) = transport::loopback();
+
+    let handle = std::thread::spawn(move || {
+        plc_end.recv().unwrap();
+        let mut negotiated = vec![0u8; 27];
+        BigEndian::write_u16(&mut negotiated[25..], 480);
+        plc_end.reply(&negotiated).unwrap();
+        plc(plc_end);
+    });
+
+    (Client::new(client_end).unwrap(), handle)
+}
+
+#[test]
+fn test_negotiation_info() {
+    let (client_end, plc) = transport::loopback();
+
+    let plc = std::thread::spawn(move || {
+        plc.recv().unwrap();
+        let mut negotiated = vec![0u8; 27];
+        BigEndian::write_u16(&mut negotiated[21..], 3);
+        BigEndian::write_u16(&mut negotiated[23..], 2);
+        BigEndian::write_u16(&mut negotiated[25..], 240);
+        plc.reply(&negotiated).unwrap();
+    });
+
+    let cl = Client::new(client_end).unwrap();
+    let info = cl.connection_info();
+    assert_eq!(info.peer_addr, None);
+    assert!(matches!(info.connection_type, transport::Connection::PG));
+    assert_eq!(info.negotiation, cl.negotiation_info());
+    assert_eq!(
+        cl.negotiation_info(),
+        transport::NegotiationInfo {
+            pdu_length: 240,
+            max_amq_calling: 3,
+            max_amq_called: 2,
+        }
+    );
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_negotiated_pdu_too_small() {
+    let (client_end, plc) = transport::loopback();
+
+    let plc = std::thread::spawn(move || {
+        plc.recv().unwrap();
+        let mut negotiated = vec![0u8; 27];
+        BigEndian::write_u16(&mut negotiated[25..], 32);
+        plc.reply(&negotiated).unwrap();
+    });
+
+    match Client::new(client_end) {
+        Err(Error::PduTooSmall(32)) => {}
+        other => panic!("expected PduTooSmall got {:?}", other.map(|_| ())),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_get_ag_block_info() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(request[30], BlockType::DB as u8);
+        assert_eq!(&request[31..36], b"00888");
+        plc.reply(&block_info_response(0xBEEF)).unwrap();
+
+        plc.recv().unwrap();
+        plc.reply(&block_info_response(0xBEEF)).unwrap();
+
+        plc.recv().unwrap();
+        plc.reply(&block_info_response(0xCAFE)).unwrap();
+
+        // only in load memory
+        plc.recv().unwrap();
+        let mut response = block_info_response(0xBEEF);
+        BigEndian::write_u16(&mut response[73..], 0);
+        plc.reply(&response).unwrap();
+    });
+
+    let first = cl.get_ag_block_info(BlockType::DB, 888).unwrap();
+    assert_eq!(first.block_number, 888);
+    assert_eq!(first.load_size, 164);
+    assert_eq!(first.mc7_size, 64);
+    assert_eq!(first.checksum, 0xBEEF);
+    assert_eq!(first.code_date, "2019/01/01 13:45:30.250");
+    assert_eq!(first.interface_date, "1984/01/01 00:00:00.000");
+    assert_eq!(first.author, "PETR");
+
+    assert!(first.flags().linked);
+    assert!(first.flags().write_protected);
+    assert!(!first.flags().standard_block);
+
+    let same = cl.get_ag_block_info(BlockType::DB, 888).unwrap();
+    assert_eq!(first, same);
+
+    let changed = cl.get_ag_block_info(BlockType::DB, 888).unwrap();
+    assert_ne!(first, changed);
+
+    assert!(first.has_code());
+    let load_only = cl.get_ag_block_info(BlockType::DB, 888).unwrap();
+    assert_eq!(load_only.load_size, 164);
+    assert!(!load_only.has_code());
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_get_ag_block_info_guards() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // the data part is cut short, as some SDB answers are
+        plc.recv().unwrap();
+        let mut response = block_info_response(0);
+        BigEndian::write_u16(&mut response[31..], 40);
+        plc.reply(&response).unwrap();
+
+        // about another block
+        plc.recv().unwrap();
+        plc.reply(&block_info_response(0)).unwrap();
+    });
+
+    for number in [888u16, 7].iter() {
+        match cl.get_ag_block_info(BlockType::SDB, *number) {
+            Err(Error::InvalidResponse { .. }) => {}
+            other => panic!("expected InvalidResponse got {:?}", other),
+        }
+    }
+    plc.join().unwrap();
+}
+
+#[cfg(test)]
+fn block_list_response(numbers: &[u16], more: bool) -> Vec<u8> {
+    let mut response = vec![0u8; transport::LIST_BLOCKS_MIN_RESPONSE];
+    response[24] = 7;
+    response[26] = more as u8;
+    response[29] = 0xFF;
+    BigEndian::write_u16(
+        &mut response[31..],
+        (numbers.len() * transport::LIST_BLOCKS_ENTRY_SIZE) as u16,
+    );
+    for number in numbers {
+        let mut entry = [0u8, 0, 0x22, 0x05];
+        BigEndian::write_u16(&mut entry, *number);
+        response.extend_from_slice(&entry);
+    }
+    response
+}
+
+#[test]
+fn test_block_directory() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        for block_type in BlockType::ALL.iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(request[30], *block_type as u8);
+
+            match block_type {
+                BlockType::DB => {
+                    plc.reply(&block_list_response(&[1, 888], true)).unwrap();
+                    let next = plc.recv().unwrap();
+                    assert_eq!(next[24], 7);
+                    plc.reply(&block_list_response(&[889], false)).unwrap();
+
+                    for number in [1u16, 888, 889].iter() {
+                        let request = plc.recv().unwrap();
+                        assert_eq!(request[30], BlockType::DB as u8);
+                        let mut response = block_info_response(0);
+                        BigEndian::write_u16(&mut response[45..], *number);
+                        plc.reply(&response).unwrap();
+                    }
+                }
+                _ => {
+                    let mut empty = vec![0u8; transport::LIST_BLOCKS_MIN_RESPONSE];
+                    empty[29] = 0x0A;
+                    plc.reply(&empty).unwrap();
+                }
+            }
+        }
+    });
+
+    let directory = cl.block_directory().unwrap();
+    let numbers: Vec<u16> = directory.iter().map(|e| e.block_number).collect();
+    assert_eq!(numbers, vec![1, 888, 889]);
+    assert!(directory.iter().all(|e| e.block_type == BlockType::DB));
+    assert_eq!(directory[0].load_size, 164);
+    assert_eq!(directory[0].mc7_size, 64);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_db_inventory() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(request[30], BlockType::DB as u8);
+        plc.reply(&block_list_response(&[1, 888], false)).unwrap();
+
+        for (number, size) in [(1u16, 12u16), (888, 64)].iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(request[30], BlockType::DB as u8);
+            let mut response = block_info_response(0);
+            BigEndian::write_u16(&mut response[45..], *number);
+            BigEndian::write_u16(&mut response[73..], *size);
+            plc.reply(&response).unwrap();
+        }
+    });
+
+    assert_eq!(cl.db_inventory().unwrap(), vec![(1, 12), (888, 64)]);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_ag_read_timed() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        plc.recv().unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[1, 2]);
+        plc.reply(&response).unwrap();
+    });
+
+    let buffer = &mut [0u8; 2];
+    let took = cl.ag_read_timed(888, 0, 2, buffer).unwrap();
+    assert_eq!(buffer, &[1, 2]);
+    assert!(took >= Duration::from_millis(20));
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_run_watchdog() {
+    fn status_response(status: constant::CpuStatus) -> Vec<u8> {
+        let mut response = vec![0u8; transport::PLC_STATUS_MIN_RESPONSE];
+        response[44] = status as u8;
+        response
+    }
+
+    let (mut cl, plc) = loopback_client(|plc| {
+        // stopped, the read isn't sent
+        let request = plc.recv().unwrap();
+        assert_eq!(request[17..], transport::PLC_STATUS_TELEGRAM[17..]);
+        plc.reply(&status_response(constant::CpuStatus::Stop))
+            .unwrap();
+
+        // checked again, back in RUN
+        plc.recv().unwrap();
+        plc.reply(&status_response(constant::CpuStatus::Run))
+            .unwrap();
+        for value in 1..3 {
+            let request = plc.recv().unwrap();
+            assert_eq!(request[17], 0x04);
+            let mut response = vec![0u8; 25];
+            response[21] = 0xFF;
+            response.extend_from_slice(&[value, value]);
+            plc.reply(&response).unwrap();
+        }
+
+        let request = plc.recv().unwrap();
+        assert_eq!(request[17..], transport::PLC_STATUS_TELEGRAM[17..]);
+        plc.reply(&status_response(constant::CpuStatus::Stop))
+            .unwrap();
+    });
+
+    cl.set_run_watchdog(Some(Duration::from_secs(60)));
+    let buffer = &mut [0u8; 2];
+    match cl.ag_read(888, 0, 2, buffer) {
+        Err(Error::NotRunning(constant::CpuStatus::Stop)) => {}
+        result => panic!("unexpected {:?}", result),
+    }
+    cl.ag_read(888, 0, 2, buffer).unwrap();
+    assert_eq!(buffer, &[1, 1]);
+    // within the cadence, no status check
+    cl.ag_read(888, 0, 2, buffer).unwrap();
+    assert_eq!(buffer, &[2, 2]);
+
+    // pipelined reads are checked too, stopped again
+    cl.set_run_watchdog(Some(Duration::new(0, 0)));
+    let items = [S7DataItem::new(
+        constant::Area::DataBausteine,
+        constant::WL_BYTE,
+        888,
+        0,
+        2,
+    )];
+    match cl.read_pipelined(&items) {
+        Err(Error::NotRunning(constant::CpuStatus::Stop)) => {}
+        result => panic!("unexpected {:?}", result),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_probe_max_read() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // a gateway passing 300 bytes, truncating up to 400 and refusing more
+        while let Ok(request) = plc.recv() {
+            assert_eq!(BigEndian::read_u16(&request[25..]), 888);
+            let size = BigEndian::read_u16(&request[23..]) as usize;
+            let mut response = vec![0u8; 25];
+            if size > 400 {
+                response.truncate(19);
+                BigEndian::write_u16(&mut response[17..], 0x8500);
+            } else {
+                response[21] = 0xFF;
+                response.resize(25 + size.min(300), 0);
+            }
+            plc.reply(&response).unwrap();
+        }
+    });
+
+    assert_eq!(cl.probe_max_read(888).unwrap(), 300);
+    assert!(cl.last_round_trips() <= 16);
+    drop(cl);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_check_word_len() {
+    assert!(check_word_len(constant::Area::DataBausteine, constant::WL_REAL).is_ok());
+    assert!(check_word_len(constant::Area::Counter, constant::WL_BYTE).is_ok());
+    assert!(check_word_len(constant::Area::Timer, constant::WL_TIMER).is_ok());
+    assert!(check_word_len(constant::Area::DataBausteine, constant::WL_TIMER).is_err());
+    assert!(check_word_len(constant::Area::Merker, constant::WL_COUNTER).is_err());
+    assert!(check_word_len(constant::Area::Merker, 0x42).is_err());
+    assert!(check_word_len(constant::Area::Unknown, constant::WL_BYTE).is_err());
+    assert!(check_word_len(constant::Area::DirectPeripheral, constant::WL_WORD).is_ok());
+    assert!(check_word_len(constant::Area::DirectPeripheral, constant::WL_BIT).is_err());
+
+    assert!(check_address(constant::Area::DataBausteine, constant::WL_BYTE, 65535).is_ok());
+    assert!(check_address(constant::Area::DataBausteine, constant::WL_BYTE, 100_000).is_err());
+    assert!(check_address(constant::Area::DataBausteine, constant::WL_BYTE, -1).is_err());
+    // ignored outside the data blocks
+    assert!(check_address(constant::Area::Merker, constant::WL_BYTE, 100_000).is_ok());
+
+    let (mut cl, plc) = loopback_client(|_| {});
+    match cl.ag_read(100_000, 0, 1, &mut [0u8; 1]) {
+        Err(Error::InvalidInput { .. }) => {}
+        other => panic!("expected InvalidInput got {:?}", other),
+    }
+
+    let items = [
+        S7DataItem::new(constant::Area::Merker, constant::WL_BYTE, 0, 0, 1),
+        S7DataItem::new(constant::Area::DataBausteine, constant::WL_TIMER, 888, 0, 1),
+    ];
+
+    match cl.read_var_table(&items) {
+        Err(Error::InvalidInput { input }) => assert!(input.starts_with("item 1:")),
+        other => panic!("expected InvalidInput got {:?}", other),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_operation_timeout() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // only the first of the chunks gets requested
+        plc.recv().unwrap();
+        std::thread::sleep(Duration::from_millis(30));
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[0u8; 462]);
+        plc.reply(&response).unwrap();
+    });
+
+    cl.set_operation_timeout(Some(Duration::from_millis(10)));
+    let buffer = &mut [0u8; 1000];
+    match cl.ag_read(888, 0, 1000, buffer) {
+        Err(Error::Timeout) => {}
+        other => panic!("expected Timeout got {:?}", other),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_db_range_reader() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // DB100 in two chunks
+        for (start, length) in [(0u16, 462u16), (462, 38)].iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(BigEndian::read_u16(&request[25..]), 100);
+            assert_eq!(BigEndian::read_u16(&request[23..]), *length);
+            assert_eq!(
+                BigEndian::read_u32(&request[27..]) & 0xFFFFFF,
+                *start as u32 * 8
+            );
+
+            let mut response = vec![0u8; 25];
+            response[21] = 0xFF;
+            response.resize(25 + *length as usize, 1);
+            plc.reply(&response).unwrap();
+        }
+
+        // DB101 doesn't exist
+        plc.recv().unwrap();
+        let mut response = vec![0u8; 25];
+        response[21] = 0x0A;
+        plc.reply(&response).unwrap();
+
+        // DB102
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[25..]), 102);
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[2u8; 10]);
+        plc.reply(&response).unwrap();
+    });
+
+    let segments = [(100, 0, 500), (101, 0, 2000), (102, 0, 10)];
+    let mut reader = cl.db_range_reader(&segments);
+
+    assert_eq!(reader.next().unwrap().unwrap().len(), 462);
+    assert_eq!(reader.next().unwrap().unwrap().len(), 38);
+    assert_eq!(reader.segment(), 0);
+    assert!(reader.next().unwrap().is_err());
+    assert_eq!(reader.segment(), 1);
+    assert_eq!(reader.next().unwrap().unwrap(), vec![2u8; 10]);
+    assert_eq!(reader.segment(), 2);
+    assert!(reader.next().is_none());
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_multi_vars() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[2..]) as usize, request.len());
+        assert_eq!(request.len(), 19 + 3 * 12);
+        assert_eq!(BigEndian::read_u16(&request[13..]), 2 + 3 * 12);
+        assert_eq!(request[17], 0x04);
+        assert_eq!(request[18], 3);
+        // MB10
+        assert_eq!(request[27], constant::Area::Merker as u8);
+        // DB888.DBD8 as a single real
+        assert_eq!(request[19 + 12 + 3], constant::WL_REAL as u8);
+        assert_eq!(BigEndian::read_u16(&request[19 + 12 + 4..]), 1);
+        assert_eq!(BigEndian::read_u16(&request[19 + 12 + 6..]), 888);
+
+        let mut response = vec![0u8; 21];
+        response[20] = 3;
+        // MB10, padded
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 8, 0x2A, 0]);
+        // DB888.DBD8, reals are counted in bytes
+        response.extend_from_slice(&[0xFF, constant::TS_RES_REAL as u8, 0, 4]);
+        response.extend_from_slice(&53.5f32.to_be_bytes());
+        // DB999 doesn't exist
+        response.extend_from_slice(&[0x0A, 0, 0, 0]);
+        plc.reply(&response).unwrap();
+    });
+
+    let mut items = [
+        S7DataItem::new(constant::Area::Merker, constant::WL_BYTE, 0, 10, 1),
+        S7DataItem::new(constant::Area::DataBausteine, constant::WL_REAL, 888, 8, 1),
+        S7DataItem::new(constant::Area::DataBausteine, constant::WL_BYTE, 999, 0, 1),
+    ];
+    cl.read_multi_vars(&mut items).unwrap();
+
+    assert_eq!(items[0].buffer, vec![0x2A]);
+    assert!(items[0].err.is_none());
+    assert_eq!(items[1].buffer, 53.5f32.to_be_bytes().to_vec());
+    assert!(items[2].buffer.is_empty());
+    match items[2].err {
+        // the whole telegram is kept for the logs
+        Some(Error::CPU {
+            code: 0x0A,
+            ref bytes,
+        }) => assert_eq!(bytes[35..], [0x0A, 0, 0, 0]),
+        ref other => panic!("expected CPU error got {:?}", other),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_multi_vars_word_lengths() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        // 2 ints at DB1.DBW4, 3 chars at DB1.DBB10
+        assert_eq!(request[19 + 3], constant::WL_INT as u8);
+        assert_eq!(BigEndian::read_u16(&request[19 + 4..]), 2);
+        assert_eq!(BigEndian::read_u32(&request[19 + 8..]) & 0xFFFFFF, 4 * 8);
+        assert_eq!(request[31 + 3], constant::WL_CHAR as u8);
+        assert_eq!(BigEndian::read_u16(&request[31 + 4..]), 3);
+
+        let mut response = vec![0u8; 21];
+        response[20] = 2;
+        response.extend_from_slice(&[0xFF, constant::TS_RES_INT as u8, 0, 32]);
+        response.extend_from_slice(&[0xFF, 0xFE, 0x00, 0x05]);
+        response.extend_from_slice(&[0xFF, constant::TS_RES_OCTET as u8, 0, 3]);
+        response.extend_from_slice(b"abc");
+        plc.reply(&response).unwrap();
+    });
+
+    let mut items = [
+        S7DataItem::new(constant::Area::DataBausteine, constant::WL_INT, 1, 4, 2),
+        S7DataItem::new(constant::Area::DataBausteine, constant::WL_CHAR, 1, 10, 3),
+    ];
+    cl.read_multi_vars(&mut items).unwrap();
+    assert_eq!(items[0].buffer, vec![0xFF, 0xFE, 0x00, 0x05]);
+    assert_eq!(items[1].buffer, b"abc".to_vec());
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_multi_vars_word_elements() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        // 3 words at DB2.DBW0, 2 dwords at MD20
+        assert_eq!(request[19 + 3], constant::WL_WORD as u8);
+        assert_eq!(BigEndian::read_u16(&request[19 + 4..]), 3);
+        assert_eq!(BigEndian::read_u16(&request[19 + 6..]), 2);
+        assert_eq!(BigEndian::read_u32(&request[19 + 8..]) & 0xFFFFFF, 0);
+        assert_eq!(request[31 + 3], constant::WL_DWORD as u8);
+        assert_eq!(BigEndian::read_u16(&request[31 + 4..]), 2);
+        assert_eq!(request[31 + 8], constant::Area::Merker as u8);
+        assert_eq!(BigEndian::read_u32(&request[31 + 8..]) & 0xFFFFFF, 20 * 8);
+
+        // both answered as byte transport sizes, lengths in bits
+        let mut response = vec![0u8; 21];
+        response[20] = 2;
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 48]);
+        response.extend_from_slice(&[0x00, 0x01, 0x00, 0x02, 0x00, 0x03]);
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 64]);
+        response.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF, 0x00, 0x00, 0x00, 0x2A]);
+        plc.reply(&response).unwrap();
+    });
+
+    let mut items = [
+        S7DataItem::new(constant::Area::DataBausteine, constant::WL_WORD, 2, 0, 3),
+        S7DataItem::new(constant::Area::Merker, constant::WL_DWORD, 0, 20, 2),
+    ];
+    cl.read_multi_vars(&mut items).unwrap();
+    let words: Vec<u16> = items[0].buffer.chunks(2).map(BigEndian::read_u16).collect();
+    assert_eq!(words, vec![1, 2, 3]);
+    assert_eq!(items[1].buffer.len(), 8);
+    assert_eq!(BigEndian::read_u32(&items[1].buffer[4..]), 42);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_multi_vars_word_elements_too_long() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        plc.recv().unwrap();
+        // 2 words requested, 3 answered
+        let mut response = vec![0u8; 21];
+        response[20] = 1;
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 48]);
+        response.extend_from_slice(&[0; 6]);
+        plc.reply(&response).unwrap();
+    });
+
+    let mut items = [S7DataItem::new(
+        constant::Area::DataBausteine,
+        constant::WL_WORD,
+        2,
+        0,
+        2,
+    )];
+    match cl.read_multi_vars(&mut items) {
+        Err(Error::InvalidResponse { .. }) => {}
+        result => panic!("unexpected {:?}", result),
+    }
+    plc.join().unwrap();
+}
 
-        while tot_elements > 0 {
-            let mut num_elements = tot_elements;
+#[test]
+fn test_read_multi_vars_reuses_buffers() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        for value in 1..3 {
+            plc.recv().unwrap();
+            let mut response = vec![0u8; 21];
+            response[20] = 1;
+            response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 32]);
+            response.extend_from_slice(&[value; 4]);
+            plc.reply(&response).unwrap();
+        }
+    });
 
-            if num_elements > max_elements {
-                num_elements = max_elements;
-            }
+    let mut items = [S7DataItem::new(
+        constant::Area::DataBausteine,
+        constant::WL_DWORD,
+        1,
+        0,
+        1,
+    )];
+    items[0].buffer = Vec::with_capacity(4);
+    let allocation = items[0].buffer.as_ptr();
 
-            let size_requested = num_elements * word_size;
-            // Setup the telegram
-            let mut request =
-                transport::READ_WRITE_TELEGRAM[..constant::SIZE_HEADER_READ as usize].to_vec();
+    cl.read_multi_vars(&mut items).unwrap();
+    assert_eq!(items[0].buffer, vec![1; 4]);
+    cl.read_multi_vars(&mut items).unwrap();
+    assert_eq!(items[0].buffer, vec![2; 4]);
+    assert_eq!(items[0].buffer.as_ptr(), allocation);
+    plc.join().unwrap();
+}
 
-            // Set DB Number
-            request[25] = db_bytes[0];
-            request[26] = db_bytes[1];
-
-            // Set Area
-            request[27] = area as u8;
-            // match area {
-            //     Area::DataBausteine => request[27] = area as u8,
-            //     _ => {}
-            // }
-
-            // Adjusts Start and word length
-            let mut address = match word_len {
-                constant::WL_BIT | constant::WL_COUNTER | constant::WL_TIMER => {
-                    request[22] = word_len as u8;
-                    start
-                }
-                _ => start << 3,
-            };
+#[test]
+fn test_read_bits() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        // MB0..MB3 and DB1.DBB10
+        assert_eq!(request[18], 2);
+        assert_eq!(request[19 + 3], constant::WL_BYTE as u8);
+        assert_eq!(BigEndian::read_u16(&request[19 + 4..]), 4);
+        assert_eq!(request[19 + 8], constant::Area::Merker as u8);
+        assert_eq!(BigEndian::read_u16(&request[31 + 4..]), 1);
+        assert_eq!(BigEndian::read_u16(&request[31 + 6..]), 1);
+        assert_eq!(BigEndian::read_u32(&request[31 + 8..]) & 0xFFFFFF, 10 * 8);
 
-            // Num elements
-            let num_elements_bytes = (num_elements as u16).to_be_bytes();
-            request[23] = num_elements_bytes[0];
-            request[24] = num_elements_bytes[1];
+        let mut response = vec![0u8; 21];
+        response[20] = 2;
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 32]);
+        response.extend_from_slice(&[0b1000_0010, 0, 0, 0b0000_0000]);
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 8, 0b100, 0]);
+        plc.reply(&response).unwrap();
+    });
 
-            // Address into the PLC (only 3 bytes)
-            request[30] = (address & 0x0FF) as u8;
-            address = address >> 8;
-            request[29] = (address & 0x0FF) as u8;
-            address = address >> 8;
-            request[28] = (address & 0x0FF) as u8;
+    let bits: Vec<Address> = ["M3.0", "M0.1", "DB1.DBX10.2", "M0.7", "M0.0"]
+        .iter()
+        .map(|address| address.parse().unwrap())
+        .collect();
+    let values: Vec<bool> = cl
+        .read_bits(&bits)
+        .unwrap()
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    assert_eq!(values, vec![false, true, true, true, false]);
+    plc.join().unwrap();
 
-            let result = self.transport.send(request.as_slice());
+    for wrong in ["MW0", "DBX0.0"].iter() {
+        match cl.read_bits(&[wrong.parse().unwrap()]) {
+            Err(Error::InvalidInput { .. }) => {}
+            other => panic!("expected InvalidInput got {:?}", other),
+        }
+    }
+}
 
-            match result {
-                Ok(response) => {
-                    if response.len() < 25 {
-                        return Err(Error::Response {
-                            code: error::ISO_INVALID_DATA_SIZE,
-                        });
-                    }
+#[test]
+fn test_read_bits_far_apart() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // MB0 and MB1000 as two items of a byte, not a single item of 1001 bytes
+        let request = plc.recv().unwrap();
+        assert_eq!(request[18], 2);
+        for (i, start) in [0u32, 1000].iter().enumerate() {
+            let item = 19 + i * 12;
+            assert_eq!(BigEndian::read_u16(&request[item + 4..]), 1);
+            assert_eq!(
+                BigEndian::read_u32(&request[item + 8..]) & 0xFFFFFF,
+                start * 8
+            );
+        }
 
-                    if response[21] != 0xFF {
-                        return Err(Error::CPU {
-                            code: response[21] as i32,
-                        });
-                    }
-                    let (mut i, end): (usize, usize) = (25, 25 + (size_requested as usize));
-
-                    //copy response to buffer
-                    for k in offset..size_requested {
-                        if i == end {
-                            break;
-                        }
-                        buffer[k as usize] = response[i];
-                        i += 1;
-                    }
-                    offset += size_requested;
-                }
-                Err(e) => {
-                    return Err(e);
-                }
+        let mut response = vec![0u8; 21];
+        response[20] = 2;
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 8, 0b1, 0]);
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 8, 0b10]);
+        plc.reply(&response).unwrap();
+
+        // a bit every 10 bytes up to MB1000 splits in items of at most 480 - 18 bytes,
+        // each filling a request on its own
+        for expected in [461u16, 461, 61].iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(request[18], 1);
+            assert_eq!(BigEndian::read_u16(&request[19 + 4..]), *expected);
+
+            let mut response = vec![0u8; 21];
+            response[20] = 1;
+            response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8]);
+            response.extend_from_slice(&(*expected * 8).to_be_bytes());
+            response.extend(vec![1u8; *expected as usize]);
+            plc.reply(&response).unwrap();
+        }
+    });
+
+    let bits: Vec<Address> = ["M1000.1", "M0.0", "M1000.0"]
+        .iter()
+        .map(|address| address.parse().unwrap())
+        .collect();
+    let values: Vec<bool> = cl
+        .read_bits(&bits)
+        .unwrap()
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    assert_eq!(values, vec![true, true, false]);
+
+    let bits: Vec<Address> = (0..=100)
+        .map(|i| format!("M{}.0", i * 10).parse().unwrap())
+        .collect();
+    let values = cl.read_bits(&bits).unwrap();
+    assert!(values.iter().all(|(_, value)| *value));
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_multi_vars_oversized_item() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        for reported in [0xFFF8u16, 16].iter() {
+            plc.recv().unwrap();
+            let mut response = vec![0u8; 21];
+            response[20] = 1;
+            response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8]);
+            response.extend_from_slice(&reported.to_be_bytes());
+            response.extend_from_slice(&[1, 2]);
+            plc.reply(&response).unwrap();
+        }
+    });
+
+    // a huge size past the end of the response, then more than requested
+    for _ in 0..2 {
+        let mut items = [S7DataItem::new(
+            constant::Area::Merker,
+            constant::WL_BYTE,
+            0,
+            0,
+            1,
+        )];
+        match cl.read_multi_vars(&mut items) {
+            Err(Error::InvalidResponse { .. }) => {}
+            other => panic!("expected InvalidResponse got {:?}", other),
+        }
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_multi_read() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(request[18], 3);
+        // DB888.DBX8.4
+        assert_eq!(request[19 + 3], constant::WL_BIT as u8);
+        assert_eq!(request[19 + 11], 8 * 8 + 4);
+
+        let mut response = vec![0u8; 21];
+        response[20] = 3;
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BIT as u8, 0, 1, 1, 0]);
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 32]);
+        response.extend_from_slice(&121.5f32.to_be_bytes());
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 16]);
+        response.extend_from_slice(&(-7i16).to_be_bytes());
+        plc.reply(&response).unwrap();
+    });
+
+    let values = MultiRead::new()
+        .bool(888, 8.4)
+        .real(888, 12)
+        .int(889, 0)
+        .execute(&mut cl)
+        .unwrap();
+    assert_eq!(
+        values,
+        vec![Value::Bool(true), Value::Real(121.5), Value::Int(-7)]
+    );
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_multi_read_batches() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // at most MAX_VARS items per request
+        for count in [transport::MAX_VARS, 5].iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(request[18] as usize, *count);
+
+            let mut response = vec![0u8; 21];
+            response[20] = *count as u8;
+            for _ in 0..*count {
+                response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 32]);
+                response.extend_from_slice(&1.0f32.to_be_bytes());
             }
+            plc.reply(&response).unwrap();
+        }
+    });
 
-            tot_elements -= num_elements;
-            start += num_elements * word_size
+    let read = (0..25).fold(MultiRead::new(), |read, i| read.real(888, i * 4));
+    assert_eq!(read.execute(&mut cl).unwrap(), vec![Value::Real(1.0); 25]);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_full_db() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        for _ in 0..2 {
+            let request = plc.recv().unwrap();
+            assert_eq!(&request[31..36], b"00888");
+            plc.reply(&block_info_response(0)).unwrap();
         }
-        Ok(())
+
+        // 64 bytes from DB888.DBB0
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[23..]), 64);
+        assert_eq!(BigEndian::read_u16(&request[25..]), 888);
+
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[0xAB; 64]);
+        plc.reply(&response).unwrap();
+    });
+
+    match cl.read_full_db(888, &mut [0u8; 10]) {
+        Err(Error::BufferSize {
+            expected: 64,
+            got: 10,
+        }) => {}
+        other => panic!("expected BufferSize got {:?}", other),
     }
 
-    fn write(
-        &mut self,
-        area: Area,
-        db_number: i32,
-        mut start: i32,
-        mut amount: i32,
-        mut word_len: i32,
-        buffer: &mut Vec<u8>,
-    ) -> Result<(), Error> {
-        // Some adjustment
-        word_len = match area {
-            Area::Counter => constant::WL_COUNTER,
-            Area::Timer => constant::WL_TIMER,
-            _ => word_len,
-        };
+    let buffer = &mut [0u8; 100];
+    assert_eq!(cl.read_full_db(888, buffer).unwrap(), 64);
+    assert!(buffer[..64].iter().all(|b| *b == 0xAB));
+    assert!(buffer[64..].iter().all(|b| *b == 0));
+    plc.join().unwrap();
+}
 
-        // Calc Word size
-        let mut word_size = constant::data_size_byte(word_len);
+#[test]
+fn test_get_ag_block_info_invalid_author() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        plc.recv().unwrap();
+        let mut response = block_info_response(0);
+        response[75..79].copy_from_slice(&[0xC3, 0x28, 0xFF, 0xFE]);
+        plc.reply(&response).unwrap();
+    });
 
-        if word_size == 0 {
-            return Err(Error::Response {
-                code: error::ISO_INVALID_DATA_SIZE,
-            });
+    match cl.get_ag_block_info(BlockType::DB, 888) {
+        Err(Error::InvalidResponse { bytes, .. }) => {
+            assert_eq!(&bytes[..4], &[0xC3, 0x28, 0xFF, 0xFE])
         }
+        other => panic!("expected InvalidResponse got {:?}", other),
+    }
+    plc.join().unwrap();
+}
 
-        if word_len == constant::WL_BIT {
-            amount = 1; // Only 1 bit can be transferred at time
-        } else {
-            if word_len != constant::WL_COUNTER && word_len != constant::WL_TIMER {
-                amount = amount * word_size;
-                word_size = 1;
-                word_len = constant::WL_BYTE;
-            }
+#[test]
+fn test_siemens_timestamp() {
+    // code date of a block saved 2023/06/15 08:30:05.123 by the programming software
+    assert_eq!(
+        siemens_timestamp(&[0x01, 0xD2, 0xFF, 0x43, 0x38, 0x4A]),
+        "2023/06/15 08:30:05.123"
+    );
+    assert_eq!(
+        siemens_timestamp(&[0x05, 0x26, 0x5B, 0xFF, 0x00, 0x00]),
+        "1984/01/01 23:59:59.999"
+    );
+    // leap day
+    assert_eq!(
+        siemens_timestamp(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x3B]),
+        "1984/02/29 00:00:00.000"
+    );
+}
+
+#[test]
+fn test_read_full_db_with_info() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        plc.recv().unwrap();
+        plc.reply(&block_info_response(0xBEEF)).unwrap();
+
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[23..]), 64);
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[7u8; 64]);
+        plc.reply(&response).unwrap();
+    });
+
+    let (info, data) = cl.read_full_db_with_info(888).unwrap();
+    assert_eq!(info.checksum, 0xBEEF);
+    assert_eq!(data, vec![7u8; 64]);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_pdu_ref() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // 500 bytes take two requests
+        for (reference, size) in [(0xFFFFu16, 462usize), (0, 38)].iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(BigEndian::read_u16(&request[11..]), *reference);
+
+            let mut response = vec![0u8; 25];
+            response[11..13].copy_from_slice(&request[11..13]);
+            response[21] = 0xFF;
+            response.extend_from_slice(&vec![0u8; *size]);
+            plc.reply(&response).unwrap();
         }
+    });
 
-        let mut offset: i32 = 0;
-        let pdu_length = self.transport.pdu_length();
-        let max_elements = (pdu_length - 35) / word_size; // 35 = Reply telegram header
-        let mut tot_elements = amount;
+    assert_eq!(cl.last_pdu_ref(), 0);
+    cl.set_pdu_ref_base(0xFFFF);
+    cl.ag_read(888, 0, 500, &mut [0u8; 500]).unwrap();
+    assert_eq!(cl.last_pdu_ref(), 0);
+    assert_eq!(cl.last_round_trips(), 2);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_empty_and_short_buffers() {
+    // nothing reaches the PLC
+    let (mut cl, plc) = loopback_client(|_| {});
+
+    cl.ag_read(888, 0, 0, &mut []).unwrap();
+    cl.ag_write(888, 0, 0, &mut []).unwrap();
+    assert_eq!(cl.last_round_trips(), 0);
+
+    for result in [
+        cl.ag_read(888, 0, 4, &mut [0u8; 2]),
+        cl.ag_write(888, 0, 4, &mut [0u8; 2]),
+        cl.tm_write(0, 2, &mut [0u8; 2]),
+    ]
+    .iter()
+    {
+        match result {
+            Err(Error::BufferSize { got: 2, .. }) => {}
+            other => panic!("expected BufferSize got {:?}", other),
+        }
+    }
+    match cl.ag_write(888, 0, -1, &mut [0u8; 2]) {
+        Err(Error::InvalidInput { .. }) => {}
+        other => panic!("expected InvalidInput got {:?}", other),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_chunks() {
+    let data: Vec<u8> = (0..500).map(|i| i as u8).collect();
+    let expected = data.clone();
+
+    let (mut cl, plc) = loopback_client(move |plc| {
+        // 480 - 18 bytes of header leave 462 bytes per request
+        for (start, size) in [(0usize, 462usize), (462, 38)].iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(BigEndian::read_u16(&request[23..]), *size as u16);
+            let mut response = vec![0u8; 25];
+            response[21] = 0xFF;
+            response.extend_from_slice(&data[*start..start + size]);
+            plc.reply(&response).unwrap();
+        }
+
+        // less data than requested
+        plc.recv().unwrap();
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[1, 2]);
+        plc.reply(&response).unwrap();
+    });
+
+    let buffer = &mut vec![0u8; 500];
+    cl.ag_read(888, 0, 500, buffer).unwrap();
+    assert_eq!(buffer, &expected);
+
+    match cl.ag_read(888, 0, 4, buffer) {
+        Err(Error::Response { code, .. }) => assert_eq!(code, error::ISO_INVALID_DATA_SIZE),
+        other => panic!("expected Response got {:?}", other),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_full_db_to() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        plc.recv().unwrap();
+        let mut response = block_info_response(0);
+        BigEndian::write_u16(&mut response[45..], 888);
+        BigEndian::write_u16(&mut response[73..], 500);
+        plc.reply(&response).unwrap();
+
+        for (start, size) in [(0u32, 462u16), (462, 38)].iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(BigEndian::read_u16(&request[23..]), *size);
+            assert_eq!(BigEndian::read_u32(&request[27..]) & 0xFFFFFF, start * 8);
+            let mut response = vec![0u8; 25];
+            response[21] = 0xFF;
+            response.extend((0..*size).map(|i| (*start as u16 + i) as u8));
+            plc.reply(&response).unwrap();
+        }
+    });
+
+    let mut sink = Vec::new();
+    assert_eq!(cl.read_full_db_to(888, &mut sink).unwrap(), 500);
+    assert_eq!(sink, (0..500).map(|i| i as u8).collect::<Vec<u8>>());
+    assert_eq!(cl.last_round_trips(), 2);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_ag_write_all() {
+    let data: Vec<u8> = (0..500).map(|i| i as u8).collect();
+    let expected = data.clone();
+
+    let (mut cl, plc) = loopback_client(move |plc| {
+        // 480 - 35 bytes of header leave 445 bytes per request
+        for (start, size) in [(100usize, 445usize), (545, 55)].iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(BigEndian::read_u16(&request[23..]), *size as u16);
+            assert_eq!(
+                BigEndian::read_u32(&request[27..]) & 0xFFFFFF,
+                *start as u32 * 8
+            );
+            assert_eq!(&request[35..], &expected[start - 100..start - 100 + size]);
+
+            let mut response = vec![0u8; 22];
+            response[21] = 0xFF;
+            plc.reply(&response).unwrap();
+        }
+    });
+
+    cl.ag_write_all(888, 100, &data).unwrap();
+    assert_eq!(cl.last_round_trips(), 2);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_write_optimized() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // MB0, DBW0 and DBW2 together, DBD8 alone as DBB4..DBB7 are not written, DBX4.1
+        let writes: [(u8, u16, u32, &[u8]); 4] = [
+            (constant::Area::Merker as u8, 0, 0, &[9]),
+            (constant::Area::DataBausteine as u8, 888, 0, &[1, 2, 3, 4]),
+            (
+                constant::Area::DataBausteine as u8,
+                888,
+                8 * 8,
+                &[5, 6, 7, 8],
+            ),
+            (constant::Area::DataBausteine as u8, 888, 4 * 8 + 1, &[1]),
+        ];
+        for (area, db_number, address, data) in writes.iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(request[27], *area);
+            assert_eq!(BigEndian::read_u16(&request[25..]), *db_number);
+            assert_eq!(BigEndian::read_u32(&request[27..]) & 0xFFFFFF, *address);
+            assert_eq!(&request[35..], *data);
+
+            let mut response = vec![0u8; 22];
+            response[21] = 0xFF;
+            plc.reply(&response).unwrap();
+        }
+    });
+
+    let items: Vec<(Address, Vec<u8>)> = vec![
+        ("DB888.DBD8".parse().unwrap(), vec![5, 6, 7, 8]),
+        ("DB888.DBX4.1".parse().unwrap(), vec![1]),
+        ("DB888.DBW2".parse().unwrap(), vec![3, 4]),
+        ("MB0".parse().unwrap(), vec![9]),
+        ("DB888.DBW0".parse().unwrap(), vec![1, 2]),
+    ];
+    cl.write_optimized(&items).unwrap();
+    assert_eq!(cl.last_round_trips(), 4);
+    plc.join().unwrap();
+
+    for wrong in [
+        vec![
+            ("DB888.DBD0".parse().unwrap(), vec![0; 4]),
+            ("DB888.DBW2".parse().unwrap(), vec![0; 2]),
+        ],
+        vec![("T1".parse().unwrap(), vec![0; 2])],
+        vec![("M0.1".parse().unwrap(), vec![1, 1])],
+        vec![("DB888.DBW0".parse().unwrap(), vec![0; 4])],
+        vec![("DB888.DBD0".parse().unwrap(), vec![0; 2])],
+        vec![("DBW0".parse().unwrap(), vec![0; 2])],
+        vec![("DBX0.1".parse().unwrap(), vec![1])],
+    ]
+    .iter()
+    {
+        match cl.write_optimized(wrong) {
+            Err(Error::InvalidInput { .. }) => {}
+            other => panic!("expected InvalidInput got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_read_sdb() {
+    let block: Vec<u8> = (0..300).map(|i| i as u8).collect();
+    let expected = block.clone();
+
+    let (mut cl, plc) = loopback_client(move |plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(request[17], 0x1D);
+        assert_eq!(&request[26..35], b"_0B07000A");
+        let mut response = vec![0u8; 27];
+        response[19] = 0x1D;
+        response[23..27].copy_from_slice(&[0, 0, 0, 7]);
+        plc.reply(&response).unwrap();
+
+        for (part, more) in [(&block[..200], 1u8), (&block[200..], 0)].iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(request[17], 0x1E);
+            assert_eq!(&request[21..25], &[0, 0, 0, 7]);
+            let mut response = vec![0u8; 25];
+            response[19] = 0x1E;
+            response[20] = *more;
+            BigEndian::write_u16(&mut response[21..], part.len() as u16);
+            response[24] = 0xFB;
+            response.extend_from_slice(part);
+            plc.reply(&response).unwrap();
+        }
+
+        let request = plc.recv().unwrap();
+        assert_eq!(request[17], 0x1F);
+        assert_eq!(&request[21..25], &[0, 0, 0, 7]);
+        let mut response = vec![0u8; 20];
+        response[19] = 0x1F;
+        plc.reply(&response).unwrap();
+
+        // a part shorter than announced still ends the upload
+        plc.recv().unwrap();
+        let mut response = vec![0u8; 27];
+        response[19] = 0x1D;
+        plc.reply(&response).unwrap();
+        plc.recv().unwrap();
+        let mut response = vec![0u8; 25];
+        response[19] = 0x1E;
+        BigEndian::write_u16(&mut response[21..], 10);
+        plc.reply(&response).unwrap();
+        assert_eq!(plc.recv().unwrap()[17], 0x1F);
+        let mut response = vec![0u8; 20];
+        response[19] = 0x1F;
+        plc.reply(&response).unwrap();
+    });
+
+    assert_eq!(cl.read_sdb(7000).unwrap(), expected);
+    match cl.read_sdb(0) {
+        Err(Error::InvalidResponse { .. }) => {}
+        other => panic!("expected InvalidResponse got {:?}", other),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_map() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // DBW0, DBX2.1 and DBD4 in one request, DBB100 in another
+        for (start, size) in [(0u32, 8u16), (100, 1)].iter() {
+            let request = plc.recv().unwrap();
+            assert_eq!(BigEndian::read_u16(&request[23..]), *size);
+            assert_eq!(BigEndian::read_u16(&request[25..]), 888);
+            assert_eq!(BigEndian::read_u32(&request[27..]) & 0xFFFFFF, start * 8);
+
+            let mut response = vec![0u8; 25];
+            response[21] = 0xFF;
+            response.extend((0..*size).map(|i| *start as u8 + i as u8));
+            plc.reply(&response).unwrap();
+        }
+    });
+
+    let fields: Vec<(String, Address)> = [
+        ("speed", "DBD4"),
+        ("count", "DB888.DBW0"),
+        ("lights", "DBX2.1"),
+        ("mode", "DBB100"),
+    ]
+    .iter()
+    .map(|(name, address)| (name.to_string(), address.parse().unwrap()))
+    .collect();
+
+    let values = cl.read_map(888, &fields).unwrap();
+    assert_eq!(values["count"], vec![0, 1]);
+    assert_eq!(values["lights"], vec![2]);
+    assert_eq!(values["speed"], vec![4, 5, 6, 7]);
+    assert_eq!(values["mode"], vec![100]);
+    plc.join().unwrap();
+
+    let wrong = vec![("flag".to_string(), "M0.1".parse().unwrap())];
+    match cl.read_map(888, &wrong) {
+        Err(Error::InvalidInput { .. }) => {}
+        other => panic!("expected InvalidInput got {:?}", other),
+    }
+
+    // a name would lose one of the values
+    let duplicate = vec![
+        ("speed".to_string(), "DBD4".parse().unwrap()),
+        ("speed".to_string(), "DBD8".parse().unwrap()),
+    ];
+    match cl.read_map(888, &duplicate) {
+        Err(Error::InvalidInput { input }) => assert!(input.contains("speed"), "{}", input),
+        other => panic!("expected InvalidInput got {:?}", other),
+    }
+}
+
+#[test]
+fn test_read_layout() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[23..]), 20);
+
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[0xFF, 0xF9, 0b0000_0010, 0]);
+        response.extend_from_slice(&121.5f32.to_be_bytes());
+        response.extend_from_slice(&[0, 0, 1, 0]);
+        response.extend_from_slice(&[6, 2, b'o', b'k', 0, 0, 0, 0]);
+        plc.reply(&response).unwrap();
+    });
+
+    let layout = DbLayout::from_csv(
+        888,
+        "count,DBW0,INT\nlights,DBX2.1,BOOL\nspeed,DBD4,REAL\ntotal,DBD8,DINT\n\
+         name,DBB12,STRING[6]",
+    )
+    .unwrap();
+    let values = cl.read_layout(&layout).unwrap();
+    assert_eq!(values.len(), 5);
+    assert_eq!(values["name"], Value::String("ok".to_string()));
+    assert_eq!(values["count"], Value::Int(-7));
+    assert_eq!(values["lights"], Value::Bool(true));
+    assert_eq!(values["speed"], Value::Real(121.5));
+    assert_eq!(values["total"], Value::DInt(256));
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_write_read_single_bit() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // bit 8.3 of DB 888
+        let request = plc.recv().unwrap();
+        assert_eq!(request.len(), 36);
+        assert_eq!(BigEndian::read_u16(&request[2..]), 36);
+        assert_eq!(BigEndian::read_u16(&request[13..]), 14);
+        assert_eq!(BigEndian::read_u16(&request[15..]), 5);
+        assert_eq!(request[17], 0x05);
+        assert_eq!(request[22], constant::WL_BIT as u8);
+        assert_eq!(BigEndian::read_u16(&request[23..]), 1);
+        assert_eq!(BigEndian::read_u16(&request[25..]), 888);
+        assert_eq!(request[30], 8 * 8 + 3);
+        assert_eq!(request[32], constant::TS_RES_BIT as u8);
+        assert_eq!(BigEndian::read_u16(&request[33..]), 1);
+        let bit = request[35];
+
+        let mut response = vec![0u8; 22];
+        response[21] = 0xFF;
+        plc.reply(&response).unwrap();
+
+        let request = plc.recv().unwrap();
+        assert_eq!(request[22], constant::WL_BIT as u8);
+        assert_eq!(request[30], 8 * 8 + 3);
+
+        let mut response = vec![0u8; 26];
+        response[21] = 0xFF;
+        response[25] = bit;
+        plc.reply(&response).unwrap();
+    });
+
+    cl.write(
+        constant::Area::DataBausteine,
+        888,
+        8 * 8 + 3,
+        1,
+        constant::WL_BIT,
+        &[1],
+    )
+    .unwrap();
+    assert_eq!(cl.last_round_trips(), 1);
+
+    let buffer = &mut [0u8; 1];
+    cl.read(
+        constant::Area::DataBausteine,
+        888,
+        8 * 8 + 3,
+        1,
+        constant::WL_BIT,
+        buffer,
+    )
+    .unwrap();
+    assert_eq!(buffer[0], 1);
+    plc.join().unwrap();
+}
+
+#[cfg(test)]
+fn szl_response(id: u16, record_length: u16, records: &[&[u8]]) -> Vec<u8> {
+    let data: Vec<u8> = records.concat();
+    let mut response = vec![0u8; 41];
+    BigEndian::write_u16(&mut response[31..], data.len() as u16 + 8);
+    BigEndian::write_u16(&mut response[33..], id);
+    BigEndian::write_u16(&mut response[37..], record_length);
+    BigEndian::write_u16(&mut response[39..], records.len() as u16);
+    response.extend_from_slice(&data);
+    response
+}
+
+/// the answer of a CPU refusing a SZL request, the parameters carry the error
+/// code and the data is only its header
+#[cfg(test)]
+fn szl_error_response(code: u16) -> Vec<u8> {
+    let mut response = vec![0u8; 29];
+    BigEndian::write_u16(&mut response[27..], code);
+    response.extend_from_slice(&[0x0A, 0, 0, 0]);
+    response
+}
+
+#[test]
+fn test_read_szl_records() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0011);
+        assert_eq!(BigEndian::read_u16(&request[31..]), 0x0001);
+        plc.reply(&szl_response(0x0011, 4, &[&[0, 1, 2, 3], &[0, 6, 7, 8]]))
+            .unwrap();
+    });
+
+    let szl = cl.read_szl(0x0011, 0x0001).unwrap();
+    assert_eq!(szl.header.length_header, 4);
+    assert_eq!(szl.header.number_of_data_record, 2);
+
+    let records: Vec<&[u8]> = szl.records().collect();
+    assert_eq!(records, vec![&[0, 1, 2, 3][..], &[0, 6, 7, 8][..]]);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_szl_into() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        for _ in 0..2 {
+            plc.recv().unwrap();
+            plc.reply(&szl_response(0x0011, 4, &[&[0, 1, 2, 3], &[0, 6, 7, 8]]))
+                .unwrap();
+        }
+    });
+
+    let buffer = &mut [0u8; 16];
+    let (size, header) = cl.read_szl_into(0x0011, 0x0001, buffer).unwrap();
+    assert_eq!(size, 8);
+    assert_eq!(header.number_of_data_record, 2);
+    assert_eq!(&buffer[..size], &[0, 1, 2, 3, 0, 6, 7, 8]);
+
+    match cl.read_szl_into(0x0011, 0x0001, &mut [0u8; 6]) {
+        Err(Error::BufferSize {
+            expected: 8,
+            got: 6,
+        }) => {}
+        other => panic!("expected BufferSize got {:?}", other),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_var_table() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[2..]) as usize, request.len());
+        assert_eq!(request[22], 0x41);
+        assert_eq!(request[23], 0x02);
+        assert_eq!(
+            BigEndian::read_u16(&request[15..]) as usize,
+            request.len() - 25
+        );
+        assert_eq!(
+            BigEndian::read_u16(&request[27..]) as usize,
+            request.len() - 29
+        );
+        assert_eq!(request[30], transport::VAR_TABLE_REQUEST);
+        assert_eq!(BigEndian::read_u16(&request[37..]), 2);
+        // IB3
+        assert_eq!(&request[39..45], &[0x11, 1, 0, 0, 0, 3]);
+        // DB888.DBW8
+        assert_eq!(&request[45..51], &[0x72, 1, 0x03, 0x78, 0, 8]);
+
+        let mut response = vec![0u8; 43];
+        response[29] = 0xFF;
+        response[34] = 0x04;
+        BigEndian::write_u16(&mut response[41..], 2);
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 8, 0x2A, 0]);
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 16, 0x12, 0x34]);
+        plc.reply(&response).unwrap();
+    });
+
+    let items = [
+        S7DataItem::new(constant::Area::ProcessInput, constant::WL_BYTE, 0, 3, 1),
+        S7DataItem::new(constant::Area::DataBausteine, constant::WL_WORD, 888, 8, 1),
+    ];
+
+    let values = cl.read_var_table(&items).unwrap();
+    assert_eq!(values, vec![vec![0x2A], vec![0x12, 0x34]]);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_forces() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        // first job of the client
+        let mut expected = transport::FORCES_TELEGRAM.to_vec();
+        BigEndian::write_u16(&mut expected[11..], 1);
+        assert_eq!(request, expected);
+
+        let mut response = vec![0u8; 43];
+        response[29] = 0xFF;
+        BigEndian::write_u16(&mut response[41..], 2);
+        // Q4.0 as QB4 and DB10.DBW2
+        response.extend_from_slice(&[0x21, 1, 0, 0, 0, 4]);
+        response.extend_from_slice(&[0x72, 1, 0, 10, 0, 2]);
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 8, 0x01, 0]);
+        response.extend_from_slice(&[0xFF, constant::TS_RES_BYTE as u8, 0, 16, 0xAB, 0xCD]);
+        plc.reply(&response).unwrap();
+
+        plc.recv().unwrap();
+        let mut response = vec![0u8; 43];
+        response[29] = 0xFF;
+        plc.reply(&response).unwrap();
+    });
+
+    let forces = cl.read_forces().unwrap();
+    assert_eq!(
+        forces,
+        vec![
+            ForceEntry {
+                area: constant::Area::ProcessOutput,
+                word_len: constant::WL_BYTE,
+                db_number: 0,
+                start: 4,
+                value: vec![0x01],
+            },
+            ForceEntry {
+                area: constant::Area::DataBausteine,
+                word_len: constant::WL_WORD,
+                db_number: 10,
+                start: 2,
+                value: vec![0xAB, 0xCD],
+            },
+        ]
+    );
+
+    assert!(cl.read_forces().unwrap().is_empty());
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_pipelined() {
+    let (client_end, plc) = transport::loopback();
+
+    let plc = std::thread::spawn(move || {
+        plc.recv().unwrap();
+        let mut negotiated = vec![0u8; 27];
+        BigEndian::write_u16(&mut negotiated[21..], 2);
+        BigEndian::write_u16(&mut negotiated[23..], 2);
+        BigEndian::write_u16(&mut negotiated[25..], 240);
+        plc.reply(&negotiated).unwrap();
+
+        let respond = |request: &[u8]| {
+            let mut response = vec![0u8; 25];
+            response[11..13].copy_from_slice(&request[11..13]);
+            response[21] = 0xFF;
+            // the data is the start byte address
+            let start = request[30] >> 3;
+            let size = BigEndian::read_u16(&request[23..]);
+            response.extend((0..size).map(|i| start + i as u8));
+            response
+        };
+
+        // both requests are in flight before any answer, answer them in reverse
+        let first = plc.recv().unwrap();
+        let second = plc.recv().unwrap();
+        plc.reply(&respond(&second)).unwrap();
+        plc.reply(&respond(&first)).unwrap();
+
+        let third = plc.recv().unwrap();
+        plc.reply(&respond(&third)).unwrap();
+    });
+
+    let mut cl = Client::new(client_end).unwrap();
+    let items = [
+        S7DataItem::new(constant::Area::DataBausteine, constant::WL_BYTE, 1, 0, 2),
+        S7DataItem::new(constant::Area::DataBausteine, constant::WL_BYTE, 1, 10, 1),
+        S7DataItem::new(
+            constant::Area::DataBausteine,
+            constant::WL_BYTE,
+            1,
+            1000,
+            500,
+        ),
+        S7DataItem::new(constant::Area::DataBausteine, constant::WL_WORD, 1, 20, 1),
+    ];
+
+    let results = cl.read_pipelined(&items).unwrap();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap(), &vec![0, 1]);
+    assert_eq!(results[1].as_ref().unwrap(), &vec![10]);
+    assert!(results[2].is_err());
+    assert_eq!(results[3].as_ref().unwrap(), &vec![20, 21]);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_counters_and_timers() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // C3 and C4
+        let request = plc.recv().unwrap();
+        assert_eq!(request[22], constant::WL_COUNTER as u8);
+        assert_eq!(BigEndian::read_u16(&request[23..]), 2);
+        assert_eq!(request[27], constant::Area::Counter as u8);
+        assert_eq!(request[30], 3);
+
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[0x00, 0x12, 0x00, 0x34]);
+        plc.reply(&response).unwrap();
+
+        // T7
+        let request = plc.recv().unwrap();
+        assert_eq!(request.len(), 37);
+        assert_eq!(request[22], constant::WL_TIMER as u8);
+        assert_eq!(BigEndian::read_u16(&request[23..]), 1);
+        assert_eq!(request[27], constant::Area::Timer as u8);
+        assert_eq!(request[30], 7);
+        assert_eq!(request[32], constant::TS_RES_OCTET as u8);
+        assert_eq!(BigEndian::read_u16(&request[33..]), 2);
+        assert_eq!(&request[35..], &[0x20, 0x10]);
+
+        let mut response = vec![0u8; 22];
+        response[21] = 0xFF;
+        plc.reply(&response).unwrap();
+    });
+
+    let buffer = &mut [0u8; 4];
+    cl.ct_read(3, 2, buffer).unwrap();
+    assert_eq!(buffer, &[0x00, 0x12, 0x00, 0x34]);
+
+    cl.tm_write(7, 1, &mut [0x20, 0x10]).unwrap();
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_peripheral() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // PIW256
+        let request = plc.recv().unwrap();
+        assert_eq!(request[22], constant::WL_BYTE as u8);
+        assert_eq!(BigEndian::read_u16(&request[23..]), 2);
+        assert_eq!(request[27], 0x80);
+        assert_eq!(BigEndian::read_u32(&request[27..]) & 0xFFFFFF, 256 * 8);
+
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[0x6C, 0x00]);
+        plc.reply(&response).unwrap();
+
+        // PQW256
+        let request = plc.recv().unwrap();
+        assert_eq!(request[27], 0x80);
+        assert_eq!(&request[35..], &[0x36, 0x00]);
+
+        let mut response = vec![0u8; 22];
+        response[21] = 0xFF;
+        plc.reply(&response).unwrap();
+    });
+
+    let buffer = &mut [0u8; 2];
+    cl.pe_read(256, 2, buffer).unwrap();
+    assert_eq!(buffer, &[0x6C, 0x00]);
+
+    cl.pe_write(256, 2, &mut [0x36, 0x00]).unwrap();
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_read_all_counters_and_timers() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(request[27], constant::Area::Counter as u8);
+        assert_eq!(BigEndian::read_u16(&request[23..]), 2);
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[0x00, 0x42, 0x09, 0x99]);
+        plc.reply(&response).unwrap();
+
+        let request = plc.recv().unwrap();
+        assert_eq!(request[27], constant::Area::Timer as u8);
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[0x21, 0x20]);
+        plc.reply(&response).unwrap();
+    });
+
+    assert_eq!(cl.read_all_counters(2).unwrap(), vec![42, 999]);
+    assert_eq!(
+        cl.read_all_timers(1).unwrap(),
+        vec![Duration::from_secs(120)]
+    );
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_operating_mode_info() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0424);
+
+        let record = [
+            0x45, 0x62, 0xFF, 0x38, 0, 0, 0, 0, 0x00, 0x01, 0x02, 0x03, 0x19, 0x01, 0x02, 0x03,
+            0x04, 0x05, 0x06, 0x07,
+        ];
+        plc.reply(&szl_response(0x0424, 20, &[&record])).unwrap();
+    });
+
+    let mode = cl.operating_mode_info().unwrap();
+    assert_eq!(mode.event_id, 0x4562);
+    assert_eq!(mode.previous_mode, 0x03);
+    assert_eq!(mode.requested_mode, 0x08);
+    assert_eq!(mode.startup_type, 0x01);
+    assert_eq!(mode.permitted_startup, 0x02);
+    assert_eq!(mode.current_mode, 0x03);
+    assert_eq!(mode.timestamp, [0x19, 1, 2, 3, 4, 5, 6, 7]);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_cycle_time() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0222);
+        assert_eq!(BigEndian::read_u16(&request[31..]), 0x0001);
+
+        // OB1 start information, 12 ms last cycle, 3 ms min, 150 ms max
+        let mut record = vec![0x11, 0x03, 0x01, 0x01, 0, 0, 0, 12, 0, 3, 0, 150];
+        record.resize(28, 0);
+        plc.reply(&szl_response(0x0222, 28, &[&record])).unwrap();
+    });
+
+    let cycle = cl.cycle_time().unwrap();
+    assert_eq!(cycle.current, Duration::from_millis(12));
+    assert_eq!(cycle.min, Duration::from_millis(3));
+    assert_eq!(cycle.max, Duration::from_millis(150));
+    plc.join().unwrap();
+}
+
+#[cfg(test)]
+fn memory_area_record(index: u16, total: u32, used: [u32; 2], free: [u32; 2]) -> Vec<u8> {
+    let mut record = vec![0u8; transport::MEMORY_AREA_RECORD_SIZE];
+    BigEndian::write_u16(&mut record[0..], index);
+    BigEndian::write_u32(&mut record[4..], total);
+    BigEndian::write_u32(&mut record[16..], used[0]);
+    BigEndian::write_u32(&mut record[20..], free[0]);
+    BigEndian::write_u32(&mut record[28..], used[1]);
+    BigEndian::write_u32(&mut record[32..], free[1]);
+    record
+}
+
+#[test]
+fn test_memory_usage() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0013);
+
+        let work = memory_area_record(1, 65536, [1000, 24], [60000, 0]);
+        let integrated = memory_area_record(2, 0, [0, 0], [0, 0]);
+        let plugged = memory_area_record(3, 1 << 20, [0, 4096], [0, 1 << 19]);
+        let backup = memory_area_record(5, 8192, [0, 0], [0, 0]);
+        let records = [&work[..], &integrated[..], &plugged[..], &backup[..]];
+        plc.reply(&szl_response(0x0013, 36, &records)).unwrap();
+
+        // a CPU without the list, then a protected one
+        for code in [0xD401u16, 0xD241].iter() {
+            plc.recv().unwrap();
+            plc.reply(&szl_error_response(*code)).unwrap();
+        }
+    });
+
+    let memory = cl.memory_usage().unwrap();
+    assert_eq!(memory.work_total, 65536);
+    assert_eq!(memory.work_used, 1024);
+    assert_eq!(memory.work_free(), 64512);
+    assert_eq!(memory.work_largest_free, 60000);
+    assert_eq!(memory.load_total, 1 << 20);
+    assert_eq!(memory.load_used, 4096);
+    assert_eq!(memory.load_largest_free, 1 << 19);
+
+    match cl.memory_usage() {
+        Err(Error::Response { code, .. }) => assert_eq!(code, error::CLI_FUN_NOT_AVAILABLE),
+        other => panic!("expected Response got {:?}", other),
+    }
+    // other errors aren't a missing list
+    match cl.memory_usage() {
+        Err(Error::CPU { bytes, .. }) => assert_eq!(BigEndian::read_u16(&bytes[27..]), 0xD241),
+        other => panic!("expected CPU got {:?}", other),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_get_plc_clock() {
+    fn clock_response() -> Vec<u8> {
+        let mut response = vec![0u8; 33];
+        response[29] = 0xFF;
+        response[31] = 10;
+        // 2024/03/31 02:30:15.123, a sunday
+        response.extend_from_slice(&[0, 0x20, 0x24, 0x03, 0x31, 0x02, 0x30, 0x15, 0x12, 0x31]);
+        response
+    }
+
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(&request[22..24], &[0x47, 0x01]);
+        plc.reply(&clock_response()).unwrap();
+
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0132);
+        assert_eq!(BigEndian::read_u16(&request[31..]), 0x0008);
+        let mut record = vec![0u8; 40];
+        record[1] = 8;
+        // summer time, UTC+2h
+        BigEndian::write_u16(&mut record[34..], 4 << 10 | 0x0100);
+        plc.reply(&szl_response(0x0132, 40, &[&record])).unwrap();
+
+        // a CPU without the time system status
+        plc.recv().unwrap();
+        plc.reply(&clock_response()).unwrap();
+        plc.recv().unwrap();
+        plc.reply(&szl_error_response(0xD401)).unwrap();
+
+        // a day that isn't BCD
+        plc.recv().unwrap();
+        let mut response = clock_response();
+        response[37] = 0x1A;
+        plc.reply(&response).unwrap();
+    });
+
+    let clock = cl.get_plc_clock().unwrap();
+    assert_eq!(clock.local, "2024/03/31 02:30:15.123");
+    assert!(clock.dst_active);
+    assert_eq!(clock.utc_offset_minutes, Some(120));
+
+    let clock = cl.get_plc_clock().unwrap();
+    assert_eq!(clock.local, "2024/03/31 02:30:15.123");
+    assert!(!clock.dst_active);
+    assert_eq!(clock.utc_offset_minutes, None);
+
+    match cl.get_plc_clock() {
+        Err(Error::InvalidResponse { .. }) => {}
+        other => panic!("expected InvalidResponse got {:?}", other),
+    }
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_clock_timestamp() {
+    let clock = [0, 0, 0x99, 0x12, 0x31, 0x23, 0x59, 0x59, 0x99, 0x97];
+    assert_eq!(clock_timestamp(&clock).unwrap(), "1999/12/31 23:59:59.999");
+    let clock = [0, 0, 0x05, 0x01, 0x02, 0x03, 0x04, 0x05, 0x00, 0x71];
+    assert_eq!(clock_timestamp(&clock).unwrap(), "2005/01/02 03:04:05.007");
+}
+
+#[test]
+fn test_network_config() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0037);
 
-        while tot_elements > 0 {
-            let mut num_elements = tot_elements;
-            if num_elements > max_elements {
-                num_elements = max_elements;
-            }
-            let data_size = num_elements * word_size;
-            let iso_size = constant::SIZE_HEADER_WRITE + data_size;
+        let mut record = vec![0u8; 48];
+        BigEndian::write_u16(&mut record, 0x07FE);
+        record[2..6].copy_from_slice(&[192, 168, 0, 10]);
+        record[6..10].copy_from_slice(&[255, 255, 255, 0]);
+        record[10..14].copy_from_slice(&[192, 168, 0, 1]);
+        record[14..20].copy_from_slice(&[0x00, 0x1B, 0x1B, 0x12, 0x34, 0x56]);
+        plc.reply(&szl_response(0x0037, 48, &[&record])).unwrap();
 
-            // Setup the telegram
-            let mut request_data = transport::READ_WRITE_TELEGRAM.to_vec();
-            // Whole telegram Size
-            BigEndian::write_u16(request_data[2..].as_mut(), iso_size as u16);
-            // Data length
-            let mut length = data_size + 4;
-            BigEndian::write_u16(request_data[15..].as_mut(), length as u16);
-            // Function
-            request_data[17] = 0x05;
-            // Set DB Number
-            request_data[27] = area as u8;
+        // a CPU without Ethernet interface
+        plc.recv().unwrap();
+        plc.reply(&szl_response(0x0037, 48, &[])).unwrap();
+    });
 
-            match area {
-                Area::DataBausteine => {
-                    BigEndian::write_u16(request_data[25..].as_mut(), db_number as u16)
-                }
-                _ => {}
-            }
-            // Adjusts start and word length
-            let mut address = match word_len {
-                constant::WL_BIT | constant::WL_COUNTER | constant::WL_TIMER => {
-                    length = data_size;
-                    request_data[22] = word_len as u8;
-                    start
-                }
-                _ => {
-                    length = data_size << 3;
-                    start << 3
-                }
-            };
+    let net = cl.network_config().unwrap();
+    assert_eq!(net.ip_address, Ipv4Addr::new(192, 168, 0, 10));
+    assert_eq!(net.subnet_mask, Ipv4Addr::new(255, 255, 255, 0));
+    assert_eq!(net.gateway, Ipv4Addr::new(192, 168, 0, 1));
+    assert_eq!(net.mac_address, [0x00, 0x1B, 0x1B, 0x12, 0x34, 0x56]);
 
-            // Num elements
-            BigEndian::write_u16(request_data[23..].as_mut(), num_elements as u16);
-            // address into the PLC
-            request_data[30] = (address & 0x0FF) as u8;
-            address = address >> 8;
-            request_data[29] = (address & 0x0FF) as u8;
-            address = address >> 8;
-            request_data[28] = (address & 0x0FF) as u8;
+    match cl.network_config() {
+        Err(Error::Response { code, .. }) => assert_eq!(code, error::CLI_FUN_NOT_AVAILABLE),
+        other => panic!("expected Response got {:?}", other),
+    }
+    plc.join().unwrap();
+}
 
-            // Transport Size
-            match word_len {
-                constant::WL_BIT => request_data[32] = constant::TS_RES_BIT as u8,
-                constant::WL_COUNTER | constant::WL_TIMER => {
-                    request_data[32] = constant::TS_RES_OCTET as u8
-                }
-                _ => request_data[32] = constant::TS_RES_BYTE as u8, // byte/word/dword etc.
-            }
-            // length
-            BigEndian::write_u16(request_data[33..].as_mut(), length as u16);
+#[test]
+fn test_hot_restart() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(request[17], transport::PDU_START);
+        assert_eq!(&request[26..29], &[2, b'A', b' ']);
+        assert_eq!(&request[30..], b"P_PROGRAM");
 
-            //expand values into array
-            request_data.splice(
-                35..35,
-                buffer[offset as usize..offset as usize + data_size as usize].to_vec(),
-            );
+        let mut response = vec![0u8; 20];
+        response[17] = transport::PDU_START;
+        plc.reply(&response).unwrap();
 
-            let result = self.transport.send(request_data.as_mut_slice());
+        plc.recv().unwrap();
+        response[18] = transport::PDU_ALREADY_STARTED;
+        plc.reply(&response).unwrap();
+    });
 
-            match result {
-                Ok(response) => {
-                    if response.len() != 22 {
-                        return Err(Error::Response {
-                            code: error::ISO_INVALID_PDU,
-                        });
-                    }
+    cl.hot_restart().unwrap();
+    assert!(cl.hot_restart().is_err());
+    plc.join().unwrap();
+}
 
-                    if response[21] != 0xFF {
-                        return Err(Error::CPU {
-                            code: response[21] as i32,
-                        });
-                    }
-                }
-                Err(e) => {
-                    return Err(e);
-                }
-            }
+#[test]
+fn test_pdu_renegotiation() {
+    fn refused() -> Vec<u8> {
+        let mut response = vec![0u8; 19];
+        BigEndian::write_u16(&mut response[17..], error::CODE_7_DATA_OVER_PDU as u16);
+        response
+    }
 
-            offset += data_size;
-            tot_elements -= num_elements;
-            start += num_elements * word_size;
+    let (mut cl, plc) = loopback_client(|plc| {
+        // disabled, the refusal is returned
+        plc.recv().unwrap();
+        plc.reply(&refused()).unwrap();
+
+        plc.recv().unwrap();
+        plc.reply(&refused()).unwrap();
+        // negotiated again, the CPU now only takes 240 bytes
+        let request = plc.recv().unwrap();
+        assert_eq!(request[17], 0xF0);
+        let mut negotiated = vec![0u8; 27];
+        BigEndian::write_u16(&mut negotiated[25..], 240);
+        plc.reply(&negotiated).unwrap();
+
+        for expected in [222u16, 178] {
+            let request = plc.recv().unwrap();
+            assert_eq!(BigEndian::read_u16(&request[23..]), expected);
+            let mut response = vec![0u8; 25 + expected as usize];
+            response[21] = 0xFF;
+            response[25..].iter_mut().for_each(|b| *b = 7);
+            plc.reply(&response).unwrap();
         }
-        Ok(())
+
+        // refused again after negotiating, not retried a second time in the same call
+        plc.recv().unwrap();
+        plc.reply(&refused()).unwrap();
+        assert_eq!(plc.recv().unwrap()[17], 0xF0);
+        plc.reply(&negotiated).unwrap();
+        plc.recv().unwrap();
+        plc.reply(&refused()).unwrap();
+    });
+
+    let buffer = &mut vec![0u8; 400];
+    match cl.ag_read(888, 0, 400, buffer) {
+        Err(Error::CPU { code, .. }) => assert_eq!(code, error::CODE_7_DATA_OVER_PDU),
+        other => panic!("{:?}", other),
     }
+
+    cl.set_pdu_renegotiation(true);
+    cl.ag_read(888, 0, 400, buffer).unwrap();
+    assert_eq!(cl.negotiation_info().pdu_length, 240);
+    assert_eq!(cl.last_round_trips(), 3);
+    assert!(buffer.iter().all(|b| *b == 7));
+
+    let buffer = &mut [0u8; 4];
+    match cl.ag_write(888, 0, 4, buffer) {
+        Err(Error::CPU { code, .. }) => assert_eq!(code, error::CODE_7_DATA_OVER_PDU),
+        other => panic!("{:?}", other),
+    }
+    drop(cl);
+    plc.join().unwrap();
 }
 
-impl<T: Transport> Client<T> {
-    /// Starting the CPU from power off,Current configuration is discarded and program processing begins again with the initial values.
-    pub fn start(&mut self) -> Result<(), Error> {
-        self.cold_warm_start_stop(
-            transport::COLD_START_TELEGRAM.as_ref(),
-            transport::PDU_START,
-            error::CLI_CANNOT_START_PLC,
-            transport::PDU_ALREADY_STARTED,
-            error::CLI_ALREADY_RUN,
+#[test]
+fn test_write_reported() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // 445 bytes per request with a PDU of 480
+        for (expected, result) in [
+            (445u16, 0xFF),
+            (445, 0xFF),
+            (110, 0xFF),
+            (445, 0xFF),
+            (445, 5),
+        ] {
+            let request = plc.recv().unwrap();
+            assert_eq!(BigEndian::read_u16(&request[23..]), expected);
+            let mut response = vec![0u8; 22];
+            response[21] = result;
+            plc.reply(&response).unwrap();
+        }
+    });
+
+    let data = vec![1u8; 1000];
+    let report = cl
+        .write_reported(
+            constant::Area::DataBausteine,
+            888,
+            10,
+            1000,
+            constant::WL_BYTE,
+            &data,
         )
-    }
+        .unwrap();
+    assert!(report.is_complete());
+    assert_eq!(
+        report.chunks,
+        vec![
+            WriteChunk {
+                start: 10,
+                elements: 445,
+                result: 0xFF
+            },
+            WriteChunk {
+                start: 455,
+                elements: 445,
+                result: 0xFF
+            },
+            WriteChunk {
+                start: 900,
+                elements: 110,
+                result: 0xFF
+            },
+        ]
+    );
+    assert_eq!(report.elements_written(), 1000);
 
-    /// Restarting the CPU without turning the power off, Program processing starts once again where Retentive data is retained.
-    pub fn restart(&mut self) -> Result<(), Error> {
-        self.cold_warm_start_stop(
-            transport::WARM_START_TELEGRAM.as_ref(),
-            transport::PDU_START,
-            error::CLI_CANNOT_START_PLC,
-            transport::PDU_ALREADY_STARTED,
-            error::CLI_ALREADY_RUN,
+    // the second chunk is refused, the rest isn't sent
+    let report = cl
+        .write_reported(
+            constant::Area::DataBausteine,
+            888,
+            0,
+            1000,
+            constant::WL_BYTE,
+            &data,
         )
-    }
+        .unwrap();
+    assert!(!report.is_complete());
+    assert_eq!(report.chunks.len(), 2);
+    assert_eq!(report.chunks[1].result, 5);
+    assert_eq!(report.elements_written(), 445);
+    assert!(matches!(report.error, Some(Error::CPU { code: 5, .. })));
 
-    /// Shut down
-    pub fn stop(&mut self) -> Result<(), Error> {
-        self.cold_warm_start_stop(
-            transport::STOP_TELEGRAM.as_ref(),
-            transport::PDU_STOP,
-            error::CLI_CANNOT_STOP_PLC,
-            transport::PDU_ALREADY_STOPPED,
-            error::CLI_ALREADY_STOP,
+    // nothing sent, no report
+    assert!(cl
+        .write_reported(
+            constant::Area::DataBausteine,
+            888,
+            0,
+            1000,
+            constant::WL_BYTE,
+            &data[..10]
         )
+        .is_err());
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_firmware_version() {
+    fn record(index: u16, version: &[u8]) -> Vec<u8> {
+        let mut record = vec![0u8; 28];
+        BigEndian::write_u16(&mut record, index);
+        record[2..21].copy_from_slice(b"6ES7 315-2EH14-0AB0");
+        record[24..].copy_from_slice(version);
+        record
     }
 
-    /// get plc status
-    pub fn plc_status(&mut self) -> Result<CpuStatus, Error> {
-        let response = self
-            .transport
-            .send(transport::PLC_STATUS_TELEGRAM.as_ref())?;
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0011);
+        let module = record(0x0001, &[0, 0, 0, 0]);
+        let hardware = record(0x0006, &[0, 0, 0, 4]);
+        let firmware = record(0x0007, &[b'V', 0x04, 0x05, 0x02]);
+        plc.reply(&szl_response(0x0011, 28, &[&module, &hardware, &firmware]))
+            .unwrap();
 
-        if response.len() < transport::PLC_STATUS_MIN_RESPONSE {
-            return Err(Error::Response {
-                code: error::ISO_INVALID_PDU,
-            });
-        }
+        let firmware = record(0x0007, &[b'V', 2, 10, 1]);
+        plc.recv().unwrap();
+        plc.reply(&szl_response(0x0011, 28, &[&firmware])).unwrap();
 
-        let result = BigEndian::read_u16(response[27..29].as_ref());
+        let firmware = record(0x0007, &[b'V', 3, 3, 0x0D]);
+        plc.recv().unwrap();
+        plc.reply(&szl_response(0x0011, 28, &[&firmware])).unwrap();
 
-        if result != 0 {
-            return Err(Error::CPU {
-                code: result as i32,
-            });
-        }
+        let firmware = record(0x0007, &[b' ', 3, 3, 0]);
+        plc.recv().unwrap();
+        plc.reply(&szl_response(0x0011, 28, &[&firmware])).unwrap();
+
+        plc.recv().unwrap();
+        plc.reply(&szl_response(0x0011, 28, &[&module])).unwrap();
+    });
 
-        CpuStatus::from_u8(response[44])
+    let version = cl.firmware_version().unwrap();
+    assert_eq!(version, (4, 5, 2));
+    assert!(version >= (4, 5, 0));
+    // binary, not BCD
+    assert_eq!(cl.firmware_version().unwrap(), (2, 10, 1));
+    assert_eq!(cl.firmware_version().unwrap(), (3, 3, 13));
+    assert!(matches!(
+        cl.firmware_version(),
+        Err(Error::InvalidResponse { .. })
+    ));
+    match cl.firmware_version() {
+        Err(Error::Response { code, .. }) => assert_eq!(code, error::CLI_FUN_NOT_AVAILABLE),
+        other => panic!("expected Response got {:?}", other),
     }
+    plc.join().unwrap();
+}
 
-    pub fn cp_info(&mut self) -> Result<CPInfo, Error> {
-        let szl = self.read_szl(0x0131, 0x000)?;
+#[test]
+fn test_write_counters() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        // C3 to C5, 2 bytes of BCD each
+        let request = plc.recv().unwrap();
+        assert_eq!(request.len(), 35 + 6);
+        assert_eq!(request[22], constant::WL_COUNTER as u8);
+        assert_eq!(BigEndian::read_u16(&request[23..]), 3);
+        assert_eq!(request[32], constant::TS_RES_OCTET as u8);
+        assert_eq!(BigEndian::read_u16(&request[33..]), 6);
+        let counters = request[35..].to_vec();
 
-        Ok(CPInfo {
-            max_pdu_length: BigEndian::read_u16(szl.data[2..].as_ref()),
-            max_connections: BigEndian::read_u16(szl.data[4..].as_ref()),
-            max_mpi_rate: BigEndian::read_u16(szl.data[6..].as_ref()),
-            max_bus_rate: BigEndian::read_u16(szl.data[10..].as_ref()),
-        })
+        let mut response = vec![0u8; 22];
+        response[21] = 0xFF;
+        plc.reply(&response).unwrap();
+
+        // read back
+        plc.recv().unwrap();
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&counters);
+        plc.reply(&response).unwrap();
+    });
+
+    let mut buffer = Vec::new();
+    for value in [5u16, 120, 999] {
+        buffer.extend_from_slice(&field::counter_word(value).unwrap().to_be_bytes());
     }
+    assert_eq!(buffer, vec![0x00, 0x05, 0x01, 0x20, 0x09, 0x99]);
+    cl.ct_write(3, 3, &mut buffer).unwrap();
 
-    /// get cpu info
-    pub fn cpu_info(&mut self) -> Result<CpuInfo, Error> {
-        let szl = self.read_szl(0x001C, 0x000)?;
+    let read = &mut [0u8; 6];
+    cl.ct_read(3, 3, read).unwrap();
+    let values: Vec<u16> = read
+        .chunks(2)
+        .map(|word| field::counter_value(BigEndian::read_u16(word)).unwrap())
+        .collect();
+    assert_eq!(values, vec![5, 120, 999]);
+    plc.join().unwrap();
+}
 
-        if szl.data.len() < transport::SZL_MIN_RESPONSE {
-            return Err(Error::Response {
-                code: error::ISO_INVALID_PDU,
-            });
-        }
+#[test]
+fn test_io_words() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        for (area, start, size) in [
+            (0x81u8, 64, 4),
+            (0x82, 64, 2),
+            (0x81, 100, 4),
+            (0x82, 100, 8),
+        ] {
+            let request = plc.recv().unwrap();
+            assert_eq!(request[27], area);
+            assert_eq!(BigEndian::read_u32(&request[27..]) & 0xFFFFFF, start * 8);
+            assert_eq!(BigEndian::read_u16(&request[23..]), size as u16);
 
-        let module_type_name = match str::from_utf8(szl.data[172..204].as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(Error::InvalidResponse {
-                    bytes: szl.data[172..204].to_vec(),
-                    reason: e.to_string(),
-                })
-            }
-        };
+            let mut response = vec![0u8; 25];
+            response[21] = 0xFF;
+            response.extend((0..size).map(|i| i as u8 + 1));
+            plc.reply(&response).unwrap();
+        }
+    });
 
-        let serial_number = match str::from_utf8(szl.data[138..162].as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(Error::InvalidResponse {
-                    bytes: szl.data[138..162].to_vec(),
-                    reason: e.to_string(),
-                })
-            }
-        };
+    let buffer = &mut [0u8; 4];
+    cl.ew_read(64, 2, buffer).unwrap();
+    assert_eq!(buffer, &[1, 2, 3, 4]);
+    let buffer = &mut [0u8; 2];
+    cl.aw_read(64, 1, buffer).unwrap();
+    assert_eq!(buffer, &[1, 2]);
+    let buffer = &mut [0u8; 4];
+    cl.ed_read(100, 1, buffer).unwrap();
+    assert_eq!(u32::from_be_bytes(*buffer), 0x01020304);
+    let buffer = &mut [0u8; 8];
+    cl.ad_read(100, 2, buffer).unwrap();
+    assert_eq!(buffer, &[1, 2, 3, 4, 5, 6, 7, 8]);
 
-        let as_name = match str::from_utf8(szl.data[2..26].as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(Error::InvalidResponse {
-                    bytes: szl.data[2..26].to_vec(),
-                    reason: e.to_string(),
-                })
-            }
-        };
+    // the size counts words
+    match cl.ew_read(64, 2, &mut [0u8; 2]) {
+        Err(Error::BufferSize { expected, got }) => assert_eq!((expected, got), (4, 2)),
+        other => panic!("{:?}", other),
+    }
+    plc.join().unwrap();
+}
 
-        let copyright = match str::from_utf8(szl.data[104..130].as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(Error::InvalidResponse {
-                    bytes: szl.data[104..130].to_vec(),
-                    reason: e.to_string(),
-                })
-            }
-        };
+#[test]
+fn test_data_item_builder() {
+    let item = S7DataItemBuilder::new(constant::Area::DataBausteine, constant::WL_DWORD, 888, 8, 2)
+        .buffer(vec![1; 8])
+        .build()
+        .unwrap();
+    assert_eq!(item.buffer, vec![1; 8]);
+    assert!(item.err.is_none());
 
-        let module_name = match str::from_utf8(szl.data[36..60].as_ref()) {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(Error::InvalidResponse {
-                    bytes: szl.data[36..60].to_vec(),
-                    reason: e.to_string(),
-                })
-            }
-        };
+    // counters take 2 bytes whatever the word length, a bit a byte
+    let item = S7DataItemBuilder::new(constant::Area::Counter, constant::WL_COUNTER, 0, 3, 2)
+        .build()
+        .unwrap();
+    assert_eq!(item.buffer.len(), 4);
+    let item = S7DataItemBuilder::new(constant::Area::Merker, constant::WL_BIT, 0, 83, 1)
+        .build()
+        .unwrap();
+    assert_eq!(item.buffer.len(), 1);
 
-        Ok(CpuInfo {
-            module_type_name: module_type_name.to_string(),
-            serial_number: serial_number.to_string(),
-            as_name: as_name.to_string(),
-            copyright: copyright.to_string(),
-            module_name: module_name.to_string(),
-        })
+    match S7DataItemBuilder::new(constant::Area::DataBausteine, constant::WL_REAL, 888, 0, 2)
+        .buffer(vec![0; 7])
+        .build()
+    {
+        Err(Error::BufferSize { expected, got }) => assert_eq!((expected, got), (8, 7)),
+        other => panic!("{:?}", other),
+    }
+    for builder in [
+        S7DataItemBuilder::new(constant::Area::DataBausteine, constant::WL_BYTE, 888, 0, 0),
+        S7DataItemBuilder::new(
+            constant::Area::DataBausteine,
+            constant::WL_BYTE,
+            70000,
+            0,
+            1,
+        ),
+        S7DataItemBuilder::new(constant::Area::Merker, constant::WL_TIMER, 0, 0, 1),
+    ] {
+        assert!(matches!(builder.build(), Err(Error::InvalidInput { .. })));
     }
+}
 
-    fn read_szl(&mut self, id: u16, index: u16) -> Result<transport::S7SZL, Error> {
-        let data_szl = 0;
-        let mut offset = 0;
-        let seq_out: u16 = 0x0000;
+#[test]
+fn test_read_write_words() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[23..]), 6);
+        assert_eq!(BigEndian::read_u16(&request[25..]), 888);
+        assert_eq!(BigEndian::read_u32(&request[27..]) & 0xFFFFFF, 10 * 8);
 
-        let mut s7_szlfirst = transport::SZL_FIRST_TELEGRAM.to_vec();
+        let mut response = vec![0u8; 25];
+        response[21] = 0xFF;
+        response.extend_from_slice(&[0x00, 0x01, 0x80, 0x00, 0xAB, 0xCD]);
+        plc.reply(&response).unwrap();
 
-        BigEndian::write_u16(s7_szlfirst[11..].as_mut(), seq_out + 1);
-        BigEndian::write_u16(s7_szlfirst[29..].as_mut(), id);
-        BigEndian::write_u16(s7_szlfirst[31..].as_mut(), index);
+        let request = plc.recv().unwrap();
+        assert_eq!(&request[35..], &[0x12, 0x34, 0xFF, 0xFF]);
+        let mut response = vec![0u8; 22];
+        response[21] = 0xFF;
+        plc.reply(&response).unwrap();
+    });
 
-        let mut res = self.transport.send(s7_szlfirst.as_ref())?;
+    assert_eq!(
+        cl.read_words(888, 10, 3).unwrap(),
+        vec![0x0001, 0x8000, 0xABCD]
+    );
+    cl.write_words(888, 10, &[0x1234, 0xFFFF]).unwrap();
 
-        let validate = |res: &[u8], size: usize| -> Result<(), Error> {
-            if res.len() < transport::MIN_SZL_FIRST_TELEGRAM + size {
-                return Err(Error::Response {
-                    code: error::ISO_INVALID_PDU,
-                });
-            }
+    // nothing sent
+    assert!(cl.read_words(888, 0, 0).unwrap().is_empty());
+    assert!(matches!(
+        cl.read_words(888, 0, usize::MAX),
+        Err(Error::InvalidInput { .. })
+    ));
+    plc.join().unwrap();
+}
 
-            if BigEndian::read_u16(res[27..].as_ref()) != 0 && res[29] != 0xFF {
-                return Err(Error::CPU {
-                    code: error::CLI_INVALID_PLC_ANSWER,
-                });
-            }
-            Ok(())
-        };
+#[test]
+fn test_read_value() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        for (start, data) in [
+            (8, vec![66, 243, 0, 0]),
+            (12, vec![0b0001_0000]),
+            (20, vec![4, 2, b'o', b'n', 0, 0]),
+        ] {
+            let request = plc.recv().unwrap();
+            assert_eq!(BigEndian::read_u16(&request[23..]), data.len() as u16);
+            assert_eq!(BigEndian::read_u32(&request[27..]) & 0xFFFFFF, start * 8);
+            let mut response = vec![0u8; 25];
+            response[21] = 0xFF;
+            response.extend_from_slice(&data);
+            plc.reply(&response).unwrap();
+        }
+    });
 
-        validate(res.as_ref(), 0)?;
+    let address: Address = "DB888.DBD8".parse().unwrap();
+    assert_eq!(
+        cl.read_value(&address, Kind::Real).unwrap(),
+        Value::Real(121.5)
+    );
+    let address: Address = "DB888.DBX12.4".parse().unwrap();
+    assert_eq!(
+        cl.read_value(&address, Kind::Bool).unwrap(),
+        Value::Bool(true)
+    );
+    let address: Address = "DB888.DBB20".parse().unwrap();
+    assert_eq!(
+        cl.read_value(&address, Kind::String(4)).unwrap(),
+        Value::String("on".to_string())
+    );
 
-        // Skips extra params (ID, Index ...)
-        let mut data_szl = BigEndian::read_u16(res[31..].as_ref()) - 8;
+    // nothing sent
+    let address: Address = "DB888.DBW20".parse().unwrap();
+    assert!(cl.read_value(&address, Kind::Bool).is_err());
+    let address: Address = "C3".parse().unwrap();
+    assert!(cl.read_value(&address, Kind::Word).is_err());
+    plc.join().unwrap();
+}
 
-        validate(res.as_ref(), data_szl as usize)?;
+#[test]
+fn test_check_pdu_size() {
+    let telegram = vec![0u8; 7 + 480];
+    assert!(check_pdu_size(&telegram, 480).is_ok());
+    match check_pdu_size(&telegram, 479) {
+        Err(Error::PduLength(size)) => assert_eq!(size, 480),
+        other => panic!("{:?}", other),
+    }
 
-        let mut done = res[26] == 0x00;
-        // Slice sequence
-        let mut seq_in: u8 = res[24];
-        let header = transport::SZLHeader {
-            length_header: BigEndian::read_u16(res[37..].as_ref()) * 2,
-            number_of_data_record: BigEndian::read_u16(res[39..].as_ref()),
-        };
+    // the largest chunk of a split write, within the PDU
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(request.len(), 480);
+        let mut response = vec![0u8; 22];
+        response[21] = 0xFF;
+        plc.reply(&response).unwrap();
+    });
+    cl.ag_write(888, 0, 445, &mut [0u8; 445]).unwrap();
+    plc.join().unwrap();
+}
 
-        let len = (offset + data_szl) as usize;
-        let mut data = vec![0u8; len];
+#[test]
+fn test_diagnostic_dump() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0000);
+        let ids = [&[0x00, 0x11][..], &[0x01, 0x32][..], &[0x00, 0x37][..]];
+        plc.reply(&szl_response(0x0000, 2, &ids)).unwrap();
 
-        data[offset as usize..len].copy_from_slice(res[41..41 + data_szl as usize].as_ref());
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0011);
+        plc.reply(&szl_response(0x0011, 4, &[&[0, 1, 2, 3]]))
+            .unwrap();
 
-        let mut szl = transport::S7SZL { header, data };
-        offset += data_szl;
+        // refused, skipped
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0132);
+        plc.reply(&szl_error_response(0xD401)).unwrap();
 
-        let mut s7szlnext: Vec<u8> = transport::SZL_NEXT_TELEGRAM.to_vec();
+        let request = plc.recv().unwrap();
+        assert_eq!(BigEndian::read_u16(&request[29..]), 0x0037);
+        plc.reply(&szl_response(0x0037, 2, &[&[7, 8]])).unwrap();
+    });
 
-        while !done {
-            BigEndian::write_u16(s7_szlfirst[11..].as_mut(), seq_out + 1);
-            s7szlnext[24] = seq_in;
+    let dump = cl.diagnostic_dump().unwrap();
+    assert_eq!(dump.len(), 2);
+    assert_eq!(dump[&0x0011], vec![0, 1, 2, 3]);
+    assert_eq!(dump[&0x0037], vec![7, 8]);
+    assert!(!dump.contains_key(&0x0132));
+    plc.join().unwrap();
+}
 
-            res = self.transport.send(s7szlnext.as_ref())?;
+#[test]
+fn test_set_bit() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        for (area, db, address, value) in [
+            (0x84u8, 888u16, 12 * 8 + 4, 1u8),
+            (0x83, 0, 10 * 8 + 3, 1),
+            (0x81, 0, 1, 1),
+            (0x82, 0, 4 * 8, 0),
+        ] {
+            let request = plc.recv().unwrap();
+            assert_eq!(request[22], constant::WL_BIT as u8);
+            assert_eq!(BigEndian::read_u16(&request[23..]), 1);
+            assert_eq!(BigEndian::read_u16(&request[25..]), db);
+            assert_eq!(request[27], area);
+            assert_eq!(BigEndian::read_u32(&request[27..]) & 0xFFFFFF, address);
+            assert_eq!(request[32], constant::TS_RES_BIT as u8);
+            assert_eq!(&request[35..], &[value]);
 
-            validate(res.as_ref(), 0)?;
+            let mut response = vec![0u8; 22];
+            response[21] = 0xFF;
+            plc.reply(&response).unwrap();
+        }
+    });
 
-            data_szl = BigEndian::read_u16(res[31..].as_ref());
-            done = res[26] == 0x00;
-            seq_in = res[24];
+    cl.db_set_bit(888, 12, 4, true).unwrap();
+    cl.m_set_bit(10, 3, true).unwrap();
+    cl.e_set_bit(0, 1, true).unwrap();
+    cl.a_set_bit(4, 0, false).unwrap();
+    assert!(matches!(
+        cl.m_set_bit(10, 8, true),
+        Err(Error::InvalidInput { .. })
+    ));
+    plc.join().unwrap();
+}
 
-            szl.data = vec![0u8; len];
-            offset += data_szl;
-            szl.header.length_header += szl.header.length_header;
-        }
-        Ok(szl)
+#[test]
+fn test_check_range() {
+    let (mut cl, plc) = loopback_client(|_| {});
+
+    for result in [
+        cl.mb_read(-1, 1, &mut [0u8; 1]),
+        cl.mb_write(-4, 1, &mut [0u8; 1]),
+        cl.mb_read(0x20_0000, 1, &mut [0u8; 1]),
+        cl.mb_read(0x1F_FFFF, 2, &mut [0u8; 2]),
+    ] {
+        assert!(
+            matches!(result, Err(Error::InvalidInput { .. })),
+            "{:?}",
+            result
+        );
     }
+    assert_eq!(cl.last_round_trips(), 0);
 
-    fn cold_warm_start_stop(
-        &mut self,
-        req: &[u8],
-        start_cmp: u8,
-        start: i32,
-        already_cmp: u8,
-        already: i32,
-    ) -> Result<(), Error> {
-        let response = self.transport.send(req)?;
+    assert!(check_range(constant::WL_BYTE, 0x1F_FFFF, 1).is_ok());
+    assert!(check_range(constant::WL_BIT, 0xFF_FFFF, 1).is_ok());
+    assert!(check_range(constant::WL_BIT, 0x100_0000, 1).is_err());
+    assert!(check_range(constant::WL_COUNTER, 0xFF_FFFE, 2).is_ok());
+    assert!(check_range(constant::WL_DWORD, 0x1F_FFFC, 2).is_err());
+    drop(cl);
+    plc.join().unwrap();
+}
 
-        if response.len() < transport::TELEGRAM_MIN_RESPONSE {
-            return Err(Error::Response {
-                code: error::ISO_INVALID_PDU,
-            });
-        }
+#[test]
+fn test_write_padded_response() {
+    let (mut cl, plc) = loopback_client(|plc| {
+        plc.recv().unwrap();
+        let mut response = vec![0u8; 26];
+        response[21] = 0xFF;
+        plc.reply(&response).unwrap();
 
-        if response[17] != start_cmp {
-            return Err(Error::Response { code: start });
-        }
-        if response[18] == already_cmp {
-            return Err(Error::Response { code: already });
-        }
-        Ok(())
+        plc.recv().unwrap();
+        plc.reply(&[0u8; 21]).unwrap();
+    });
+
+    cl.ag_write(888, 0, 2, &mut [1, 2]).unwrap();
+    match cl.ag_write(888, 0, 2, &mut [1, 2]) {
+        Err(Error::Response { code, .. }) => assert_eq!(code, error::ISO_INVALID_PDU),
+        other => panic!("{:?}", other),
     }
+    plc.join().unwrap();
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_paths() {
+    let area: Area = constant::Area::Merker;
+    assert_eq!(area, constant::Area::Merker);
+    let status: CpuStatus = constant::CpuStatus::Run;
+    assert!(status.is_running());
 }
@@ -1,10 +1,17 @@
+//! Memory areas and word lengths used to address the PLC memory,
+//! for instance to build `client::S7DataItem`s
+
 use crate::error::Error;
 
-// Area ID
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-pub(crate) enum Area {
+/// Memory areas of the PLC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Area {
+    /// direct access to the I/O modules, bypassing the process image,
+    /// `PI` or `PQ` in the mnemonics. Bytes, words and double words only
+    DirectPeripheral = 0x80,
+    /// inputs, `I` or `E` in the German mnemonics
     ProcessInput = 0x81,
+    /// outputs, `Q` or `A` in the German mnemonics
     ProcessOutput = 0x82,
     /// Merkers are address registers within the CPU.
     /// The number of available flag bytes depends on the respective CPU and can be taken from the technical data.
@@ -13,24 +20,47 @@ pub(crate) enum Area {
     /// German thing, means building blocks
     /// This is your storage  
     DataBausteine = 0x84,
+    /// S7 counters, always read with `WL_COUNTER`
     Counter = 0x1C,
+    /// S7 timers, always read with `WL_TIMER`
     Timer = 0x1D,
     Unknown,
 }
 
 // Word Length
-pub const WL_BIT: i32 = 0x01; //Bit (inside a word)
-pub const WL_BYTE: i32 = 0x02; //Byte (8 bit)
+/// a single bit, `DBX`, only one can be transferred at a time
+pub const WL_BIT: i32 = 0x01;
+/// 8 bit, `DBB`
+pub const WL_BYTE: i32 = 0x02;
+/// 8 bit character
 pub const WL_CHAR: i32 = 0x03;
-pub const WL_WORD: i32 = 0x04; //Word (16 bit)
+/// 16 bit unsigned, `DBW`
+pub const WL_WORD: i32 = 0x04;
+/// 16 bit signed integer
 pub const WL_INT: i32 = 0x05;
-pub const WL_DWORD: i32 = 0x06; //Double Word (32 bit)
+/// 32 bit unsigned, `DBD`
+pub const WL_DWORD: i32 = 0x06;
+/// 32 bit signed integer
 pub const WL_DINT: i32 = 0x07;
-pub const WL_REAL: i32 = 0x08; //Real (32 bit float)
-pub const WL_COUNTER: i32 = 0x1C; //Counter (16 bit)
-pub const WL_TIMER: i32 = 0x1D; //Timer (16 bit)
+/// 32 bit float
+pub const WL_REAL: i32 = 0x08;
+/// 16 bit counter value, only valid in `Area::Counter`
+pub const WL_COUNTER: i32 = 0x1C;
+/// 16 bit timer value, only valid in `Area::Timer`
+pub const WL_TIMER: i32 = 0x1D;
 
-//dataSize to number of byte accordingly
+/// size in bytes of an element of the word length, 0 for unknown word lengths.
+/// A bit takes a whole byte on the wire.
+///
+/// # Examples
+///
+/// ```
+/// use s7::constant::{data_size_byte, WL_REAL};
+///
+/// // buffer for 10 reals
+/// let buffer = vec![0u8; 10 * data_size_byte(WL_REAL) as usize];
+/// assert_eq!(buffer.len(), 40);
+/// ```
 pub fn data_size_byte(word_length: i32) -> i32 {
     match word_length {
         WL_BIT | WL_BYTE | WL_CHAR => 1,
@@ -41,6 +71,7 @@ pub fn data_size_byte(word_length: i32) -> i32 {
 }
 
 // PLC Status
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum CpuStatus {
     Unknown = 0,
     Stop = 4,
@@ -56,18 +87,42 @@ impl CpuStatus {
             _ => Err(Error::InvalidCpuStatus(value)),
         }
     }
+
+    /// the PLC is in RUN mode
+    pub fn is_running(&self) -> bool {
+        *self == CpuStatus::Run
+    }
+
+    /// the PLC is in STOP mode
+    pub fn is_stopped(&self) -> bool {
+        *self == CpuStatus::Stop
+    }
 }
 
 //size header
-pub const SIZE_HEADER_READ: i32 = 31; // Header Size when Reading
-pub const SIZE_HEADER_WRITE: i32 = 35; // Header Size when Writing
+pub(crate) const SIZE_HEADER_READ: i32 = 31; // Header Size when Reading
+pub(crate) const SIZE_HEADER_WRITE: i32 = 35; // Header Size when Writing
 
 // Result transport size
-pub const TS_RES_BIT: i32 = 3;
-pub const TS_RES_BYTE: i32 = 4;
-#[allow(dead_code)]
-pub const TS_RES_INT: i32 = 5;
-//todo implement read write multi
-#[allow(dead_code)]
-pub const TS_RES_REAL: i32 = 7;
-pub const TS_RES_OCTET: i32 = 9;
+pub(crate) const TS_RES_BIT: i32 = 3;
+pub(crate) const TS_RES_BYTE: i32 = 4;
+pub(crate) const TS_RES_INT: i32 = 5;
+pub(crate) const TS_RES_REAL: i32 = 7;
+pub(crate) const TS_RES_OCTET: i32 = 9;
+
+#[test]
+fn test_cpu_status() {
+    let run = CpuStatus::from_u8(8).unwrap();
+    assert!(run.is_running());
+    assert!(!run.is_stopped());
+
+    let stop = CpuStatus::from_u8(4).unwrap();
+    assert!(stop.is_stopped());
+    assert!(!stop.is_running());
+
+    let unknown = CpuStatus::from_u8(0).unwrap();
+    assert!(!unknown.is_running());
+    assert!(!unknown.is_stopped());
+
+    assert!(CpuStatus::from_u8(1).is_err());
+}
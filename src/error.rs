@@ -2,9 +2,10 @@
 // This software may be modified and distributed under the terms
 // of the BSD license. See the LICENSE file for details.
 
+use crate::constant::CpuStatus;
 use std::error;
 use std::fmt;
-use std::io::{Error as IOError, ErrorKind};
+use std::io::{self, ErrorKind};
 
 const TCP_SOCKET_CREATION: i32 = 1;
 const TCP_CONNECTION_TIMEOUT: i32 = 2;
@@ -27,7 +28,7 @@ const CLI_JOB_PENDING: i32 = 0x00300000;
 const CLI_TOO_MANY_ITEMS: i32 = 0x00400000;
 const CLI_INVALID_DWORD_LEN: i32 = 0x00500000;
 const CLI_PARTIAL_DATA_WRITTEN: i32 = 0x00600000;
-const CLI_SIZE_OVER_PDU: i32 = 0x00700000;
+pub(crate) const CLI_SIZE_OVER_PDU: i32 = 0x00700000;
 pub(crate) const CLI_INVALID_PLC_ANSWER: i32 = 0x00800000;
 const CLI_ADDRESS_OUT_OF_RANGE: i32 = 0x00900000;
 const CLI_INVALID_TRANSPORT_SIZE: i32 = 0x00A00000;
@@ -40,7 +41,7 @@ pub(crate) const CLI_CANNOT_STOP_PLC: i32 = 0x01000000;
 const CLI_CANNOT_COPY_RAM_TO_ROM: i32 = 0x01100000;
 const CLI_CANNOT_COMPRESS: i32 = 0x01200000;
 pub(crate) const CLI_ALREADY_STOP: i32 = 0x01300000;
-const CLI_FUN_NOT_AVAILABLE: i32 = 0x01400000;
+pub(crate) const CLI_FUN_NOT_AVAILABLE: i32 = 0x01400000;
 const CLI_UPLOAD_SEQUENCE_FAILED: i32 = 0x01500000;
 const CLI_INVALID_DATA_SIZE_RECVD: i32 = 0x01600000;
 const CLI_INVALID_BLOCK_TYPE: i32 = 0x01700000;
@@ -58,6 +59,8 @@ const CLI_INVALID_PARAM_NUMBER: i32 = 0x02500000;
 const CLI_CANNOT_CHANGE_PARAM: i32 = 0x02600000;
 const CLI_FUNCTION_NOT_IMPLEMENTED: i32 = 0x02700000;
 
+const CODE_7_HARDWARE_FAULT: i32 = 1;
+const CODE_7_ACCESS_DENIED: i32 = 3;
 const CODE_7_ADDRESS_OUT_OF_RANGE: i32 = 5;
 const CODE_7_INVALID_TRANSPORT_SIZE: i32 = 6;
 const CODE_7_WRITE_DATA_SIZE_MISMATCH: i32 = 7;
@@ -69,22 +72,135 @@ const CODE_7_INVALID_PASSWORD: i32 = 54786;
 const CODE_7_NO_PASSWORD_TO_CLEAR: i32 = 54788;
 const CODE_7_NO_PASSWORD_TO_SET: i32 = 54789;
 const CODE_7_FUN_NOT_AVAILABLE: i32 = 33028;
-const CODE_7_DATA_OVER_PDU: i32 = 34048;
+pub(crate) const CODE_7_DATA_OVER_PDU: i32 = 34048;
+pub(crate) const CODE_7_INVALID_SZL_ID: i32 = 54273;
+pub(crate) const CODE_7_INVALID_SZL_INDEX: i32 = 54274;
 
 #[derive(Debug)]
 pub enum Error {
     Connect(String),
     Lock,
-    IOError(ErrorKind),
-    Response { code: i32 },
-    CPU { code: i32 },
-    InvalidInput { input: String },
+    /// the connection failed, was reset or closed
+    Io(io::Error),
+    /// `bytes` is the offending response, empty when the request was refused before sending
+    Response {
+        code: i32,
+        bytes: Vec<u8>,
+    },
+    /// `bytes` is the response carrying the CPU error code
+    CPU {
+        code: i32,
+        bytes: Vec<u8>,
+    },
+    InvalidInput {
+        input: String,
+    },
+    /// a buffer or byte slice doesn't have the size the operation needs,
+    /// `expected` is the exact size or the minimum, depending on the operation
+    BufferSize {
+        expected: usize,
+        got: usize,
+    },
     Send,
     Iso,
     PduLength(i32),
+    /// the PLC negotiated a PDU length too small for the request telegrams
+    PduTooSmall(i32),
     TryFrom(Vec<u8>, String),
     InvalidCpuStatus(u8),
-    InvalidResponse { reason: String, bytes: Vec<u8> },
+    InvalidResponse {
+        reason: String,
+        bytes: Vec<u8>,
+    },
+    /// the PLC didn't answer in time or the operation took longer than allowed
+    Timeout,
+    /// the PLC accepted the TCP connection but refused the S7 one,
+    /// usually the rack, slot or connection type don't match the PLC
+    ConnectionRefused(String),
+    /// the run watchdog found the CPU out of RUN, see `Client::set_run_watchdog`
+    NotRunning(CpuStatus),
+}
+
+/// the meaning of the code of an `Error::CPU`, see `Error::as_plc_error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlcError {
+    AddressOutOfRange,
+    ObjectDoesNotExist,
+    /// the transport size or the data length doesn't match the variable
+    DataTypeMismatch,
+    /// the object can't be accessed or the protection level forbids it
+    AccessDenied,
+    HardwareFault,
+    Unknown(i32),
+}
+
+impl From<i32> for PlcError {
+    fn from(code: i32) -> Self {
+        match code {
+            CODE_7_ADDRESS_OUT_OF_RANGE => PlcError::AddressOutOfRange,
+            CODE_7_RES_ITEM_NOT_AVAILABLE | CODE_7_RES_ITEM_NOT_AVAILABLE1 => {
+                PlcError::ObjectDoesNotExist
+            }
+            CODE_7_INVALID_TRANSPORT_SIZE | CODE_7_WRITE_DATA_SIZE_MISMATCH => {
+                PlcError::DataTypeMismatch
+            }
+            CODE_7_ACCESS_DENIED | CODE_7_NEED_PASSWORD | CODE_7_INVALID_PASSWORD => {
+                PlcError::AccessDenied
+            }
+            CODE_7_HARDWARE_FAULT => PlcError::HardwareFault,
+            _ => PlcError::Unknown(code),
+        }
+    }
+}
+
+impl Error {
+    /// the CPU error code as a `PlcError`, `None` for any other error
+    pub fn as_plc_error(&self) -> Option<PlcError> {
+        match self {
+            Error::CPU { code, .. } => Some(PlcError::from(*code)),
+            _ => None,
+        }
+    }
+}
+
+/// `io::Error` can't be cloned, an `Io` error is cloned as a new one
+/// of the same kind and message
+impl Clone for Error {
+    fn clone(&self) -> Error {
+        match self {
+            Error::Connect(s) => Error::Connect(s.clone()),
+            Error::Lock => Error::Lock,
+            Error::Io(e) => Error::Io(io::Error::new(e.kind(), e.to_string())),
+            Error::Response { code, bytes } => Error::Response {
+                code: *code,
+                bytes: bytes.clone(),
+            },
+            Error::CPU { code, bytes } => Error::CPU {
+                code: *code,
+                bytes: bytes.clone(),
+            },
+            Error::InvalidInput { input } => Error::InvalidInput {
+                input: input.clone(),
+            },
+            Error::BufferSize { expected, got } => Error::BufferSize {
+                expected: *expected,
+                got: *got,
+            },
+            Error::Send => Error::Send,
+            Error::Iso => Error::Iso,
+            Error::PduLength(pdu) => Error::PduLength(*pdu),
+            Error::PduTooSmall(pdu) => Error::PduTooSmall(*pdu),
+            Error::TryFrom(bytes, reason) => Error::TryFrom(bytes.clone(), reason.clone()),
+            Error::InvalidCpuStatus(status) => Error::InvalidCpuStatus(*status),
+            Error::InvalidResponse { reason, bytes } => Error::InvalidResponse {
+                reason: reason.clone(),
+                bytes: bytes.clone(),
+            },
+            Error::Timeout => Error::Timeout,
+            Error::ConnectionRefused(reason) => Error::ConnectionRefused(reason.clone()),
+            Error::NotRunning(status) => Error::NotRunning(*status),
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -92,15 +208,32 @@ impl fmt::Display for Error {
         match self {
             Error::Connect(s) => write!(f, "connection error: {}", s),
             Error::Lock => write!(f, "Lock error: panicked"),
-            Error::IOError(kind) => write!(f, "IO error: {:?}", kind),
-            Error::Response { code } => write!(f, "Error response: {}", error_text(*code)),
-            Error::CPU { code } => {
-                write!(f, "Error response CPU: {}", error_text(cpu_error(*code)))
+            Error::Io(e) => write!(f, "IO error: {}", e),
+            Error::Response { code, bytes } if bytes.is_empty() => {
+                write!(f, "Error response: {}", error_text(*code))
+            }
+            Error::Response { code, bytes } => {
+                write!(f, "Error response: {} {:?}", error_text(*code), bytes)
             }
+            Error::CPU { code, bytes } => write!(
+                f,
+                "Error response CPU: {} {:?}",
+                error_text(cpu_error(*code)),
+                bytes
+            ),
             Error::InvalidInput { input } => write!(f, "Invalid input: {}", input),
+            Error::BufferSize { expected, got } => {
+                write!(f, "buffer of {} bytes, {} expected", got, expected)
+            }
             Error::Send => write!(f, "Send connection error"),
             Error::Iso => write!(f, "ISO connection error"),
             Error::PduLength(pdu) => write!(f, "PDU length connection error {}", pdu),
+            Error::PduTooSmall(pdu) => write!(
+                f,
+                "PLC negotiated an unusable PDU length {}, at least {} is required",
+                pdu,
+                crate::transport::MIN_PDU_LENGTH
+            ),
             Error::TryFrom(bytes, reason) => {
                 write!(f, "Could not read bytes {:?} reason {}", bytes, reason)
             }
@@ -108,19 +241,34 @@ impl fmt::Display for Error {
             Error::InvalidResponse { reason, bytes } => {
                 write!(f, "Invalid response {:?} err {}", bytes, reason)
             }
+            Error::Timeout => write!(f, "Timeout"),
+            Error::ConnectionRefused(reason) => write!(
+                f,
+                "PLC refused the connection ({}), check the rack, slot and connection type",
+                reason
+            ),
+            Error::NotRunning(status) => write!(f, "PLC is not in RUN: {:?}", status),
         }
     }
 }
 
-impl From<IOError> for Error {
-    fn from(e: IOError) -> Self {
-        Error::IOError(e.kind())
+/// socket timeouts become `Error::Timeout`, depending on the platform
+/// they are reported as `TimedOut` or `WouldBlock`
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => Error::Timeout,
+            _ => Error::Io(e),
+        }
     }
 }
 // This is important for other errors to wrap this one.
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        None
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
     }
 }
 
@@ -201,3 +349,62 @@ fn error_text(err: i32) -> &'static str {
         _ => "CLI : Unknown error",
     }
 }
+
+#[test]
+fn test_from_io_error() {
+    use std::error::Error as _;
+
+    for kind in [ErrorKind::TimedOut, ErrorKind::WouldBlock].iter() {
+        match Error::from(io::Error::from(*kind)) {
+            Error::Timeout => {}
+            other => panic!("expected Timeout got {:?}", other),
+        }
+    }
+
+    let reset = Error::from(io::Error::from(ErrorKind::ConnectionReset));
+    assert!(reset.source().is_some());
+    match reset {
+        Error::Io(e) => assert_eq!(e.kind(), ErrorKind::ConnectionReset),
+        other => panic!("expected Io got {:?}", other),
+    }
+}
+
+#[test]
+fn test_as_plc_error() {
+    let cpu = |code| Error::CPU {
+        code,
+        bytes: Vec::new(),
+    };
+
+    assert_eq!(cpu(1).as_plc_error(), Some(PlcError::HardwareFault));
+    assert_eq!(cpu(3).as_plc_error(), Some(PlcError::AccessDenied));
+    assert_eq!(cpu(5).as_plc_error(), Some(PlcError::AddressOutOfRange));
+    assert_eq!(cpu(6).as_plc_error(), Some(PlcError::DataTypeMismatch));
+    assert_eq!(cpu(7).as_plc_error(), Some(PlcError::DataTypeMismatch));
+    assert_eq!(cpu(10).as_plc_error(), Some(PlcError::ObjectDoesNotExist));
+    assert_eq!(
+        cpu(0xD209).as_plc_error(),
+        Some(PlcError::ObjectDoesNotExist)
+    );
+    assert_eq!(cpu(0xD241).as_plc_error(), Some(PlcError::AccessDenied));
+    assert_eq!(cpu(0x8104).as_plc_error(), Some(PlcError::Unknown(0x8104)));
+    assert_eq!(Error::Timeout.as_plc_error(), None);
+}
+
+#[test]
+fn test_clone() {
+    let e = Error::Io(io::Error::new(ErrorKind::ConnectionReset, "reset by peer"));
+    match e.clone() {
+        Error::Io(cloned) => {
+            assert_eq!(cloned.kind(), ErrorKind::ConnectionReset);
+            assert_eq!(cloned.to_string(), "reset by peer");
+        }
+        other => panic!("expected Io got {:?}", other),
+    }
+
+    let e = Error::CPU {
+        code: 5,
+        bytes: vec![1, 2],
+    };
+    assert_eq!(e.clone().to_string(), e.to_string());
+}
@@ -6,6 +6,9 @@
 
 use super::error::Error;
 use byteorder::{BigEndian, ByteOrder};
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::time::Duration;
 
 /// Fields collection type alias for convenience
 /// # Examples
@@ -30,6 +33,434 @@ use byteorder::{BigEndian, ByteOrder};
 /// ```
 pub type Fields = Vec<Box<dyn Field>>;
 
+/// splits a Siemens `byte.bit` offset like `8.4` into the byte and the bit index.
+/// The digit after the decimal point is rounded, so `8.3` is always bit 3
+/// even though `0.3` has no exact float representation.
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::split_offset;
+///
+/// assert_eq!(split_offset(8.4), (8, 4));
+/// assert_eq!(split_offset(8.7), (8, 7));
+/// assert_eq!(split_offset(12.0), (12, 0));
+/// ```
+pub fn split_offset(offset: f32) -> (u16, u8) {
+    let byte = offset.trunc();
+    let bit = ((offset - byte) * 10.0).round();
+    (byte as u16, bit as u8)
+}
+
+/// joins a byte and a bit index into a Siemens `byte.bit` offset
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::{join_offset, split_offset};
+///
+/// assert_eq!(split_offset(join_offset(8, 3)), (8, 3));
+/// ```
+pub fn join_offset(byte: u16, bit: u8) -> f32 {
+    byte as f32 + bit as f32 / 10.0
+}
+
+/// the first `size` bytes of `bytes`
+fn leading(bytes: &[u8], size: usize) -> Result<&[u8], Error> {
+    bytes.get(..size).ok_or(Error::BufferSize {
+        expected: size,
+        got: bytes.len(),
+    })
+}
+
+/// decodes bit `bit` of the first byte, without building a `Bool`
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::get_bool;
+///
+/// assert!(get_bool(&[0b0000_1000], 3).unwrap());
+/// assert!(get_bool(&[], 3).is_err());
+/// ```
+pub fn get_bool(bytes: &[u8], bit: u8) -> Result<bool, Error> {
+    if bit > 7 {
+        return Err(Error::TryFrom(
+            bytes.to_vec(),
+            format!("get_bool: bit offset {} out of range", bit),
+        ));
+    }
+    Ok(leading(bytes, 1)?[0] & (1 << bit) != 0)
+}
+
+/// decodes a big endian `WORD` from the first 2 bytes, without building a `Word`
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::get_word;
+///
+/// assert_eq!(get_word(&[0xAB, 0xCD, 0xFF]).unwrap(), 0xABCD);
+/// assert!(get_word(&[0xAB]).is_err());
+/// ```
+pub fn get_word(bytes: &[u8]) -> Result<u16, Error> {
+    Ok(BigEndian::read_u16(leading(bytes, 2)?))
+}
+
+/// decodes a big endian `INT` from the first 2 bytes
+pub fn get_int(bytes: &[u8]) -> Result<i16, Error> {
+    Ok(BigEndian::read_i16(leading(bytes, 2)?))
+}
+
+/// decodes a big endian `DWORD` from the first 4 bytes, without building a `DWord`
+pub fn get_dword(bytes: &[u8]) -> Result<u32, Error> {
+    Ok(BigEndian::read_u32(leading(bytes, 4)?))
+}
+
+/// decodes a big endian `DINT` from the first 4 bytes, without building a `DInt`
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::get_dint;
+///
+/// assert_eq!(get_dint(&[0xFF, 0xFF, 0xFF, 0xFE]).unwrap(), -2);
+/// ```
+pub fn get_dint(bytes: &[u8]) -> Result<i32, Error> {
+    Ok(BigEndian::read_i32(leading(bytes, 4)?))
+}
+
+/// decodes a big endian `REAL` from the first 4 bytes, without building a `Float`
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::get_real;
+///
+/// assert_eq!(get_real(&[66, 86, 0, 0]).unwrap(), 53.5);
+/// ```
+pub fn get_real(bytes: &[u8]) -> Result<f32, Error> {
+    Ok(BigEndian::read_f32(leading(bytes, 4)?))
+}
+
+/// decodes the 3 BCD digits of a value read from `Area::Counter`
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::counter_value;
+///
+/// assert_eq!(counter_value(0x0123).unwrap(), 123);
+/// ```
+pub fn counter_value(word: u16) -> Result<u16, Error> {
+    bcd(word & 0x0FFF).ok_or_else(|| {
+        Error::TryFrom(
+            word.to_be_bytes().to_vec(),
+            format!("counter_value: invalid BCD {:#06x}", word),
+        )
+    })
+}
+
+/// encodes a counter value as the 3 BCD digits `Area::Counter` holds,
+/// 2 bytes per counter once converted with `to_be_bytes`
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::counter_word;
+///
+/// assert_eq!(counter_word(123).unwrap(), 0x0123);
+/// assert!(counter_word(1000).is_err());
+/// ```
+pub fn counter_word(value: u16) -> Result<u16, Error> {
+    if value > 999 {
+        return Err(Error::InvalidInput {
+            input: format!("counter value {}, at most 999", value),
+        });
+    }
+    Ok(((value / 100) << 8) | ((value / 10 % 10) << 4) | (value % 10))
+}
+
+/// decodes a S5TIME as read from `Area::Timer`,
+/// 3 BCD digits multiplied by the time base in bits 12 and 13
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::s5time;
+/// use std::time::Duration;
+///
+/// // 250 * 100ms
+/// assert_eq!(s5time(0x1250).unwrap(), Duration::from_secs(25));
+/// ```
+pub fn s5time(word: u16) -> Result<Duration, Error> {
+    let base = match (word >> 12) & 0x03 {
+        0 => 10,
+        1 => 100,
+        2 => 1000,
+        _ => 10_000,
+    };
+    match bcd(word & 0x0FFF) {
+        Some(value) => Ok(Duration::from_millis(value as u64 * base)),
+        None => Err(Error::TryFrom(
+            word.to_be_bytes().to_vec(),
+            format!("s5time: invalid BCD {:#06x}", word),
+        )),
+    }
+}
+
+/// binary value of packed BCD digits, `None` if a digit is over 9
+fn bcd(mut digits: u16) -> Option<u16> {
+    let mut value = 0;
+    let mut weight = 1;
+    while digits > 0 {
+        let digit = digits & 0x0F;
+        if digit > 9 {
+            return None;
+        }
+        value += digit * weight;
+        weight *= 10;
+        digits >>= 4;
+    }
+    Some(value)
+}
+
+/// decodes an `ARRAY[1..count] OF STRING[max_len]`. Each element takes `max_len + 2` bytes,
+/// its maximum and current length followed by the characters, one byte each.
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::{read_string_array, write_string_array};
+///
+/// let bytes = write_string_array(&["pump on", "low level"], 10).unwrap();
+/// assert_eq!(bytes.len(), 2 * 12);
+/// assert_eq!(read_string_array(&bytes, 10, 2).unwrap(), vec!["pump on", "low level"]);
+/// ```
+pub fn read_string_array(buffer: &[u8], max_len: u8, count: usize) -> Result<Vec<String>, Error> {
+    let stride = max_len as usize + 2;
+    let bytes = leading(buffer, stride * count)?;
+
+    bytes
+        .chunks(stride)
+        .enumerate()
+        .map(|(i, element)| {
+            let len = element[1];
+            if len > max_len {
+                return Err(Error::TryFrom(
+                    element.to_vec(),
+                    format!(
+                        "read_string_array: element {} of {} characters, at most {}",
+                        i, len, max_len
+                    ),
+                ));
+            }
+            Ok(element[2..2 + len as usize]
+                .iter()
+                .map(|b| *b as char)
+                .collect())
+        })
+        .collect()
+}
+
+/// encodes `strings` as an `ARRAY OF STRING[max_len]`, each element padded
+/// to `max_len + 2` bytes. Characters have to fit in a byte (Latin-1).
+pub fn write_string_array<S: AsRef<str>>(strings: &[S], max_len: u8) -> Result<Vec<u8>, Error> {
+    let stride = max_len as usize + 2;
+    let mut bytes = Vec::with_capacity(stride * strings.len());
+
+    for string in strings.iter() {
+        let string = string.as_ref();
+        let chars = string
+            .chars()
+            .map(|c| u8::try_from(c as u32).ok())
+            .collect::<Option<Vec<u8>>>()
+            .filter(|chars| chars.len() <= max_len as usize)
+            .ok_or_else(|| {
+                Error::TryFrom(
+                    string.as_bytes().to_vec(),
+                    format!(
+                        "write_string_array: {:?} doesn't fit a STRING[{}]",
+                        string, max_len
+                    ),
+                )
+            })?;
+
+        let start = bytes.len();
+        bytes.push(max_len);
+        bytes.push(chars.len() as u8);
+        bytes.extend_from_slice(&chars);
+        bytes.resize(start + stride, 0);
+    }
+    Ok(bytes)
+}
+
+/// a value decoded from the PLC memory
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Byte(u8),
+    Word(u16),
+    /// 16 bit signed integer
+    Int(i16),
+    DWord(u32),
+    /// 32 bit signed integer
+    DInt(i32),
+    /// 32 bit float
+    Real(f32),
+    /// the characters of a `STRING`, Latin-1
+    String(String),
+}
+
+/// the S7 elementary type of a variable, parsed from its name like `REAL` or `STRING[20]`
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::{Kind, Value};
+///
+/// let kind: Kind = "dint".parse().unwrap();
+/// assert_eq!(kind.size(), 4);
+/// assert_eq!(kind.decode(&[0xFF, 0xFF, 0xFF, 0xFE], 0).unwrap(), Value::DInt(-2));
+///
+/// let kind: Kind = "STRING[8]".parse().unwrap();
+/// assert_eq!(kind.encode(&Value::String("on".to_string())).unwrap().len(), 10);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Bool,
+    Byte,
+    Word,
+    Int,
+    DWord,
+    DInt,
+    Real,
+    /// a `STRING` of at most this many characters, `STRING` alone holds 254
+    String(u8),
+}
+
+impl Kind {
+    /// size in bytes, a bool takes the byte holding it
+    pub fn size(&self) -> i32 {
+        match self {
+            Kind::Bool | Kind::Byte => 1,
+            Kind::Word | Kind::Int => 2,
+            Kind::DWord | Kind::DInt | Kind::Real => 4,
+            Kind::String(max_len) => *max_len as i32 + 2,
+        }
+    }
+
+    /// the bytes of `value` as stored in the PLC, a bool as the single byte of a bit write.
+    /// The value has to be of this kind.
+    pub fn encode(&self, value: &Value) -> Result<Vec<u8>, Error> {
+        Ok(match (self, value) {
+            (Kind::Bool, Value::Bool(v)) => vec![*v as u8],
+            (Kind::Byte, Value::Byte(v)) => vec![*v],
+            (Kind::Word, Value::Word(v)) => v.to_be_bytes().to_vec(),
+            (Kind::Int, Value::Int(v)) => v.to_be_bytes().to_vec(),
+            (Kind::DWord, Value::DWord(v)) => v.to_be_bytes().to_vec(),
+            (Kind::DInt, Value::DInt(v)) => v.to_be_bytes().to_vec(),
+            (Kind::Real, Value::Real(v)) => v.to_be_bytes().to_vec(),
+            (Kind::String(max_len), Value::String(v)) => write_string_array(&[v], *max_len)?,
+            _ => {
+                return Err(Error::InvalidInput {
+                    input: format!("{:?} is not a {:?}", value, self),
+                })
+            }
+        })
+    }
+
+    /// decodes the leading bytes of `bytes`, `bit` is only used by `Bool`
+    pub fn decode(&self, bytes: &[u8], bit: u8) -> Result<Value, Error> {
+        Ok(match self {
+            Kind::Bool => Value::Bool(get_bool(bytes, bit)?),
+            Kind::Byte => Value::Byte(leading(bytes, 1)?[0]),
+            Kind::Word => Value::Word(get_word(bytes)?),
+            Kind::Int => Value::Int(get_int(bytes)?),
+            Kind::DWord => Value::DWord(get_dword(bytes)?),
+            Kind::DInt => Value::DInt(get_dint(bytes)?),
+            Kind::Real => Value::Real(get_real(bytes)?),
+            Kind::String(max_len) => {
+                Value::String(read_string_array(bytes, *max_len, 1)?.remove(0))
+            }
+        })
+    }
+}
+
+impl FromStr for Kind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Kind, Error> {
+        match s.trim().to_uppercase().as_str() {
+            "BOOL" => Ok(Kind::Bool),
+            "BYTE" => Ok(Kind::Byte),
+            "WORD" => Ok(Kind::Word),
+            "INT" => Ok(Kind::Int),
+            "DWORD" => Ok(Kind::DWord),
+            "DINT" => Ok(Kind::DInt),
+            "REAL" => Ok(Kind::Real),
+            "STRING" => Ok(Kind::String(254)),
+            name => name
+                .strip_prefix("STRING[")
+                .and_then(|rest| rest.strip_suffix(']'))
+                .and_then(|max_len| max_len.trim().parse().ok())
+                .filter(|max_len| *max_len <= 254)
+                .map(Kind::String)
+                .ok_or_else(|| Error::InvalidInput {
+                    input: format!("type {}", s),
+                }),
+        }
+    }
+}
+
+/// decodes the leading bytes of `bytes` as a value of `kind`, bit 0 for a `Bool`
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::{decode, encode, Kind, Value};
+///
+/// let bytes = encode(Kind::Int, &Value::Int(-300)).unwrap();
+/// assert_eq!(decode(Kind::Int, &bytes).unwrap(), Value::Int(-300));
+/// ```
+pub fn decode(kind: Kind, bytes: &[u8]) -> Result<Value, Error> {
+    kind.decode(bytes, 0)
+}
+
+/// the bytes of `value` as a `kind` in the PLC memory, see `Kind::encode`
+pub fn encode(kind: Kind, value: &Value) -> Result<Vec<u8>, Error> {
+    kind.encode(value)
+}
+
+/// size in bytes of a data block or struct declaring `fields` in order, as the PLC lays
+/// them out without optimized access: consecutive bools share the bits of a byte,
+/// a byte starts on the next byte, larger types on the next even byte
+/// and the whole is rounded up to an even size.
+///
+/// # Examples
+///
+/// ```
+/// use s7::field::{aligned_size, Kind};
+///
+/// // 3 bools in byte 0, a byte at 1, a real at 2
+/// assert_eq!(aligned_size(&[Kind::Bool, Kind::Bool, Kind::Bool, Kind::Byte, Kind::Real]), 6);
+/// // a byte at 0, an int at 2, a byte at 4, padded
+/// assert_eq!(aligned_size(&[Kind::Byte, Kind::Int, Kind::Byte]), 6);
+/// ```
+pub fn aligned_size(fields: &[Kind]) -> usize {
+    let round_up = |value: usize, multiple: usize| value.div_ceil(multiple) * multiple;
+
+    let mut bits = 0;
+    for kind in fields.iter() {
+        bits = match kind {
+            Kind::Bool => bits + 1,
+            Kind::Byte => round_up(bits, 8) + 8,
+            _ => round_up(bits, 16) + kind.size() as usize * 8,
+        };
+    }
+    round_up(bits, 16) / 8
+}
+
 /// represents a type stored in the hardware
 /// ie `bool`, `real(32 bit float)`
 pub trait Field {
@@ -56,15 +487,14 @@ pub struct Float {
 
 impl Float {
     pub fn new(data_block: i32, offset: f32, mut bytes: Vec<u8>) -> Result<Float, Error> {
-        let len = bytes.len();
         if bytes.len() != Float::size() as usize {
-            return Err(Error::TryFrom(
-                bytes,
-                format!("Float.new: expected buf size {} got {}", Float::size(), len),
-            ));
+            return Err(Error::BufferSize {
+                expected: Float::size() as usize,
+                got: bytes.len(),
+            });
         }
 
-        let bit_offset = ((offset * 10.0) as usize % 10) as u8;
+        let bit_offset = split_offset(offset).1;
         if bit_offset != 0 {
             return Err(Error::TryFrom(
                 bytes,
@@ -107,7 +537,7 @@ impl Field for Float {
     fn to_bytes(&self) -> Vec<u8> {
         let mut buf = vec![0u8; Float::size() as usize];
         BigEndian::write_f32(buf.as_mut_slice(), self.value);
-        return buf;
+        buf
     }
 }
 
@@ -128,15 +558,14 @@ pub struct Bool {
 
 impl Bool {
     pub fn new(data_block: i32, offset: f32, bytes: Vec<u8>) -> Result<Self, Error> {
-        let len = bytes.len();
         if bytes.len() != Self::size() as usize {
-            return Err(Error::TryFrom(
-                bytes,
-                format!("Bool.new: expected buf size {} got {}", Self::size(), len),
-            ));
+            return Err(Error::BufferSize {
+                expected: Self::size() as usize,
+                got: bytes.len(),
+            });
         }
 
-        let bit_offset = ((offset * 10.0) as usize % 10) as u8;
+        let bit_offset = split_offset(offset).1;
         if bit_offset > 7 {
             return Err(Error::TryFrom(
                 bytes,
@@ -157,7 +586,7 @@ impl Bool {
         if val {
             return b | (1 << bit_pos);
         }
-        return b & !(1 << bit_pos);
+        b & !(1 << bit_pos)
     }
 
     pub fn size() -> i32 {
@@ -171,11 +600,7 @@ impl Bool {
 
     pub fn set_value(&mut self, v: bool) {
         self.value = v;
-        self.byte = Bool::set_value_at(
-            self.byte,
-            ((self.offset * 10.0) as usize % 10) as u8,
-            self.value,
-        );
+        self.byte = Bool::set_value_at(self.byte, split_offset(self.offset).1, self.value);
     }
 }
 
@@ -206,15 +631,14 @@ pub struct Word {
 
 impl Word {
     pub fn new(data_block: i32, offset: f32, mut bytes: Vec<u8>) -> Result<Word, Error> {
-        let len = bytes.len();
         if bytes.len() != Word::size() as usize {
-            return Err(Error::TryFrom(
-                bytes,
-                format!("Word.new: expected buf size {} got {}", Word::size(), len),
-            ));
+            return Err(Error::BufferSize {
+                expected: Word::size() as usize,
+                got: bytes.len(),
+            });
         }
 
-        let bit_offset = ((offset * 10.0) as usize % 10) as u8;
+        let bit_offset = split_offset(offset).1;
         if bit_offset != 0 {
             return Err(Error::TryFrom(
                 bytes,
@@ -257,10 +681,160 @@ impl Field for Word {
     fn to_bytes(&self) -> Vec<u8> {
         let mut buf = vec![0u8; Word::size() as usize];
         BigEndian::write_u16(buf.as_mut_slice(), self.value);
-        return buf;
+        buf
+    }
+}
+
+/// PLC double word field, unsigned 32 bit, like bit packed flags.
+/// `DInt` reads the same bytes as signed
+#[derive(Debug)]
+pub struct DWord {
+    data_block: i32,
+    /// offset example 8.1
+    /// left side is index within the block
+    /// right side is the bit position only used for bool, zero for all other types
+    offset: f32,
+    value: u32,
+}
+
+impl DWord {
+    pub fn new(data_block: i32, offset: f32, bytes: Vec<u8>) -> Result<DWord, Error> {
+        if bytes.len() != DWord::size() as usize {
+            return Err(Error::BufferSize {
+                expected: DWord::size() as usize,
+                got: bytes.len(),
+            });
+        }
+
+        let bit_offset = split_offset(offset).1;
+        if bit_offset != 0 {
+            return Err(Error::TryFrom(
+                bytes,
+                format!(
+                    "DWord.new: dword should not have a bit offset got {}",
+                    bit_offset
+                ),
+            ));
+        }
+
+        Ok(DWord {
+            data_block,
+            offset,
+            value: BigEndian::read_u32(bytes.as_slice()),
+        })
+    }
+
+    pub fn size() -> i32 {
+        4
+    }
+
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    pub fn set_value(&mut self, v: u32) {
+        self.value = v
+    }
+}
+
+impl Field for DWord {
+    fn data_block(&self) -> i32 {
+        self.data_block
+    }
+
+    fn offset(&self) -> i32 {
+        self.offset as i32
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0u8; DWord::size() as usize];
+        BigEndian::write_u32(buf.as_mut_slice(), self.value);
+        buf
+    }
+}
+
+/// PLC double integer field, signed 32 bit in two's complement.
+/// `DWord` reads the same bytes as unsigned
+#[derive(Debug)]
+pub struct DInt {
+    data_block: i32,
+    /// offset example 8.1
+    /// left side is index within the block
+    /// right side is the bit position only used for bool, zero for all other types
+    offset: f32,
+    value: i32,
+}
+
+impl DInt {
+    pub fn new(data_block: i32, offset: f32, bytes: Vec<u8>) -> Result<DInt, Error> {
+        if bytes.len() != DInt::size() as usize {
+            return Err(Error::BufferSize {
+                expected: DInt::size() as usize,
+                got: bytes.len(),
+            });
+        }
+
+        let bit_offset = split_offset(offset).1;
+        if bit_offset != 0 {
+            return Err(Error::TryFrom(
+                bytes,
+                format!(
+                    "DInt.new: dint should not have a bit offset got {}",
+                    bit_offset
+                ),
+            ));
+        }
+
+        Ok(DInt {
+            data_block,
+            offset,
+            value: BigEndian::read_i32(bytes.as_slice()),
+        })
+    }
+
+    pub fn size() -> i32 {
+        4
+    }
+
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
+    pub fn set_value(&mut self, v: i32) {
+        self.value = v
     }
 }
 
+impl Field for DInt {
+    fn data_block(&self) -> i32 {
+        self.data_block
+    }
+
+    fn offset(&self) -> i32 {
+        self.offset as i32
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0u8; DInt::size() as usize];
+        BigEndian::write_i32(buf.as_mut_slice(), self.value);
+        buf
+    }
+}
+
+#[test]
+fn test_split_join_offset() {
+    for byte in [0u16, 1, 8, 255, 1000].iter() {
+        for bit in 0..8u8 {
+            assert_eq!(split_offset(join_offset(*byte, bit)), (*byte, bit));
+        }
+    }
+
+    // used to truncate to bit 6
+    assert_eq!(split_offset(8.7), (8, 7));
+
+    let b = Bool::new(888, 8.7, vec![0b1000_0000]).unwrap();
+    assert!(b.value());
+}
 
 #[test]
 fn test_fields() {
@@ -281,6 +855,7 @@ fn test_fields() {
 }
 
 #[test]
+#[allow(clippy::assertions_on_constants, clippy::single_match)]
 fn test_float() {
     let val: f32 = 53.5;
     let mut b = vec![0u8; Float::size() as usize];
@@ -303,6 +878,11 @@ fn test_float() {
 }
 
 #[test]
+#[allow(
+    clippy::assertions_on_constants,
+    clippy::bool_assert_comparison,
+    clippy::single_match
+)]
 fn test_bool() {
     let b = vec![1u8; 1];
     let mut field = Bool::new(888, 8.1, b).unwrap();
@@ -342,6 +922,7 @@ fn test_bool() {
 }
 
 #[test]
+#[allow(clippy::assertions_on_constants, clippy::single_match)]
 fn test_word() {
     let val: u16 = 43981;
     let mut b = vec![0u8; Word::size() as usize];
@@ -362,3 +943,148 @@ fn test_word() {
         Err(_) => {}
     }
 }
+
+#[test]
+fn test_counter_and_s5time() {
+    assert_eq!(counter_value(0x0999).unwrap(), 999);
+    assert_eq!(counter_value(0x0000).unwrap(), 0);
+    assert!(counter_value(0x00A1).is_err());
+    for value in [0, 7, 42, 999] {
+        assert_eq!(counter_value(counter_word(value).unwrap()).unwrap(), value);
+    }
+    assert_eq!(counter_word(305).unwrap(), 0x0305);
+    assert!(counter_word(1000).is_err());
+
+    assert_eq!(s5time(0x0001).unwrap(), Duration::from_millis(10));
+    assert_eq!(s5time(0x2120).unwrap(), Duration::from_secs(120));
+    assert_eq!(s5time(0x3999).unwrap(), Duration::from_secs(9990));
+    assert!(s5time(0x10F0).is_err());
+}
+
+#[test]
+fn test_dword_and_dint() {
+    let bytes = vec![0xFF, 0xFF, 0xFF, 0xFE];
+    let dword = DWord::new(888, 8.0, bytes.clone()).unwrap();
+    let mut dint = DInt::new(888, 8.0, bytes.clone()).unwrap();
+    assert_eq!(dword.value(), 0xFFFF_FFFE);
+    assert_eq!(dint.value(), -2);
+    assert_eq!(dword.to_bytes(), bytes);
+    assert_eq!(dint.to_bytes(), bytes);
+
+    dint.set_value(i32::MIN);
+    assert_eq!(dint.to_bytes(), vec![0x80, 0, 0, 0]);
+    let mut dword = DWord::new(888, 8.0, dint.to_bytes()).unwrap();
+    assert_eq!(dword.value(), 0x8000_0000);
+    dword.set_value(0x0102_0304);
+    assert_eq!(dword.to_bytes(), vec![1, 2, 3, 4]);
+
+    assert!(DWord::new(888, 8.1, vec![0; 4]).is_err());
+    match DInt::new(888, 8.0, vec![0; 2]) {
+        Err(Error::BufferSize {
+            expected: 4,
+            got: 2,
+        }) => {}
+        other => panic!("expected BufferSize got {:?}", other),
+    }
+}
+
+#[test]
+fn test_get_decoders() {
+    let bytes = [0x80, 0x00, 0x00, 0x01, 0xFF];
+    assert!(get_bool(&bytes, 7).unwrap());
+    assert!(!get_bool(&bytes, 0).unwrap());
+    assert!(get_bool(&bytes, 8).is_err());
+    assert_eq!(get_word(&bytes).unwrap(), 0x8000);
+    assert_eq!(get_int(&bytes).unwrap(), i16::MIN);
+    assert_eq!(get_dword(&bytes).unwrap(), 0x8000_0001);
+    assert_eq!(get_dint(&bytes).unwrap(), i32::MIN + 1);
+    assert_eq!(get_real(&53.5f32.to_be_bytes()).unwrap(), 53.5);
+
+    for short in [&bytes[..0], &bytes[..1], &bytes[..3]].iter() {
+        assert!(get_dint(short).is_err());
+        assert!(get_real(short).is_err());
+        assert!(get_dword(short).is_err());
+    }
+    assert!(get_word(&bytes[..1]).is_err());
+    match get_int(&[]) {
+        Err(Error::BufferSize {
+            expected: 2,
+            got: 0,
+        }) => {}
+        other => panic!("expected BufferSize got {:?}", other),
+    }
+}
+
+#[test]
+fn test_string_array() {
+    // STRING[4] elements: "ab", "" and "wxyz"
+    let bytes = [
+        4, 2, b'a', b'b', 0, 0, 4, 0, 0, 0, 0, 0, 4, 4, b'w', b'x', b'y', b'z',
+    ];
+    assert_eq!(
+        read_string_array(&bytes, 4, 3).unwrap(),
+        vec!["ab", "", "wxyz"]
+    );
+    assert_eq!(write_string_array(&["ab", "", "wxyz"], 4).unwrap(), bytes);
+    assert_eq!(
+        read_string_array(&bytes, 4, 0).unwrap(),
+        Vec::<String>::new()
+    );
+
+    assert!(read_string_array(&bytes, 4, 4).is_err());
+    let mut too_long = bytes;
+    too_long[7] = 5;
+    assert!(read_string_array(&too_long, 4, 3).is_err());
+    assert!(write_string_array(&["abcde"], 4).is_err());
+    assert!(write_string_array(&["€"], 4).is_err());
+    assert_eq!(write_string_array(&["é"], 1).unwrap(), vec![1, 1, 0xE9]);
+}
+
+#[test]
+fn test_aligned_size() {
+    assert_eq!(aligned_size(&[]), 0);
+    assert_eq!(aligned_size(&[Kind::Bool]), 2);
+    assert_eq!(aligned_size(&[Kind::Byte]), 2);
+    // 8 bools fill byte 0, the ninth starts byte 1
+    assert_eq!(aligned_size(&[Kind::Bool; 8]), 2);
+    assert_eq!(aligned_size(&[Kind::Bool; 17]), 4);
+    // bytes pack at odd offsets
+    assert_eq!(aligned_size(&[Kind::Byte, Kind::Byte, Kind::Byte]), 4);
+    // a bool after a word starts a new byte, the int after it the next even one
+    assert_eq!(
+        aligned_size(&[Kind::Word, Kind::Bool, Kind::Int, Kind::Bool, Kind::Byte]),
+        8
+    );
+    assert_eq!(
+        aligned_size(&[Kind::Bool, Kind::DInt, Kind::Byte, Kind::Real, Kind::DWord]),
+        16
+    );
+}
+
+#[test]
+fn test_kind_encode_decode() {
+    let values = [
+        (Kind::Bool, Value::Bool(true)),
+        (Kind::Byte, Value::Byte(0xA5)),
+        (Kind::Word, Value::Word(0xBEEF)),
+        (Kind::Int, Value::Int(-2)),
+        (Kind::DWord, Value::DWord(0xDEAD_BEEF)),
+        (Kind::DInt, Value::DInt(-70000)),
+        (Kind::Real, Value::Real(121.5)),
+        (Kind::String(10), Value::String("tank é".to_string())),
+    ];
+    for (kind, value) in values.iter() {
+        let bytes = encode(*kind, value).unwrap();
+        assert_eq!(bytes.len(), kind.size() as usize, "{:?}", kind);
+        assert_eq!(&decode(*kind, &bytes).unwrap(), value);
+    }
+    assert_eq!(encode(Kind::Int, &Value::Int(1)).unwrap(), vec![0, 1]);
+    assert!(encode(Kind::Int, &Value::Word(1)).is_err());
+    assert!(encode(Kind::String(2), &Value::String("abc".to_string())).is_err());
+
+    assert_eq!("string".parse::<Kind>().unwrap(), Kind::String(254));
+    assert_eq!("STRING[ 20 ]".parse::<Kind>().unwrap(), Kind::String(20));
+    assert!("STRING[255]".parse::<Kind>().is_err());
+    assert!("STRING[".parse::<Kind>().is_err());
+    assert_eq!(aligned_size(&[Kind::Byte, Kind::String(3)]), 8);
+}
@@ -0,0 +1,127 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! Describes the variables of a data block at runtime, for tools that can't know its shape
+
+use super::address::Address;
+use super::constant::{self, Area};
+use super::error::Error;
+use super::field::Kind;
+use std::collections::HashSet;
+
+/// The named and typed variables of a data block, read at once by `Client::read_layout`
+///
+/// # Examples
+///
+/// ```
+/// use s7::layout::DbLayout;
+///
+/// let layout = DbLayout::from_csv(
+///     888,
+///     "speed,DBD8,REAL\n\
+///      lights,DBX12.4,BOOL\n\
+///      recipe,DBB14,STRING[20]\n",
+/// )
+/// .unwrap();
+/// assert_eq!(layout.entries().len(), 3);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbLayout {
+    db_number: i32,
+    entries: Vec<(String, Address, Kind)>,
+}
+
+impl DbLayout {
+    /// checks every address is in the data block and matches the size of its kind,
+    /// addresses without a block number like `DBW4` belong to `db_number`.
+    /// A `STRING` starts at a byte address like `DBB20` and takes the size of its kind.
+    /// Names are the keys of the values read, they have to be unique.
+    pub fn new(db_number: i32, entries: Vec<(String, Address, Kind)>) -> Result<DbLayout, Error> {
+        let mut names = HashSet::with_capacity(entries.len());
+        for (name, address, kind) in entries.iter() {
+            if !names.insert(name) {
+                return Err(Error::InvalidInput {
+                    input: format!("duplicate layout name {}", name),
+                });
+            }
+            if address.area != Area::DataBausteine
+                || (address.db_number != 0 && address.db_number != db_number)
+            {
+                return Err(Error::InvalidInput {
+                    input: format!("{} at {} is not in DB{}", name, address, db_number),
+                });
+            }
+            let fits = match kind {
+                Kind::Bool => address.word_len == constant::WL_BIT,
+                Kind::String(_) => address.word_len == constant::WL_BYTE,
+                _ => address.word_len != constant::WL_BIT && address.size() == kind.size(),
+            };
+            if !fits {
+                return Err(Error::InvalidInput {
+                    input: format!("{} at {} can't hold a {:?}", name, address, kind),
+                });
+            }
+        }
+        Ok(DbLayout { db_number, entries })
+    }
+
+    /// parses one `name,address,type` line per variable, as exported from the
+    /// symbol table, like `speed,DB888.DBD8,REAL`. Blank lines are skipped.
+    pub fn from_csv(db_number: i32, csv: &str) -> Result<DbLayout, Error> {
+        let mut entries = Vec::new();
+
+        for line in csv.lines().filter(|line| !line.trim().is_empty()) {
+            let columns: Vec<&str> = line.split(',').collect();
+            if columns.len() != 3 {
+                return Err(Error::InvalidInput {
+                    input: format!("layout line {}", line),
+                });
+            }
+            entries.push((
+                columns[0].trim().to_string(),
+                columns[1].parse()?,
+                columns[2].parse()?,
+            ));
+        }
+        DbLayout::new(db_number, entries)
+    }
+
+    pub fn db_number(&self) -> i32 {
+        self.db_number
+    }
+
+    pub fn entries(&self) -> &[(String, Address, Kind)] {
+        &self.entries
+    }
+}
+
+#[test]
+fn test_layout() {
+    let layout = DbLayout::from_csv(
+        888,
+        "speed, DB888.DBD8, REAL\n\n counter,DBW4,int\nlights,DBX12.4,BOOL\nname,DBB14,STRING[8]\n",
+    )
+    .unwrap();
+    assert_eq!(layout.db_number(), 888);
+    let names: Vec<&str> = layout.entries().iter().map(|e| e.0.as_str()).collect();
+    assert_eq!(names, vec!["speed", "counter", "lights", "name"]);
+    assert_eq!(layout.entries()[1].2, Kind::Int);
+    assert_eq!(layout.entries()[3].2, Kind::String(8));
+
+    // another block, wrong sizes and malformed lines
+    assert!(DbLayout::from_csv(888, "speed,DB1.DBD8,REAL").is_err());
+    assert!(DbLayout::from_csv(888, "speed,DBW8,REAL").is_err());
+    assert!(DbLayout::from_csv(888, "speed,DBX8.0,INT").is_err());
+    assert!(DbLayout::from_csv(888, "speed,DBB8,BOOL").is_err());
+    assert!(DbLayout::from_csv(888, "speed,MD8,REAL").is_err());
+    assert!(DbLayout::from_csv(888, "speed,DBD8").is_err());
+    // a STRING starts at a byte address
+    assert!(DbLayout::from_csv(888, "speed,DBD8,STRING").is_err());
+    assert!(DbLayout::from_csv(888, "speed,DBX8.0,STRING").is_err());
+
+    match DbLayout::from_csv(888, "speed,DBD8,REAL\nspeed ,DBD12,REAL") {
+        Err(Error::InvalidInput { input }) => assert!(input.contains("speed"), "{}", input),
+        other => panic!("expected InvalidInput got {:?}", other),
+    }
+}
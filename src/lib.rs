@@ -2,9 +2,11 @@
 // This software may be modified and distributed under the terms
 // of the BSD license. See the LICENSE file for details.
 
+pub mod address;
 pub mod client;
-mod constant;
+pub mod constant;
 pub mod error;
 pub mod field;
+pub mod layout;
 pub mod tcp;
 pub mod transport;
@@ -6,20 +6,32 @@
 
 extern crate byteorder;
 
-use super::error::{self, Error};
+use super::error::Error;
 use super::transport::{self, Transport as PackTrait};
-use crate::transport::Connection;
+use crate::transport::{Connection, COTP_DATA, COTP_EOT};
 use byteorder::{BigEndian, ByteOrder};
-use std::io::{Read, Write};
+#[cfg(feature = "local-bind")]
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io::{self, ErrorKind, Read, Write};
 use std::net::IpAddr;
-use std::net::TcpStream;
-use std::sync::Mutex;
-use std::time::Duration;
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "async")]
+pub use self::asynchronous::AsyncTransport;
 
 /// Default TCP timeout
 pub const TIMEOUT: Duration = Duration::from_secs(10);
 /// Default TCP idle timeout
 pub const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Default time the keepalive waits for the PLC status
+pub const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(1);
 pub const MAX_LENGTH: usize = 2084;
 //messages
 const PDU_SIZE_REQUESTED: i32 = 480;
@@ -27,18 +39,97 @@ const ISO_TCP: i32 = 102; //default isotcp port
 const ISO_HEADER_SIZE: i32 = 7; // TPKT+COTP Header Size
 const MIN_PDU_SIZE: i32 = 16;
 
+/// source reference for the next connection request that doesn't set one
+static NEXT_SRC_REFERENCE: AtomicU16 = AtomicU16::new(1);
+
+/// TCP connection to the PLC.
+/// It owns the socket and is deliberately not `Clone`, see `client::Client` for sharing it.
 pub struct Transport {
     options: Options,
-    stream: Mutex<TcpStream>,
+    /// the address the stream is connected to
+    peer: SocketAddr,
+    stream: Arc<Mutex<TcpStream>>,
+    /// when the last telegram was exchanged, always locked after `stream`
+    last_used: Arc<Mutex<Instant>>,
+    /// stops the keepalive thread when dropped
+    keepalive: Option<Sender<()>>,
+    /// PDU reference of the last job, shared with the client
+    pdu_ref: Arc<AtomicU16>,
+    /// local and remote COTP references once connected, used to disconnect
+    references: Option<(u16, u16)>,
+}
+
+/// Conventional rack and slot of the CPU for common PLC families.
+/// They match the default hardware configuration, check it when the connection is refused.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SlotConfig {
+    /// rack 0, slot 2
+    S7_300,
+    /// rack 0, slot 3
+    S7_400,
+    /// rack 0, slot 1
+    S7_1200,
+    /// rack 0, slot 1
+    S7_1500,
+}
+
+impl SlotConfig {
+    pub fn rack(&self) -> u16 {
+        0
+    }
+
+    pub fn slot(&self) -> u16 {
+        match self {
+            SlotConfig::S7_300 => 2,
+            SlotConfig::S7_400 => 3,
+            SlotConfig::S7_1200 | SlotConfig::S7_1500 => 1,
+        }
+    }
 }
 
 /// a set of options for the TCP connection
 #[derive(Debug, Clone)]
 pub struct Options {
+    /// zero disables the timeout
     pub read_timeout: Duration,
+    /// zero disables the timeout
     pub write_timeout: Duration,
+    /// read and write timeout while connecting and negotiating the PDU length,
+    /// so a PLC that accepts the connection but never answers can't block forever.
+    /// Zero disables the timeout.
+    pub negotiate_timeout: Duration,
+    /// timeout of the TCP connect, zero leaves it to the OS
+    pub connect_timeout: Duration,
     address: String,
     pub conn_type: transport::Connection,
+    /// source reference sent in the ISO connection request.
+    /// When `None` every connection gets its own, incremented, reference
+    /// which keeps gateways happy when several clients connect from the same host.
+    pub src_reference: Option<u16>,
+    /// when set, the PLC status is read in the background once the connection
+    /// has been idle that long, so PLCs and firewalls don't drop it
+    pub keepalive: Option<Duration>,
+    /// how long the keepalive waits for the PLC status, whatever the read timeout.
+    /// The client's jobs wait for the keepalive in progress, keep it short.
+    /// Zero uses `KEEPALIVE_TIMEOUT`.
+    pub keepalive_timeout: Duration,
+    /// maximum number of outstanding jobs requested when negotiating,
+    /// the PLC answers with what it supports. Some devices reject the default of 1
+    pub max_amq_calling: u16,
+    pub max_amq_called: u16,
+    /// sent as is instead of the negotiation telegram, for devices that need
+    /// more than the AMQ counts changed. The answer must still be a regular one.
+    pub negotiation_telegram: Option<Vec<u8>>,
+    /// every received frame must start with TPKT version 3 and a zero reserved byte,
+    /// so another protocol on the port is reported as such. On by default,
+    /// gateways that fill the reserved byte need it off.
+    pub strict_tpkt: bool,
+    /// local address the connection originates from, to pick the interface
+    /// of a multi-homed host instead of the one of the default route.
+    /// Port 0 lets the OS choose the port. Needs the `local-bind` feature.
+    #[cfg(feature = "local-bind")]
+    pub local_addr: Option<SocketAddr>,
     rack: u16,
     slot: u16,
     //Transport Service Access Point
@@ -49,17 +140,28 @@ pub struct Options {
     remote_tsap_high: u8,
     remote_tsap_low: u8,
     last_pdu_type: u8,
-    //PDULength variable to store pdu length after connect
-    pdu_length: i32,
+    //negotiated parameters, among which the pdu length, stored after connect
+    negotiation: transport::NegotiationInfo,
 }
 
 impl Options {
     pub fn new(address: IpAddr, rack: u16, slot: u16, conn_type: Connection) -> Options {
         Options {
-            read_timeout: Duration::new(0, 0),
-            write_timeout: Duration::new(0, 0),
-            address: format!("{}:{}", address.to_string(), ISO_TCP.to_string()), //ip:102,
+            read_timeout: TIMEOUT,
+            write_timeout: TIMEOUT,
+            negotiate_timeout: TIMEOUT,
+            connect_timeout: TIMEOUT,
+            address: format!("{}:{}", address, ISO_TCP), //ip:102,
             conn_type,
+            src_reference: None,
+            keepalive: None,
+            keepalive_timeout: KEEPALIVE_TIMEOUT,
+            max_amq_calling: 1,
+            max_amq_called: 1,
+            negotiation_telegram: None,
+            strict_tpkt: true,
+            #[cfg(feature = "local-bind")]
+            local_addr: None,
             rack,
             slot,
             local_tsap: 0,
@@ -69,90 +171,466 @@ impl Options {
             remote_tsap_high: 0,
             remote_tsap_low: 0,
             last_pdu_type: 0,
-            pdu_length: 0,
+            negotiation: transport::NegotiationInfo::default(),
+        }
+    }
+
+    /// options with the rack and slot of a common CPU
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{tcp, transport};
+    ///
+    /// let addr = IpAddr::from(Ipv4Addr::new(127, 0, 0, 1));
+    /// let opts = tcp::Options::with_slot_config(addr, tcp::SlotConfig::S7_300, transport::Connection::PG);
+    /// ```
+    pub fn with_slot_config(
+        address: IpAddr,
+        slot_config: SlotConfig,
+        conn_type: Connection,
+    ) -> Options {
+        Options::new(address, slot_config.rack(), slot_config.slot(), conn_type)
+    }
+
+    /// the connection type goes in the high byte of the remote TSAP,
+    /// rack and slot in the low byte, the local TSAP is always 0x0100
+    fn set_tsap(&mut self) {
+        let remote_tsap = ((self.conn_type as u16) << 8) + (self.rack * 0x20) + self.slot;
+        let local_tsap: u16 = 0x0100;
+
+        self.local_tsap = local_tsap;
+        self.local_tsap_high = (local_tsap >> 8) as u8;
+        self.local_tsap_low = (local_tsap & 0x00FF) as u8;
+
+        self.remote_tsap = remote_tsap;
+        self.remote_tsap_high = (remote_tsap >> 8) as u8;
+        self.remote_tsap_low = remote_tsap as u8;
+    }
+
+    /// the frames the PLC may send, up to a negotiated PDU and its headers.
+    /// Until the PDU is negotiated any frame up to `MAX_LENGTH` is accepted.
+    fn framing(&self) -> Framing {
+        let max_length = match self.negotiation.pdu_length {
+            0 => MAX_LENGTH,
+            pdu_length => pdu_length as usize + ISO_HEADER_SIZE as usize,
+        };
+        Framing {
+            max_length,
+            strict: self.strict_tpkt,
+        }
+    }
+
+    fn negotiation_request(&self) -> Vec<u8> {
+        if let Some(telegram) = &self.negotiation_telegram {
+            return telegram.clone();
+        }
+
+        let mut msg = transport::PDU_NEGOTIATION_TELEGRAM.to_vec();
+        BigEndian::write_u16(msg[19..].as_mut(), self.max_amq_calling);
+        BigEndian::write_u16(msg[21..].as_mut(), self.max_amq_called);
+        BigEndian::write_u16(msg[23..].as_mut(), PDU_SIZE_REQUESTED as u16);
+        msg
+    }
+
+    fn connection_request(&self) -> Vec<u8> {
+        let mut msg = transport::ISO_CONNECTION_REQUEST_TELEGRAM.to_vec();
+
+        let src_reference = self.src_reference.unwrap_or_else(next_src_reference);
+        BigEndian::write_u16(msg[8..].as_mut(), src_reference);
+
+        msg[16] = self.local_tsap_high;
+        msg[17] = self.local_tsap_low;
+        msg[20] = self.remote_tsap_high;
+        msg[21] = self.remote_tsap_low;
+        msg
+    }
+}
+
+/// whether the PLC closed the connection
+fn closed(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::UnexpectedEof | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted
+    )
+}
+
+/// a zero duration means no timeout, the socket would reject it otherwise
+fn timeout(duration: Duration) -> Option<Duration> {
+    if duration == Duration::new(0, 0) {
+        None
+    } else {
+        Some(duration)
+    }
+}
+
+/// zero is not a valid reference, skip it when wrapping around
+fn next_src_reference() -> u16 {
+    loop {
+        let reference = NEXT_SRC_REFERENCE.fetch_add(1, Ordering::Relaxed);
+        if reference != 0 {
+            return reference;
         }
     }
 }
 
 impl Transport {
     pub fn connect(options: Options) -> Result<Transport, Error> {
-        let tcp_client = TcpStream::connect(&options.address)?;
+        #[cfg(feature = "local-bind")]
+        let tcp_client = match options.local_addr {
+            Some(local) => connect_from(local, &options.address, options.connect_timeout)?,
+            None => connect_to(&options.address, options.connect_timeout)?,
+        };
+        #[cfg(not(feature = "local-bind"))]
+        let tcp_client = connect_to(&options.address, options.connect_timeout)?;
+        Transport::from_stream(tcp_client, options)
+    }
 
-        tcp_client.set_read_timeout(Some(options.read_timeout))?;
-        tcp_client.set_write_timeout(Some(options.write_timeout))?;
+    /// uses a stream connected elsewhere, through a proxy or a tunnel for instance.
+    /// The address of the options is ignored, everything else behaves as with `connect`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr, TcpStream};
+    /// use s7::{client, tcp, transport};
+    ///
+    /// // the local end of an ssh tunnel to the PLC
+    /// let stream = TcpStream::connect("127.0.0.1:10102").unwrap();
+    ///
+    /// let addr = Ipv4Addr::new(192, 168, 0, 1);
+    /// let opts = tcp::Options::new(IpAddr::from(addr), 0, 2, transport::Connection::PG);
+    /// let t = tcp::Transport::from_stream(stream, opts).unwrap();
+    /// let mut cl = client::Client::new(t).unwrap();
+    /// ```
+    pub fn from_stream(tcp_client: TcpStream, options: Options) -> Result<Transport, Error> {
+        tcp_client.set_read_timeout(timeout(options.read_timeout))?;
+        tcp_client.set_write_timeout(timeout(options.write_timeout))?;
         Ok(Transport {
             options,
-            stream: Mutex::new(tcp_client),
+            peer: tcp_client.peer_addr()?,
+            stream: Arc::new(Mutex::new(tcp_client)),
+            last_used: Arc::new(Mutex::new(Instant::now())),
+            keepalive: None,
+            pdu_ref: Arc::new(AtomicU16::new(0)),
+            references: None,
         })
     }
 
-    fn set_tsap(&mut self) {
-        let mut remote_tsap = ((self.connection_type() as u16) << 8) as u16
-            + (self.options.rack * 0x20)
-            + self.options.slot;
-        let local_tsap: u16 = 0x0100 & 0x0000FFFF;
-        remote_tsap = remote_tsap & 0x0000FFFF;
+    /// the address of the PLC, or of the proxy for streams connected elsewhere
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
 
-        self.options.local_tsap = local_tsap;
-        self.options.local_tsap_high = (local_tsap >> 8) as u8;
-        self.options.local_tsap_low = (local_tsap & 0x00FF) as u8;
+    fn start_keepalive(&mut self, interval: Duration) {
+        let (stop, stopped) = mpsc::channel();
+        let stream = Arc::clone(&self.stream);
+        let last_used = Arc::clone(&self.last_used);
+        let pdu_ref = Arc::clone(&self.pdu_ref);
+        let framing = self.options.framing();
+        let wait = timeout(self.options.keepalive_timeout).unwrap_or(KEEPALIVE_TIMEOUT);
 
-        self.options.remote_tsap = remote_tsap;
-        self.options.remote_tsap_high = (remote_tsap >> 8) as u8;
-        self.options.remote_tsap_low = (remote_tsap as u8) & 0x00FF;
+        thread::spawn(move || {
+            keepalive(stream, last_used, pdu_ref, interval, wait, framing, stopped)
+        });
+        self.keepalive = Some(stop);
     }
 
-    fn iso_connect(&mut self) -> Result<(), Error> {
-        let mut msg = transport::ISO_CONNECTION_REQUEST_TELEGRAM.to_vec();
+    fn touch(&self) {
+        if let Ok(mut last_used) = self.last_used.lock() {
+            *last_used = Instant::now();
+        }
+    }
 
-        msg[16] = self.options.local_tsap_high;
-        msg[17] = self.options.local_tsap_low;
-        msg[20] = self.options.remote_tsap_high;
-        msg[21] = self.options.remote_tsap_low;
+    fn set_timeouts(&self, read: Duration, write: Duration) -> Result<(), Error> {
+        let stream = match self.stream.lock() {
+            Ok(s) => s,
+            Err(_) => return Err(Error::Lock),
+        };
+        stream.set_read_timeout(timeout(read))?;
+        stream.set_write_timeout(timeout(write))?;
+        Ok(())
+    }
 
-        let r = self.send(msg.as_slice());
+    fn iso_connect(&mut self) -> Result<(), Error> {
+        let msg = self.options.connection_request();
 
-        let n = match r {
-            Ok(n) => n.len(),
-            Err(e) => return Err(Error::Connect(e.to_string())),
-        };
+        let response = self.send(msg.as_slice()).map_err(connection_refused)?;
+        self.references = Some(connection_confirm(&msg, &response)?);
+        Ok(())
+    }
+
+    fn negotiate_pdu_length(&mut self) -> Result<(), Error> {
+        let request = self.options.negotiation_request();
 
         // Sends the connection request telegram
-        if n != msg.len() {
-            return Err(Error::PduLength(n as i32));
+        let response = self.send(request.as_slice());
+        self.options.negotiation = negotiated(response)?;
+        Ok(())
+    }
+}
+
+/// the error of a refused connection request.
+/// A refusing PLC either answers with a short disconnect request
+/// or closes the connection
+fn connection_refused(e: Error) -> Error {
+    match e {
+        Error::PduLength(length) => {
+            Error::ConnectionRefused(format!("answered with {} bytes", length))
         }
+        Error::Io(ref e) if closed(e) => Error::ConnectionRefused(e.to_string()),
+        e @ Error::InvalidResponse { .. } => e,
+        e => Error::Connect(e.to_string()),
+    }
+}
+
+/// the local and remote COTP references of the answer to the connection request `request`
+fn connection_confirm(request: &[u8], response: &[u8]) -> Result<(u16, u16), Error> {
+    if response[5] != transport::CONFIRM_CONNECTION {
+        return Err(Error::ConnectionRefused(format!(
+            "COTP answer {:#04x} instead of a connection confirm",
+            response[5]
+        )));
+    }
+
+    // the confirm may carry more parameters than were requested
+    if response.len() < request.len() {
+        return Err(Error::PduLength(response.len() as i32));
+    }
+
+    Ok((
+        BigEndian::read_u16(&request[8..]),
+        BigEndian::read_u16(&response[8..]),
+    ))
+}
 
-        if self.options.last_pdu_type != transport::CONFIRM_CONNECTION {
-            return Err(Error::Iso);
+/// the parameters of the answer to the negotiation request
+fn negotiated(response: Result<Vec<u8>, Error>) -> Result<transport::NegotiationInfo, Error> {
+    let response = match response {
+        Err(Error::Io(ref e)) if closed(e) => return Err(Error::ConnectionRefused(e.to_string())),
+        result => result?,
+    };
+    if response.len() > 18 && (response[17] != 0 || response[18] != 0) {
+        return Err(Error::ConnectionRefused(format!(
+            "S7 error class {:#04x} code {:#04x}",
+            response[17], response[18]
+        )));
+    }
+    // Get PDU Size Negotiated
+    transport::NegotiationInfo::parse(response.as_ref())
+}
+
+/// the disconnect request for the COTP references of the connection
+fn disconnect_request((local, remote): (u16, u16)) -> [u8; 11] {
+    let mut request = transport::DISCONNECT_REQUEST_TELEGRAM;
+    BigEndian::write_u16(&mut request[6..], remote);
+    BigEndian::write_u16(&mut request[8..], local);
+    request
+}
+
+/// sends a COTP disconnect request so the PLC frees the connection right away
+/// instead of waiting for the TCP teardown. Best effort, errors are ignored.
+impl Drop for Transport {
+    fn drop(&mut self) {
+        let request = match self.references {
+            Some(references) => disconnect_request(references),
+            None => return,
+        };
+
+        if let Ok(mut stream) = self.stream.lock() {
+            let _ = stream.write_all(&request);
         }
-        Ok(())
     }
+}
 
-    fn negotiate_pdu_length(&mut self) -> Result<(), Error> {
-        // Set PDU Size Requested //lth
-        let mut pdu_size_package = transport::PDU_NEGOTIATION_TELEGRAM.to_vec();
-        BigEndian::write_u16(pdu_size_package[23..].as_mut(), PDU_SIZE_REQUESTED as u16);
+/// reads the PLC status whenever the connection has been idle for `interval`,
+/// waiting at most `wait` for the answer since the client's jobs wait for it.
+/// The request takes the next PDU reference of the client.
+/// Stops when the transport is dropped or the connection fails. A failed exchange
+/// shuts the socket down, so a late answer can't be taken for the one of the next job,
+/// which reports the failure instead.
+fn keepalive(
+    stream: Arc<Mutex<TcpStream>>,
+    last_used: Arc<Mutex<Instant>>,
+    pdu_ref: Arc<AtomicU16>,
+    interval: Duration,
+    wait: Duration,
+    framing: Framing,
+    stop: Receiver<()>,
+) {
+    loop {
+        let idle = match last_used.lock() {
+            Ok(l) => l.elapsed(),
+            Err(_) => return,
+        };
 
-        // Sends the connection request telegram
-        let response = self.send(pdu_size_package.as_slice())?;
-        if response.len() == 27 && response[17] == 0 && response[18] == 0 {
-            // 20 = size of Negotiate Answer
-            // Get PDU Size Negotiated
-            self.options.pdu_length = BigEndian::read_u16(&response[25..]) as i32;
-            if self.options.pdu_length <= 0 {
-                return Err(Error::Response {
-                    code: error::CLI_NEGOTIATING_PDU,
-                });
+        match stop.recv_timeout(interval.saturating_sub(idle)) {
+            Err(RecvTimeoutError::Timeout) => {}
+            _ => return,
+        }
+
+        let mut stream = match stream.lock() {
+            Ok(s) => s,
+            Err(_) => return,
+        };
+        let mut last_used = match last_used.lock() {
+            Ok(l) => l,
+            Err(_) => return,
+        };
+        if last_used.elapsed() < interval {
+            continue;
+        }
+
+        let mut request = transport::PLC_STATUS_TELEGRAM;
+        let reference = pdu_ref.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+        BigEndian::write_u16(&mut request[11..], reference);
+
+        let timeouts = (stream.read_timeout(), stream.write_timeout());
+        let exchanged = stream.set_read_timeout(Some(wait)).is_ok()
+            && stream.set_write_timeout(Some(wait)).is_ok()
+            && stream.write_all(&request).is_ok()
+            && receive(&mut *stream, framing).is_ok();
+
+        let restored = match timeouts {
+            (Ok(read), Ok(write)) => {
+                stream.set_read_timeout(read).is_ok() && stream.set_write_timeout(write).is_ok()
             }
-        } else {
-            return Err(Error::Response {
-                code: error::CLI_NEGOTIATING_PDU,
-            });
+            _ => false,
+        };
+        if !exchanged || !restored {
+            let _ = stream.shutdown(Shutdown::Both);
+            return;
         }
-        Ok(())
+        *last_used = Instant::now();
+    }
+}
+
+/// connects to the first address of `address` that accepts within `duration`
+fn connect_to(address: &str, duration: Duration) -> io::Result<TcpStream> {
+    let duration = match timeout(duration) {
+        Some(duration) => duration,
+        None => return TcpStream::connect(address),
+    };
+
+    let mut last_error = None;
+    for remote in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&remote, duration) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} resolves to no address", address),
+        )
+    }))
+}
+
+/// connects to `address` from the socket bound to `local`
+#[cfg(feature = "local-bind")]
+fn connect_from(local: SocketAddr, address: &str, duration: Duration) -> io::Result<TcpStream> {
+    let remote = address
+        .to_socket_addrs()?
+        .find(|remote| remote.is_ipv4() == local.is_ipv4())
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("no address of {} reachable from {}", address, local),
+            )
+        })?;
+
+    let socket = Socket::new(
+        Domain::for_address(remote),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    socket.bind(&local.into())?;
+    match timeout(duration) {
+        Some(duration) => socket.connect_timeout(&remote.into(), duration)?,
+        None => socket.connect(&remote.into())?,
+    }
+    Ok(socket.into())
+}
+
+/// what `receive` accepts of the frames
+#[derive(Debug, Copy, Clone)]
+struct Framing {
+    /// longest frame, headers included
+    max_length: usize,
+    /// the TPKT version and reserved byte are checked, see `Options::strict_tpkt`
+    strict: bool,
+}
+
+/// any frame a PLC may send before negotiating
+#[cfg(test)]
+const ANY_FRAME: Framing = Framing {
+    max_length: MAX_LENGTH,
+    strict: true,
+};
+
+/// the length of the frame starting with the TPKT header `tpkt`
+fn tpkt_length(tpkt: &[u8], framing: Framing) -> Result<i32, Error> {
+    match transport::parse_tpkt_len(tpkt) {
+        Some(length) => Ok(length as i32),
+        None if !framing.strict => Ok(BigEndian::read_u16(&tpkt[2..]) as i32),
+        // another protocol on the port, an HTTP server for instance
+        None => Err(Error::InvalidResponse {
+            reason: "not a TPKT frame, the peer isn't an S7 endpoint".to_string(),
+            bytes: tpkt.to_vec(),
+        }),
     }
 }
 
+/// reads a whole telegram from the stream.
+/// Some CPUs split a single response over several COTP data frames,
+/// the payloads are joined until the frame marked as the last one (EOT) arrives.
+/// The returned telegram has the headers of the first frame and the total length.
+fn receive<R: Read>(stream: &mut R, framing: Framing) -> Result<Vec<u8>, Error> {
+    let mut telegram: Vec<u8> = Vec::with_capacity(MAX_LENGTH);
+    let mut header = [0u8; ISO_HEADER_SIZE as usize];
+
+    loop {
+        // Get TPKT (4 bytes)
+        stream.read_exact(&mut header[..4])?;
+        let length = tpkt_length(&header[..4], framing)?;
+
+        // Skip remaining 3 COTP bytes
+        stream.read_exact(&mut header[4..7])?;
+
+        if length == ISO_HEADER_SIZE {
+            continue;
+        }
+
+        let first = telegram.is_empty();
+        let min = if first { MIN_PDU_SIZE } else { ISO_HEADER_SIZE };
+        if !(min..=framing.max_length as i32).contains(&length) {
+            return Err(Error::PduLength(length));
+        }
+
+        if first {
+            telegram.extend_from_slice(&header);
+        }
+
+        // Receives the S7 Payload
+        let payload_start = telegram.len();
+        telegram.resize(payload_start + (length - ISO_HEADER_SIZE) as usize, 0);
+        stream.read_exact(&mut telegram[payload_start..])?;
+
+        if header[5] != COTP_DATA || header[6] & COTP_EOT != 0 {
+            break;
+        }
+    }
+
+    if telegram.len() > u16::MAX as usize {
+        return Err(Error::PduLength(telegram.len() as i32));
+    }
+    let total = telegram.len() as u16;
+    BigEndian::write_u16(&mut telegram[2..], total);
+    Ok(telegram)
+}
+
 impl PackTrait for Transport {
     fn send(&mut self, request: &[u8]) -> Result<Vec<u8>, Error> {
         // Send sends data to server and ensures response length is greater than header length.
@@ -160,49 +638,679 @@ impl PackTrait for Transport {
             Ok(s) => s,
             Err(_) => return Err(Error::Lock),
         };
-        stream.write(request)?;
-
-        let mut data = vec![0u8; MAX_LENGTH];
-        let mut length;
-
-        loop {
-            // Get TPKT (4 bytes)
-            stream.read(&mut data[..4])?;
-
-            // Read length, ignore transaction & protocol id (4 bytes)
-            length = BigEndian::read_u16(&data[2..]);
-            let length_n = length as i32;
-
-            if length_n == ISO_HEADER_SIZE {
-                stream.read(&mut data[4..7])?;
-            } else {
-                if length_n > PDU_SIZE_REQUESTED + ISO_HEADER_SIZE || length_n < MIN_PDU_SIZE {
-                    return Err(Error::PduLength(length_n));
-                }
-                break;
+        stream.write_all(request)?;
+
+        let response = receive(&mut *stream, self.options.framing())?;
+        self.touch();
+        self.options.last_pdu_type = response[5]; // Stores PDU Type, we need it for later
+        Ok(response)
+    }
+
+    fn send_only(&mut self, request: &[u8]) -> Result<(), Error> {
+        let mut stream = match self.stream.lock() {
+            Ok(s) => s,
+            Err(_) => return Err(Error::Lock),
+        };
+        stream.write_all(request)?;
+        self.touch();
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<Vec<u8>, Error> {
+        let mut stream = match self.stream.lock() {
+            Ok(s) => s,
+            Err(_) => return Err(Error::Lock),
+        };
+        let response = receive(&mut *stream, self.options.framing())?;
+        self.touch();
+        self.options.last_pdu_type = response[5];
+        Ok(response)
+    }
+
+    fn send_pipelined(
+        &mut self,
+        requests: &[Vec<u8>],
+        max_outstanding: usize,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let mut stream = match self.stream.lock() {
+            Ok(s) => s,
+            Err(_) => return Err(Error::Lock),
+        };
+        let framing = self.options.framing();
+        let mut responses = Vec::with_capacity(requests.len());
+
+        for window in requests.chunks(max_outstanding.max(1)) {
+            for request in window {
+                stream.write_all(request)?;
+            }
+            for _ in window {
+                let response = receive(&mut *stream, framing)?;
+                self.options.last_pdu_type = response[5];
+                responses.push(response);
             }
         }
+        self.touch();
+        Ok(responses)
+    }
 
-        // Skip remaining 3 COTP bytes
-        stream.read(&mut data[4..7])?;
-        self.options.last_pdu_type = data[5]; // Stores PDU Type, we need it for later
+    fn pdu_length(&self) -> i32 {
+        self.options.negotiation.pdu_length as i32
+    }
 
-        // Receives the S7 Payload
-        stream.read(&mut data[7..length as usize])?;
-        Ok(data[0..length as usize].to_vec())
+    fn negotiation_info(&self) -> transport::NegotiationInfo {
+        self.options.negotiation
     }
 
-    fn pdu_length(&self) -> i32 {
-        self.options.pdu_length
+    fn negotiate(&mut self) -> Result<transport::NegotiationInfo, Error> {
+        self.options.set_tsap();
+
+        let negotiate_timeout = self.options.negotiate_timeout;
+        self.set_timeouts(negotiate_timeout, negotiate_timeout)?;
+
+        let result = self.iso_connect().and_then(|_| self.negotiate_pdu_length());
+
+        self.set_timeouts(self.options.read_timeout, self.options.write_timeout)?;
+        result?;
+
+        if let (Some(interval), None) = (self.options.keepalive, &self.keepalive) {
+            self.start_keepalive(interval);
+        }
+        Ok(self.options.negotiation)
     }
 
-    fn negotiate(&mut self) -> Result<(), Error> {
-        self.set_tsap();
-        self.iso_connect()?;
-        self.negotiate_pdu_length()
+    /// only the setup communication, the ISO connection stays open
+    fn renegotiate_pdu(&mut self) -> Result<transport::NegotiationInfo, Error> {
+        self.negotiate_pdu_length()?;
+        Ok(self.options.negotiation)
     }
 
     fn connection_type(&self) -> Connection {
         self.options.conn_type
     }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        Some(self.peer)
+    }
+
+    fn pdu_ref_counter(&self) -> Option<Arc<AtomicU16>> {
+        Some(Arc::clone(&self.pdu_ref))
+    }
+}
+
+#[test]
+fn test_receive_single_frame() {
+    let mut frame = vec![3, 0, 0, 22, 2, COTP_DATA, COTP_EOT];
+    frame.extend_from_slice(&[7u8; 15]);
+
+    let telegram = receive(&mut frame.as_slice(), ANY_FRAME).unwrap();
+    assert_eq!(telegram, frame);
+}
+
+#[test]
+fn test_receive_fragmented_response() {
+    let mut stream = vec![3, 0, 0, 20, 2, COTP_DATA, 0];
+    stream.extend_from_slice(&[1u8; 13]);
+    // empty keep alive frame in between
+    stream.extend_from_slice(&[3, 0, 0, 7, 2, COTP_DATA, 0]);
+    stream.extend_from_slice(&[3, 0, 0, 10, 2, COTP_DATA, COTP_EOT]);
+    stream.extend_from_slice(&[2u8; 3]);
+
+    let telegram = receive(&mut stream.as_slice(), ANY_FRAME).unwrap();
+
+    let mut expected = vec![3, 0, 0, 23, 2, COTP_DATA, 0];
+    expected.extend_from_slice(&[1u8; 13]);
+    expected.extend_from_slice(&[2u8; 3]);
+    assert_eq!(telegram, expected);
+}
+
+#[test]
+fn test_receive_truncated_fragment() {
+    let mut stream = vec![3, 0, 0, 20, 2, COTP_DATA, 0];
+    stream.extend_from_slice(&[1u8; 13]);
+
+    assert!(receive(&mut stream.as_slice(), ANY_FRAME).is_err());
+}
+
+#[test]
+fn test_connection_request_src_reference() {
+    use std::net::Ipv4Addr;
+
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+
+    let first = BigEndian::read_u16(&opts.connection_request()[8..]);
+    let second = BigEndian::read_u16(&opts.connection_request()[8..]);
+    assert_ne!(first, 0);
+    assert_ne!(first, second);
+
+    opts.src_reference = Some(0x1234);
+    let msg = opts.connection_request();
+    assert_eq!(msg.len(), transport::ISO_CONNECTION_REQUEST_TELEGRAM.len());
+    assert_eq!(msg[8], 0x12);
+    assert_eq!(msg[9], 0x34);
+}
+
+#[test]
+fn test_connection_request_tsap() {
+    use std::net::Ipv4Addr;
+
+    let cases = [
+        (Connection::PG, 0x01),
+        (Connection::OP, 0x02),
+        (Connection::Basic, 0x03),
+    ];
+    for (conn_type, high) in cases.iter() {
+        let mut opts = Options::new(IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)), 1, 3, *conn_type);
+        opts.set_tsap();
+
+        let msg = opts.connection_request();
+        // local TSAP
+        assert_eq!(msg[16..18], [0x01, 0x00], "{:?}", conn_type);
+        // remote TSAP, rack 1 slot 3
+        assert_eq!(msg[20..22], [*high, 0x23], "{:?}", conn_type);
+    }
+}
+
+#[test]
+fn test_negotiation_request() {
+    use std::net::Ipv4Addr;
+
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+
+    let msg = opts.negotiation_request();
+    assert_eq!(msg.len(), transport::PDU_NEGOTIATION_TELEGRAM.len());
+    assert_eq!(&msg[19..], &[0, 1, 0, 1, 0x01, 0xE0]);
+
+    opts.max_amq_calling = 8;
+    opts.max_amq_called = 8;
+    assert_eq!(&opts.negotiation_request()[19..], &[0, 8, 0, 8, 0x01, 0xE0]);
+
+    let mut custom = transport::PDU_NEGOTIATION_TELEGRAM.to_vec();
+    custom[13] = 0x42;
+    opts.negotiation_telegram = Some(custom.clone());
+    assert_eq!(opts.negotiation_request(), custom);
+}
+
+#[test]
+fn test_negotiate_timeout() {
+    use crate::client::Client;
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::time::Instant;
+
+    // accepts the connection but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = listener.local_addr().unwrap().to_string();
+    opts.read_timeout = Duration::new(0, 0);
+    opts.negotiate_timeout = Duration::from_millis(200);
+
+    let started = Instant::now();
+    let t = Transport::connect(opts).unwrap();
+    let _accepted = listener.accept().unwrap();
+
+    assert!(Client::new(t).is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_connection_refused() {
+    use crate::client::Client;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = listener.local_addr().unwrap().to_string();
+
+    let plc = thread::spawn(move || {
+        // a disconnect request instead of the confirm
+        let (mut conn, _) = listener.accept().unwrap();
+        receive(&mut conn, ANY_FRAME).unwrap();
+        conn.write_all(&[3, 0, 0, 11, 6, 0x80, 0, 1, 0, 0, 0])
+            .unwrap();
+
+        // the connection is closed right away
+        let (mut conn, _) = listener.accept().unwrap();
+        receive(&mut conn, ANY_FRAME).unwrap();
+        drop(conn);
+
+        // the negotiation fails with an error class
+        let (mut conn, _) = listener.accept().unwrap();
+        let request = receive(&mut conn, ANY_FRAME).unwrap();
+        let mut confirm = request.clone();
+        confirm[5] = transport::CONFIRM_CONNECTION;
+        conn.write_all(&confirm).unwrap();
+        receive(&mut conn, ANY_FRAME).unwrap();
+        let mut refused = vec![3, 0, 0, 27, 2, COTP_DATA, COTP_EOT];
+        refused.resize(27, 0);
+        refused[17] = 0x81;
+        refused[18] = 0x04;
+        conn.write_all(&refused).unwrap();
+    });
+
+    for _ in 0..3 {
+        match Client::new(Transport::connect(opts.clone()).unwrap()) {
+            Err(Error::ConnectionRefused(_)) => {}
+            other => panic!("expected ConnectionRefused got {:?}", other.err()),
+        }
+    }
+    plc.join().unwrap();
+}
+
+/// plays the PLC side of the connection and pdu negotiation
+#[cfg(test)]
+fn accept_negotiation(conn: &mut TcpStream, pdu_length: u16) {
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    let request = receive(conn, ANY_FRAME).unwrap();
+    let mut confirm = request.clone();
+    confirm[5] = transport::CONFIRM_CONNECTION;
+    // the PLC reference
+    BigEndian::write_u16(&mut confirm[8..], 0x4321);
+    conn.write_all(&confirm).unwrap();
+
+    receive(conn, ANY_FRAME).unwrap();
+    let mut negotiated = vec![3, 0, 0, 27, 2, COTP_DATA, COTP_EOT];
+    negotiated.resize(27, 0);
+    BigEndian::write_u16(&mut negotiated[25..], pdu_length);
+    conn.write_all(&negotiated).unwrap();
+}
+
+#[test]
+fn test_from_stream() {
+    use crate::client::Client;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+    let plc = thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        accept_negotiation(&mut conn, 480);
+    });
+
+    // the address is never used
+    let opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(192, 0, 2, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    let cl = Client::new(Transport::from_stream(stream, opts).unwrap()).unwrap();
+    assert_eq!(cl.negotiation_info().pdu_length, 480);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_negotiated_pdu_above_480() {
+    use crate::client::Client;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = listener.local_addr().unwrap().to_string();
+
+    let plc = thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        accept_negotiation(&mut conn, 960);
+
+        // the whole read fits a single answer longer than 480 bytes
+        let request = receive(&mut conn, ANY_FRAME).unwrap();
+        assert_eq!(BigEndian::read_u16(&request[23..]), 900);
+        let mut response = vec![3, 0, 0, 0, 2, COTP_DATA, COTP_EOT];
+        response.resize(25, 0);
+        response[21] = 0xFF;
+        response.resize(925, 0x5A);
+        BigEndian::write_u16(&mut response[2..], 925);
+        conn.write_all(&response).unwrap();
+    });
+
+    let mut cl = Client::new(Transport::connect(opts).unwrap()).unwrap();
+    assert_eq!(cl.negotiation_info().pdu_length, 960);
+
+    let buffer = &mut [0u8; 900];
+    cl.ag_read(1, 0, 900, buffer).unwrap();
+    assert!(buffer.iter().all(|b| *b == 0x5A));
+    plc.join().unwrap();
+
+    // the negotiated PDU still bounds the frames
+    let mut frame = vec![3, 0, 0, 0, 2, COTP_DATA, COTP_EOT];
+    frame.resize(500, 0);
+    BigEndian::write_u16(&mut frame[2..], 500);
+    let mut framing = Framing {
+        max_length: 487,
+        strict: true,
+    };
+    assert!(matches!(
+        receive(&mut frame.as_slice(), framing),
+        Err(Error::PduLength(500))
+    ));
+    framing.max_length = 967;
+    assert_eq!(receive(&mut frame.as_slice(), framing).unwrap(), frame);
+}
+
+#[test]
+fn test_disconnect_request_on_drop() {
+    use crate::client::Client;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = listener.local_addr().unwrap().to_string();
+    opts.src_reference = Some(0x1234);
+
+    let plc = thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        accept_negotiation(&mut conn, 480);
+
+        let mut request = [0u8; 11];
+        conn.read_exact(&mut request).unwrap();
+        assert_eq!(request, [3, 0, 0, 11, 6, 0x80, 0x43, 0x21, 0x12, 0x34, 0]);
+    });
+
+    let cl = Client::new(Transport::connect(opts).unwrap()).unwrap();
+    drop(cl);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_keepalive() {
+    use crate::client::Client;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = listener.local_addr().unwrap().to_string();
+    opts.keepalive = Some(Duration::from_millis(50));
+
+    let plc = thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        accept_negotiation(&mut conn, 480);
+
+        // idle client, the status gets polled with the client's references
+        for reference in [0x7000u16, 0x7001].iter() {
+            let request = receive(&mut conn, ANY_FRAME).unwrap();
+            assert_eq!(request[..11], transport::PLC_STATUS_TELEGRAM[..11]);
+            assert_eq!(request[13..], transport::PLC_STATUS_TELEGRAM[13..]);
+            assert_eq!(BigEndian::read_u16(&request[11..]), *reference);
+            let mut status = vec![3, 0, 0, 45, 2, COTP_DATA, COTP_EOT];
+            status.resize(45, 0);
+            conn.write_all(&status).unwrap();
+        }
+    });
+
+    let mut cl = Client::new(Transport::connect(opts).unwrap()).unwrap();
+    cl.set_pdu_ref_base(0x7000);
+    plc.join().unwrap();
+    assert_eq!(cl.last_pdu_ref(), 0x7001);
+    drop(cl);
+}
+
+#[test]
+fn test_keepalive_timeout() {
+    use crate::client::Client;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = listener.local_addr().unwrap().to_string();
+    opts.keepalive = Some(Duration::from_millis(50));
+    opts.keepalive_timeout = Duration::from_millis(100);
+    // no timeout for the client's own jobs
+    opts.read_timeout = Duration::new(0, 0);
+
+    let plc = thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        accept_negotiation(&mut conn, 480);
+
+        // the status is never answered
+        receive(&mut conn, ANY_FRAME).unwrap();
+        thread::sleep(Duration::from_secs(2));
+    });
+
+    let mut cl = Client::new(Transport::connect(opts).unwrap()).unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    // waits for the keepalive to give up, not for the PLC
+    let started = Instant::now();
+    assert!(cl.ag_read(1, 0, 1, &mut [0u8; 1]).is_err());
+    assert!(started.elapsed() < Duration::from_secs(1));
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_keepalive_failure() {
+    use crate::client::Client;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = listener.local_addr().unwrap().to_string();
+    opts.keepalive = Some(Duration::from_millis(50));
+    opts.read_timeout = Duration::from_millis(100);
+
+    let plc = thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        accept_negotiation(&mut conn, 480);
+
+        // the status answer comes after the keepalive gave up waiting
+        receive(&mut conn, ANY_FRAME).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        let mut late = vec![3, 0, 0, 26, 2, COTP_DATA, COTP_EOT];
+        late.resize(26, 0);
+        late[21] = 0xFF;
+        let _ = conn.write_all(&late);
+        thread::sleep(Duration::from_millis(300));
+    });
+
+    let mut cl = Client::new(Transport::connect(opts).unwrap()).unwrap();
+    thread::sleep(Duration::from_millis(500));
+
+    // the late answer isn't taken for the one of the read
+    let buffer = &mut [0u8; 1];
+    assert!(cl.ag_read(1, 0, 1, buffer).is_err());
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_slot_config() {
+    use std::net::Ipv4Addr;
+
+    let addr = IpAddr::from(Ipv4Addr::new(127, 0, 0, 1));
+
+    let opts = Options::with_slot_config(addr, SlotConfig::S7_300, Connection::PG);
+    assert_eq!((opts.rack, opts.slot), (0, 2));
+
+    let opts = Options::with_slot_config(addr, SlotConfig::S7_1200, Connection::PG);
+    assert_eq!((opts.rack, opts.slot), (0, 1));
+
+    let opts = Options::with_slot_config(addr, SlotConfig::S7_400, Connection::OP);
+    assert_eq!((opts.rack, opts.slot), (0, 3));
+}
+
+#[cfg(feature = "local-bind")]
+#[test]
+fn test_connect_from_local_addr() {
+    use std::net::{Ipv4Addr, TcpListener};
+
+    // a free port to bind to
+    let local = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = listener.local_addr().unwrap().to_string();
+    opts.local_addr = Some(local);
+
+    let peer = listener.local_addr().unwrap();
+    let plc = thread::spawn(move || listener.accept().unwrap().1);
+    let transport = Transport::connect(opts).unwrap();
+    assert_eq!(plc.join().unwrap(), local);
+    assert_eq!(transport.peer_addr(), peer);
+    assert_eq!(PackTrait::peer_addr(&transport), Some(peer));
+}
+
+#[test]
+fn test_send_only_recv() {
+    use std::net::{Ipv4Addr, TcpListener};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let plc = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        // both requests arrive before any answer is sent
+        let first = receive(&mut stream, ANY_FRAME).unwrap();
+        let second = receive(&mut stream, ANY_FRAME).unwrap();
+        stream.write_all(&second).unwrap();
+        stream.write_all(&first).unwrap();
+    });
+
+    let opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    let mut transport = Transport::from_stream(TcpStream::connect(address).unwrap(), opts).unwrap();
+    let mut first = vec![3, 0, 0, 22, 2, COTP_DATA, COTP_EOT];
+    let mut second = first.clone();
+    first.extend_from_slice(&[1u8; 15]);
+    second.extend_from_slice(&[2u8; 15]);
+    transport.send_only(&first).unwrap();
+    transport.send_only(&second).unwrap();
+    assert_eq!(transport.recv().unwrap(), second);
+    assert_eq!(transport.recv().unwrap(), first);
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_not_a_tpkt_endpoint() {
+    use std::net::{Ipv4Addr, TcpListener};
+
+    let http = b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n";
+    match receive(&mut &http[..], ANY_FRAME) {
+        Err(Error::InvalidResponse { reason, bytes }) => {
+            assert!(reason.contains("not a TPKT frame"));
+            assert_eq!(bytes, b"HTTP");
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = listener.local_addr().unwrap().to_string();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.read_exact(&mut [0u8; 22]).unwrap();
+        stream.write_all(http).unwrap();
+    });
+
+    let mut transport = Transport::connect(opts).unwrap();
+    assert!(matches!(
+        transport.negotiate(),
+        Err(Error::InvalidResponse { .. })
+    ));
+    server.join().unwrap();
+}
+
+#[test]
+fn test_strict_tpkt() {
+    use crate::client::Client;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = listener.local_addr().unwrap().to_string();
+
+    // a gateway filling the reserved byte of its answers
+    let gateway = thread::spawn(move || {
+        for _ in 0..2 {
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut confirm = receive(&mut conn, ANY_FRAME).unwrap();
+            confirm[1] = 0xFF;
+            confirm[5] = transport::CONFIRM_CONNECTION;
+            conn.write_all(&confirm).unwrap();
+
+            if receive(&mut conn, ANY_FRAME).is_err() {
+                continue;
+            }
+            let mut negotiated = vec![3, 0xFF, 0, 27, 2, COTP_DATA, COTP_EOT];
+            negotiated.resize(27, 0);
+            BigEndian::write_u16(&mut negotiated[25..], 480);
+            conn.write_all(&negotiated).unwrap();
+        }
+    });
+
+    assert!(opts.strict_tpkt);
+    match Client::new(Transport::connect(opts.clone()).unwrap()) {
+        Err(Error::InvalidResponse { reason, bytes }) => {
+            assert!(reason.contains("not a TPKT frame"));
+            assert_eq!(bytes, [3, 0xFF, 0, 22]);
+        }
+        other => panic!("expected InvalidResponse got {:?}", other.err()),
+    }
+
+    opts.strict_tpkt = false;
+    let cl = Client::new(Transport::connect(opts).unwrap()).unwrap();
+    assert_eq!(cl.negotiation_info().pdu_length, 480);
+    gateway.join().unwrap();
 }
@@ -0,0 +1,283 @@
+// Copyright 2019 Petar Dambovaliev. All rights reserved.
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+//! TCP transport on tokio, needs the `async` feature
+
+use super::{
+    connection_confirm, connection_refused, disconnect_request, negotiated, timeout, tpkt_length,
+    Framing, Options, ISO_HEADER_SIZE,
+};
+use crate::error::Error;
+use crate::transport::{self, Connection, COTP_DATA, COTP_EOT};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// TCP connection to the PLC for tokio applications.
+/// The exchanges of `Transport` as futures, without the keepalive.
+pub struct AsyncTransport {
+    options: Options,
+    /// the address the stream is connected to
+    peer: SocketAddr,
+    stream: TcpStream,
+    /// local and remote COTP references, used to disconnect
+    references: (u16, u16),
+}
+
+impl AsyncTransport {
+    /// connects to the PLC, opens the ISO connection and negotiates the PDU length.
+    ///
+    /// The TCP connect is bounded by `Options::connect_timeout`, the rest by
+    /// `Options::negotiate_timeout`, either fails with `Error::Timeout`.
+    /// The socket belongs to the future until it resolves: dropping the future,
+    /// because it lost a `select!` for instance, closes the socket at any stage.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, IpAddr};
+    /// use s7::{error::Error, tcp, transport};
+    ///
+    /// async fn connect() -> Result<tcp::AsyncTransport, Error> {
+    ///     let addr = Ipv4Addr::new(192, 168, 0, 1);
+    ///     let opts = tcp::Options::new(IpAddr::from(addr), 0, 2, transport::Connection::PG);
+    ///     tcp::AsyncTransport::connect(opts).await
+    /// }
+    /// ```
+    pub async fn connect(mut options: Options) -> Result<AsyncTransport, Error> {
+        let mut stream = within(options.connect_timeout, async {
+            Ok(TcpStream::connect(options.address.as_str()).await?)
+        })
+        .await?;
+
+        options.set_tsap();
+        let (references, negotiation) =
+            within(options.negotiate_timeout, negotiate(&mut stream, &options)).await?;
+        options.negotiation = negotiation;
+
+        Ok(AsyncTransport {
+            peer: stream.peer_addr()?,
+            options,
+            stream,
+            references,
+        })
+    }
+
+    /// sends the request and waits for the answer, bounded by the write and read timeouts.
+    /// A dropped exchange leaves its answer on the connection, don't use it anymore.
+    pub async fn send(&mut self, request: &[u8]) -> Result<Vec<u8>, Error> {
+        let framing = self.options.framing();
+        let stream = &mut self.stream;
+
+        within(self.options.write_timeout, async {
+            Ok(stream.write_all(request).await?)
+        })
+        .await?;
+        let response = within(self.options.read_timeout, receive(stream, framing)).await?;
+        self.options.last_pdu_type = response[5];
+        Ok(response)
+    }
+
+    pub fn pdu_length(&self) -> i32 {
+        self.options.negotiation.pdu_length as i32
+    }
+
+    pub fn negotiation_info(&self) -> transport::NegotiationInfo {
+        self.options.negotiation
+    }
+
+    pub fn connection_type(&self) -> Connection {
+        self.options.conn_type
+    }
+
+    /// the address of the PLC
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+}
+
+/// sends a COTP disconnect request so the PLC frees the connection right away.
+/// Best effort, only what fits in the socket buffer without waiting is sent.
+impl Drop for AsyncTransport {
+    fn drop(&mut self) {
+        let _ = self.stream.try_write(&disconnect_request(self.references));
+    }
+}
+
+/// runs `future` for at most `duration`, zero means no limit
+async fn within<T, F>(duration: Duration, future: F) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    match timeout(duration) {
+        Some(duration) => tokio::time::timeout(duration, future)
+            .await
+            .map_err(|_| Error::Timeout)?,
+        None => future.await,
+    }
+}
+
+/// opens the ISO connection and negotiates the PDU length,
+/// returns the COTP references and the negotiated parameters
+async fn negotiate(
+    stream: &mut TcpStream,
+    options: &Options,
+) -> Result<((u16, u16), transport::NegotiationInfo), Error> {
+    let framing = options.framing();
+
+    let request = options.connection_request();
+    stream.write_all(&request).await?;
+    let response = receive(stream, framing).await.map_err(connection_refused)?;
+    let references = connection_confirm(&request, &response)?;
+
+    stream.write_all(&options.negotiation_request()).await?;
+    let negotiation = negotiated(receive(stream, framing).await)?;
+    Ok((references, negotiation))
+}
+
+/// reads the frames of a whole telegram, which the blocking `receive` checks and joins
+async fn receive<R: AsyncRead + Unpin>(stream: &mut R, framing: Framing) -> Result<Vec<u8>, Error> {
+    let mut frames = Vec::new();
+
+    loop {
+        let start = frames.len();
+        frames.resize(start + 4, 0);
+        stream.read_exact(&mut frames[start..]).await?;
+
+        let length = tpkt_length(&frames[start..], framing)?;
+        if !(ISO_HEADER_SIZE..=framing.max_length as i32).contains(&length) {
+            return Err(Error::PduLength(length));
+        }
+        frames.resize(start + length as usize, 0);
+        stream.read_exact(&mut frames[start + 4..]).await?;
+
+        let header = &frames[start..start + ISO_HEADER_SIZE as usize];
+        if length != ISO_HEADER_SIZE && (header[5] != COTP_DATA || header[6] & COTP_EOT != 0) {
+            break;
+        }
+    }
+    super::receive(&mut frames.as_slice(), framing)
+}
+
+#[cfg(test)]
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[cfg(test)]
+fn local_options(address: SocketAddr) -> Options {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    let mut opts = Options::new(
+        IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
+        0,
+        2,
+        Connection::PG,
+    );
+    opts.address = address.to_string();
+    opts
+}
+
+/// the connection request is read and never answered,
+/// returns whether the client closed the connection within 5s
+#[cfg(test)]
+fn ignore_connection_request(listener: std::net::TcpListener) -> bool {
+    use std::io::Read;
+
+    let (mut conn, _) = listener.accept().unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    super::receive(&mut conn, super::ANY_FRAME).unwrap();
+
+    let mut buf = [0u8; 1];
+    matches!(conn.read(&mut buf), Ok(0))
+}
+
+#[test]
+fn test_async_connect() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let opts = local_options(address);
+
+    let plc = thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        super::accept_negotiation(&mut conn, 960);
+
+        let mut answer = super::receive(&mut conn, super::ANY_FRAME).unwrap();
+        answer[7] = 0x03;
+        conn.write_all(&answer).unwrap();
+
+        let mut disconnect = [0u8; 11];
+        conn.read_exact(&mut disconnect).unwrap();
+        disconnect
+    });
+
+    block_on(async {
+        let mut t = AsyncTransport::connect(opts).await.unwrap();
+        assert_eq!(t.pdu_length(), 960);
+        assert_eq!(t.peer_addr(), address);
+
+        let mut request = vec![3, 0, 0, 22, 2, COTP_DATA, COTP_EOT, 0x32];
+        request.resize(22, 0);
+        let answer = t.send(&request).await.unwrap();
+        assert_eq!(answer.len(), 22);
+        assert_eq!(answer[7], 0x03);
+    });
+
+    // dropping the transport disconnects, with the PLC reference as destination
+    let disconnect = plc.join().unwrap();
+    assert_eq!(disconnect[5], 0x80);
+    assert_eq!(disconnect[6..8], [0x43, 0x21]);
+}
+
+#[test]
+fn test_async_connect_cancelled() {
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let opts = local_options(listener.local_addr().unwrap());
+    let plc = thread::spawn(move || ignore_connection_request(listener));
+
+    block_on(async {
+        let connect = AsyncTransport::connect(opts);
+        // a lost race drops the future half way through the connection
+        let cancelled = tokio::time::timeout(Duration::from_millis(100), connect).await;
+        assert!(cancelled.is_err());
+    });
+    assert!(
+        plc.join().unwrap(),
+        "the socket of the cancelled connect is still open"
+    );
+}
+
+#[test]
+fn test_async_negotiate_timeout() {
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut opts = local_options(listener.local_addr().unwrap());
+    opts.negotiate_timeout = Duration::from_millis(100);
+    let plc = thread::spawn(move || ignore_connection_request(listener));
+
+    match block_on(AsyncTransport::connect(opts)) {
+        Err(Error::Timeout) => {}
+        Err(e) => panic!("expected Timeout got {:?}", e),
+        Ok(_) => panic!("connected to a PLC that never answers"),
+    }
+    assert!(
+        plc.join().unwrap(),
+        "the socket of the failed connect is still open"
+    );
+}
@@ -5,13 +5,21 @@
 //! Transport definition for PLC
 
 use super::constant;
-use super::error::Error;
+use super::error::{self, Error};
+use byteorder::{BigEndian, ByteOrder};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicU16;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 
 /// Client Connection Type
 /// 16 possible connections limited by the hardware
 /// The types are defined from the highest to lowest priority
 /// The basic connections are the first which would be closed
-/// if there aren't enough resources
+/// if there aren't enough resources.
+/// The value is sent as the high byte of the remote TSAP when connecting.
 #[derive(Debug, Copy, Clone)]
 pub enum Connection {
     /// Connect to the PLC programming console (Programmiergeräte). German for programming device.
@@ -28,21 +36,387 @@ pub enum Connection {
 ///
 /// Types that are [`Transport`] should store the `pdu_length`
 /// at the connection phase `self.pdu_length = BigEndian::read_u16(&response[25..]) as i32;`
+/// or the whole [`NegotiationInfo`] with `NegotiationInfo::parse(&response)`
+///
+/// `io::Error` converts into [`Error`], so `?` works on the underlying medium directly.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Read, Write};
+/// use s7::error::Error;
+/// use s7::transport::{Connection, NegotiationInfo, Transport};
+///
+/// /// a PLC behind any byte stream, a serial gateway for instance
+/// struct Gateway<S: Read + Write> {
+///     stream: S,
+///     negotiation: NegotiationInfo,
+/// }
+///
+/// impl<S: Read + Write> Transport for Gateway<S> {
+///     fn send(&mut self, request: &[u8]) -> Result<Vec<u8>, Error> {
+///         self.stream.write_all(request)?;
+///
+///         let mut header = [0u8; 4];
+///         self.stream.read_exact(&mut header)?;
+///         let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+///
+///         let mut response = header.to_vec();
+///         response.resize(length.max(4), 0);
+///         self.stream.read_exact(&mut response[4..])?;
+///         Ok(response)
+///     }
+///
+///     fn pdu_length(&self) -> i32 {
+///         self.negotiation.pdu_length as i32
+///     }
+///
+///     fn negotiate(&mut self) -> Result<NegotiationInfo, Error> {
+///         let mut request = vec![3, 0, 0, 25, 2, 240, 128, 50, 1, 0, 0, 4, 0, 0, 8, 0, 0];
+///         request.extend_from_slice(&[240, 0, 0, 1, 0, 1, 1, 224]);
+///         let response = self.send(&request)?;
+///         self.negotiation = NegotiationInfo::parse(&response)?;
+///         Ok(self.negotiation)
+///     }
+///
+///     fn connection_type(&self) -> Connection {
+///         Connection::PG
+///     }
+/// }
+/// ```
 pub trait Transport {
     /// send request to the plc.
     /// returns a response and an error, if there was any.
     fn send(&mut self, request: &[u8]) -> Result<Vec<u8>, Error>;
     /// pdu length needs to be set by the implementor, during the connection phase.
     fn pdu_length(&self) -> i32;
-    /// negotiate is called by the client and should only be defined by the implementor
-    fn negotiate(&mut self) -> Result<(), Error>;
+    /// negotiate is called by the client and should only be defined by the implementor.
+    /// Returns the parameters agreed with the PLC, checked by `Client::new` right away.
+    fn negotiate(&mut self) -> Result<NegotiationInfo, Error>;
 
     fn connection_type(&self) -> Connection;
+
+    /// the address of the PLC, `None` for transports not over IP
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    /// the PDU reference of the last job, for transports sending jobs of their own
+    /// like a keepalive. The client numbers its jobs with the same counter.
+    fn pdu_ref_counter(&self) -> Option<Arc<AtomicU16>> {
+        None
+    }
+
+    /// negotiates the pdu length again over the established connection,
+    /// after the PLC refused a request as larger than its PDU.
+    /// Defaults to `negotiate`, implementors that set up a connection there override it.
+    fn renegotiate_pdu(&mut self) -> Result<NegotiationInfo, Error> {
+        self.negotiate()
+    }
+
+    /// sends a request without waiting for its answer, collected later with `recv`.
+    /// Several requests may be sent before their answers are read,
+    /// up to the `max_amq_calling` of the negotiation.
+    /// Defaults to `send`, its answer is kept until `recv` collects it.
+    /// The answers are kept by the address of the transport,
+    /// don't move it before they are all collected.
+    fn send_only(&mut self, request: &[u8]) -> Result<(), Error> {
+        let response = self.send(request)?;
+        let mut pending = PENDING_ANSWERS.lock().map_err(|_| Error::Lock)?;
+        pending
+            .entry(pending_key(self))
+            .or_default()
+            .push_back(response);
+        Ok(())
+    }
+
+    /// waits for the next telegram from the plc, the answer to a `send_only`.
+    /// Defaults to the oldest answer kept by `send_only`.
+    fn recv(&mut self) -> Result<Vec<u8>, Error> {
+        let mut pending = PENDING_ANSWERS.lock().map_err(|_| Error::Lock)?;
+        let key = pending_key(self);
+        let response = pending.get_mut(&key).and_then(VecDeque::pop_front);
+        if pending.get(&key).is_some_and(VecDeque::is_empty) {
+            pending.remove(&key);
+        }
+        response.ok_or_else(|| Error::InvalidInput {
+            input: "no request sent with send_only is waiting for its answer".to_string(),
+        })
+    }
+
+    /// sends all the requests without waiting for each answer in between,
+    /// keeping at most `max_outstanding` of them in flight.
+    /// The responses are returned in the order they arrived, which may differ
+    /// from the order of the requests, use the PDU reference to match them.
+    /// Implementors that can't pipeline send the requests one by one.
+    fn send_pipelined(
+        &mut self,
+        requests: &[Vec<u8>],
+        max_outstanding: usize,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let _ = max_outstanding;
+        requests.iter().map(|request| self.send(request)).collect()
+    }
+
+    /// the parameters agreed with the PLC during negotiation.
+    /// Implementors that don't keep track of them only report the pdu length.
+    fn negotiation_info(&self) -> NegotiationInfo {
+        NegotiationInfo {
+            pdu_length: self.pdu_length() as u16,
+            max_amq_calling: 1,
+            max_amq_called: 1,
+        }
+    }
+}
+
+/// answers to the requests of the default `Transport::send_only`
+/// until `Transport::recv` collects them, by address of the transport
+static PENDING_ANSWERS: Mutex<BTreeMap<usize, VecDeque<Vec<u8>>>> = Mutex::new(BTreeMap::new());
+
+fn pending_key<T: ?Sized>(transport: &T) -> usize {
+    transport as *const T as *const () as usize
+}
+
+/// smallest negotiated PDU length the client can work with
+pub const MIN_PDU_LENGTH: i32 = 240;
+
+/// Parameters agreed with the PLC in the setup communication exchange
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct NegotiationInfo {
+    /// the maximum size of a telegram
+    pub pdu_length: u16,
+    /// the maximum number of outstanding jobs the client may send
+    pub max_amq_calling: u16,
+    /// the maximum number of outstanding jobs the PLC may send
+    pub max_amq_called: u16,
+}
+
+/// Which PLC a client talks to and how, to tell connections apart in logs
+#[derive(Debug, Copy, Clone)]
+pub struct ConnectionInfo {
+    pub peer_addr: Option<SocketAddr>,
+    pub connection_type: Connection,
+    pub negotiation: NegotiationInfo,
+}
+
+impl NegotiationInfo {
+    /// parses the answer to `PDU_NEGOTIATION_TELEGRAM`
+    pub fn parse(response: &[u8]) -> Result<NegotiationInfo, Error> {
+        // trailing padding of some gateways is ignored
+        if response.len() < 27 || response[17] != 0 || response[18] != 0 {
+            return Err(Error::Response {
+                code: error::CLI_NEGOTIATING_PDU,
+                bytes: response.to_vec(),
+            });
+        }
+
+        let info = NegotiationInfo {
+            max_amq_calling: BigEndian::read_u16(&response[21..]),
+            max_amq_called: BigEndian::read_u16(&response[23..]),
+            pdu_length: BigEndian::read_u16(&response[25..]),
+        };
+
+        if info.pdu_length == 0 {
+            return Err(Error::Response {
+                code: error::CLI_NEGOTIATING_PDU,
+                bytes: response.to_vec(),
+            });
+        }
+        Ok(info)
+    }
+}
+
+/// PDU length requested by the loopback transport during negotiation
+const LOOPBACK_PDU_SIZE_REQUESTED: u16 = 480;
+
+/// One end of an in-memory duplex channel created by [`loopback`]
+///
+/// Both ends implement [`Transport`], so either one can be handed to a `Client`.
+/// The other end plays the part of the PLC and answers the telegrams with
+/// [`Loopback::recv`] and [`Loopback::reply`].
+#[derive(Debug)]
+pub struct Loopback {
+    tx: Sender<Vec<u8>>,
+    rx: Receiver<Vec<u8>>,
+    conn_type: Connection,
+    negotiation: NegotiationInfo,
+}
+
+/// Creates a connected pair of in-memory transports.
+/// Everything sent on one end is received on the other, no network involved.
+///
+/// # Examples
+///
+/// ```
+/// use s7::{client::Client, transport};
+/// use std::thread;
+///
+/// let (client_end, plc) = transport::loopback();
+///
+/// let plc = thread::spawn(move || {
+///     // answer the pdu negotiation with a pdu length of 480
+///     plc.recv().unwrap();
+///     let mut negotiated = vec![0u8; 27];
+///     negotiated[25..].copy_from_slice(&480u16.to_be_bytes());
+///     plc.reply(&negotiated).unwrap();
+///
+///     // answer a read request with a single byte
+///     plc.recv().unwrap();
+///     let mut response = vec![0u8; 26];
+///     response[21] = 0xFF;
+///     response[25] = 42;
+///     plc.reply(&response).unwrap();
+/// });
+///
+/// let mut cl = Client::new(client_end).unwrap();
+/// let buffer = &mut vec![0u8; 1];
+///
+/// cl.ag_read(1, 0, 1, buffer).unwrap();
+/// assert_eq!(buffer[0], 42);
+/// plc.join().unwrap();
+/// ```
+pub fn loopback() -> (Loopback, Loopback) {
+    let (client_tx, plc_rx) = mpsc::channel();
+    let (plc_tx, client_rx) = mpsc::channel();
+
+    (
+        Loopback::new(client_tx, client_rx),
+        Loopback::new(plc_tx, plc_rx),
+    )
+}
+
+impl Loopback {
+    fn new(tx: Sender<Vec<u8>>, rx: Receiver<Vec<u8>>) -> Loopback {
+        Loopback {
+            tx,
+            rx,
+            conn_type: Connection::PG,
+            negotiation: NegotiationInfo::default(),
+        }
+    }
+
+    /// waits for the next telegram sent by the other end
+    pub fn recv(&self) -> Result<Vec<u8>, Error> {
+        self.rx
+            .recv()
+            .map_err(|_| Error::Io(io::Error::from(ErrorKind::ConnectionAborted)))
+    }
+
+    /// sends a telegram to the other end without waiting for an answer
+    pub fn reply(&self, telegram: &[u8]) -> Result<(), Error> {
+        self.tx.send(telegram.to_vec()).map_err(|_| Error::Send)
+    }
+}
+
+impl Transport for Loopback {
+    fn send(&mut self, request: &[u8]) -> Result<Vec<u8>, Error> {
+        self.reply(request)?;
+        self.recv()
+    }
+
+    fn pdu_length(&self) -> i32 {
+        self.negotiation.pdu_length as i32
+    }
+
+    /// there is no ISO connection to establish, only the pdu length is negotiated
+    fn negotiate(&mut self) -> Result<NegotiationInfo, Error> {
+        let mut request = PDU_NEGOTIATION_TELEGRAM.to_vec();
+        BigEndian::write_u16(request[23..].as_mut(), LOOPBACK_PDU_SIZE_REQUESTED);
+
+        let response = self.send(request.as_slice())?;
+        self.negotiation = NegotiationInfo::parse(response.as_ref())?;
+        Ok(self.negotiation)
+    }
+
+    fn connection_type(&self) -> Connection {
+        self.conn_type
+    }
+
+    fn send_only(&mut self, request: &[u8]) -> Result<(), Error> {
+        self.reply(request)
+    }
+
+    fn recv(&mut self) -> Result<Vec<u8>, Error> {
+        Loopback::recv(self)
+    }
+
+    fn send_pipelined(
+        &mut self,
+        requests: &[Vec<u8>],
+        max_outstanding: usize,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let mut responses = Vec::with_capacity(requests.len());
+
+        for window in requests.chunks(max_outstanding.max(1)) {
+            for request in window {
+                self.reply(request)?;
+            }
+            for _ in window {
+                responses.push(self.recv()?);
+            }
+        }
+        Ok(responses)
+    }
+
+    fn negotiation_info(&self) -> NegotiationInfo {
+        self.negotiation
+    }
+}
+
+/// COTP data transfer PDU type
+pub(crate) const COTP_DATA: u8 = 0xF0;
+/// last data unit of the telegram
+pub(crate) const COTP_EOT: u8 = 0x80;
+
+/// length of the frame starting with this TPKT header (RFC 1006), headers included.
+/// `None` when the bytes don't start with a TPKT header: too short,
+/// another version than 3 or a length shorter than the header itself.
+///
+/// # Examples
+///
+/// ```
+/// use s7::transport::parse_tpkt_len;
+///
+/// assert_eq!(parse_tpkt_len(&[3, 0, 0, 22, 17, 224]), Some(22));
+/// assert_eq!(parse_tpkt_len(&[0x16, 3, 1, 0]), None);
+/// ```
+pub fn parse_tpkt_len(frame: &[u8]) -> Option<usize> {
+    match frame {
+        [3, 0, high, low, ..] => {
+            Some(u16::from_be_bytes([*high, *low]) as usize).filter(|l| *l >= 4)
+        }
+        _ => None,
+    }
+}
+
+/// the frame is a COTP data transfer carrying a S7 PDU, as opposed to
+/// connection management frames or another protocol on the same port
+///
+/// # Examples
+///
+/// ```
+/// use s7::transport::is_s7_data_pdu;
+///
+/// assert!(is_s7_data_pdu(&[3, 0, 0, 25, 2, 0xF0, 0x80, 0x32, 1]));
+/// // a connection confirm
+/// assert!(!is_s7_data_pdu(&[3, 0, 0, 22, 17, 0xD0, 0, 1]));
+/// ```
+pub fn is_s7_data_pdu(frame: &[u8]) -> bool {
+    parse_tpkt_len(frame).is_some() && frame.len() > 7 && frame[5] == COTP_DATA && frame[7] == 0x32
 }
 
 /// response from the plc that the connection has been confirmed
 pub const CONFIRM_CONNECTION: u8 = 0xD0;
 
+/// COTP disconnect request, tells the PLC to free the connection right away
+pub(crate) const DISCONNECT_REQUEST_TELEGRAM: [u8; 11] = [
+    3, 0, 0, 11, // TPKT
+    6, 128, // DR
+    0, 0, // Dst Reference (6)
+    0, 0, // Src Reference (8)
+    0, // Normal disconnect
+];
+
 /// ISO Connection Request telegram (contains also ISO Header and COTP Header)
 /// TPKT (RFC1006 Header)
 pub const ISO_CONNECTION_REQUEST_TELEGRAM: [u8; 22] = [
@@ -68,8 +442,8 @@ pub const ISO_CONNECTION_REQUEST_TELEGRAM: [u8; 22] = [
     194, // Dst TSAP Identifier
     2,   // Dst TSAP Length (2 bytes)
     1,   // Dst TSAP HI (will be overwritten)
-    2,
-]; // Dst TSAP LO (will be overwritten)
+    2,   // Dst TSAP LO (will be overwritten)
+];
 
 /// S7 Read/Write Request Header (contains also ISO Header and COTP Header)
 pub const READ_WRITE_TELEGRAM: [u8; 35] = [
@@ -118,20 +492,52 @@ pub const PDU_NEGOTIATION_TELEGRAM: [u8; 25] = [
     50, 1, 0, 0, 4, 0, 0, 8, 0, 0, 240, 0, 0, 1, 0, 1, 0, 30,
 ]; // PDU Length Requested = HI-LO Here Default 480 bytes
 
+/// builds a PLC control job: `function` is `PDU_START` or `PDU_STOP`, `service` the
+/// program invocation like `P_PROGRAM`. Start jobs also carry `argument`, the kind
+/// of start: empty for a warm start, `C ` for a cold start, `A ` for a hot restart.
+pub(crate) fn control_telegram(function: u8, argument: &[u8], service: &[u8]) -> Vec<u8> {
+    let mut parameter = vec![function, 0, 0, 0, 0, 0];
+    if function == PDU_START {
+        parameter.extend_from_slice(&[0, 0xFD]);
+        parameter.extend_from_slice(&(argument.len() as u16).to_be_bytes());
+        parameter.extend_from_slice(argument);
+    }
+    parameter.push(service.len() as u8);
+    parameter.extend_from_slice(service);
+
+    // TPKT + COTP, then the S7 job header without data
+    let mut telegram = vec![3, 0, 0, 0, 2, 240, 128, 50, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+    BigEndian::write_u16(telegram[13..].as_mut(), parameter.len() as u16);
+    telegram.extend_from_slice(&parameter);
+    let length = telegram.len() as u16;
+    BigEndian::write_u16(telegram[2..].as_mut(), length);
+    telegram
+}
+
 /// warm start request
-pub(crate) const WARM_START_TELEGRAM: [u8; 37] = [
+#[cfg(test)]
+const WARM_START_TELEGRAM: [u8; 37] = [
     3, 0, 0, 37, 2, 240, 128, 50, 1, 0, 0, 12, 0, 0, 20, 0, 0, 40, 0, 0, 0, 0, 0, 0, 253, 0, 0, 9,
     80, 95, 80, 82, 79, 71, 82, 65, 77,
 ];
 
 /// cold start request
-pub(crate) const COLD_START_TELEGRAM: [u8; 39] = [
+#[cfg(test)]
+const COLD_START_TELEGRAM: [u8; 39] = [
     3, 0, 0, 39, 2, 240, 128, 50, 1, 0, 0, 15, 0, 0, 22, 0, 0, 40, 0, 0, 0, 0, 0, 0, 253, 0, 2, 67,
     32, 9, 80, 95, 80, 82, 79, 71, 82, 65, 77,
 ];
 
+/// hot restart request, only S7-400 CPUs support it
+#[cfg(test)]
+const HOT_RESTART_TELEGRAM: [u8; 39] = [
+    3, 0, 0, 39, 2, 240, 128, 50, 1, 0, 0, 15, 0, 0, 22, 0, 0, 40, 0, 0, 0, 0, 0, 0, 253, 0, 2, 65,
+    32, 9, 80, 95, 80, 82, 79, 71, 82, 65, 77,
+];
+
 /// stop request
-pub(crate) const STOP_TELEGRAM: [u8; 33] = [
+#[cfg(test)]
+const STOP_TELEGRAM: [u8; 33] = [
     3, 0, 0, 33, 2, 240, 128, 50, 1, 0, 0, 14, 0, 0, 16, 0, 0, 41, 0, 0, 0, 0, 0, 9, 80, 95, 80,
     82, 79, 71, 82, 65, 77,
 ];
@@ -148,7 +554,11 @@ pub(crate) const SZL_FIRST_TELEGRAM: [u8; 33] = [
     0, 0,
 ]; // Index (31)];
 
-pub(crate) const MIN_SZL_FIRST_TELEGRAM: usize = 42;
+/// header size of the first SZL response, the data starts right after
+pub(crate) const MIN_SZL_FIRST_TELEGRAM: usize = 41;
+
+/// header size of the following SZL responses, the data starts right after
+pub(crate) const MIN_SZL_NEXT_TELEGRAM: usize = 37;
 
 pub(crate) const SZL_NEXT_TELEGRAM: [u8; 33] = [
     3, 0, 0, 33, 2, 240, 128, 50, 7, 0, 0, 6, 0, 0, 12, 0, 4, 0, 1, 18, 8, 18, 68, 1,
@@ -156,10 +566,130 @@ pub(crate) const SZL_NEXT_TELEGRAM: [u8; 33] = [
     0, 0, 0, 0, 10, 0, 0, 0,
 ]; // Index (31)];
 
+/// read clock request, userdata function group 7 (time) subfunction 1
+pub(crate) const GET_CLOCK_TELEGRAM: [u8; 29] = [
+    3, 0, 0, 29, 2, 240, 128, 50, 7, 0, 0, 56, 0, 0, 8, 0, 4, 0, 1, 18, 4, 17, 71, 1, 0, 10, 0, 0,
+    0,
+];
+
+/// size of the read clock response, up to the end of the 10 bytes timestamp at 33
+pub(crate) const GET_CLOCK_MIN_RESPONSE: usize = 43;
+
+/// start upload request, the file name is `_0`, the block type, its number and `A`
+pub(crate) const START_UPLOAD_TELEGRAM: [u8; 35] = [
+    3, 0, 0, 35, 2, 240, 128, 50, 1, 0, 0, 0, 0, 0, 18, 0, 0, 29, 0, 0, 0, 0, 0, 0, 0, 9, 95, 48,
+    65, // Block type (28)
+    48, 48, 48, 48, 48, // ASCII Block number (29)
+    65,
+];
+
+/// upload request, asks for the next part of the block
+pub(crate) const UPLOAD_TELEGRAM: [u8; 25] = [
+    3, 0, 0, 25, 2, 240, 128, 50, 1, 0, 0, 0, 0, 0, 8, 0, 0, 30, 0, 0, 0, // Upload id (21)
+    0, 0, 0, 0,
+];
+
+/// end upload request
+pub(crate) const END_UPLOAD_TELEGRAM: [u8; 25] = [
+    3, 0, 0, 25, 2, 240, 128, 50, 1, 0, 0, 0, 0, 0, 8, 0, 0, 31, 0, 0, 0, // Upload id (21)
+    0, 0, 0, 0,
+];
+
+/// size of the start upload response, up to the upload id
+pub(crate) const START_UPLOAD_MIN_RESPONSE: usize = 27;
+
+/// size of the upload response header, the block data starts right after
+pub(crate) const UPLOAD_MIN_RESPONSE: usize = 25;
+
+/// get block info request
+pub(crate) const BLOCK_INFO_TELEGRAM: [u8; 37] = [
+    3, 0, 0, 37, 2, 240, 128, 50, 7, 0, 0, 5, 0, 0, 8, 0, 12, 0, 1, 18, 4, 17, 67, 3, 0, 255, 9, 0,
+    8, 48, 65, // Block type (30)
+    48, 48, 48, 48, 48, // ASCII Block number (31)
+    65,
+];
+
+pub(crate) const BLOCK_INFO_MIN_RESPONSE: usize = 103;
+
+/// list the blocks of a type
+pub(crate) const LIST_BLOCKS_TELEGRAM: [u8; 31] = [
+    3, 0, 0, 31, 2, 240, 128, 50, 7, 0, 0, 5, 0, // Sequence out
+    0, 8, 0, 6, 0, 1, 18, 4, 17, 67, 2, 0, 255, 9, 0, 2, 48, 65, // Block type (30)
+];
+
+/// ask for the next slice of a block list
+pub(crate) const LIST_BLOCKS_NEXT_TELEGRAM: [u8; 33] = [
+    3, 0, 0, 33, 2, 240, 128, 50, 7, 0, 0, 6, 0, // Sequence out
+    0, 12, 0, 4, 0, 1, 18, 8, 18, 67, 2, 0, // Sequence (24)
+    0, 0, 0, 0, 10, 0, 0, 0,
+];
+
+/// header size of a block list response, the entries start right after
+pub(crate) const LIST_BLOCKS_MIN_RESPONSE: usize = 33;
+
+/// bytes per block in a block list response
+pub(crate) const LIST_BLOCKS_ENTRY_SIZE: usize = 4;
+
+/// variable table request, the var table header and the items are appended
+pub(crate) const VAR_TABLE_TELEGRAM: [u8; 29] = [
+    3, 0, 0, 29, 2, 240, 128, 50, 7, 0, 0, 7, 0, 0, 8, 0, 0, // Data length (15)
+    0, 1, 18, 4, 17, 65, 2, 0, // Programmer commands, variable table
+    255, 9, 0, 0, // Data length (27)
+];
+
+/// read the active force job
+pub(crate) const FORCES_TELEGRAM: [u8; 29] = [
+    3, 0, 0, 29, 2, 240, 128, 50, 7, 0, 0, 8, 0, 0, 8, 0, 4, // TPKT + COTP + S7 header
+    0, 1, 18, 4, 17, 65, 16, 0, // Programmer commands, forces
+    10, 0, 0, 0, // No data
+];
+
+/// where the data part of the variable table request starts
+pub(crate) const VAR_TABLE_DATA_START: usize = 25;
+
+/// bytes per item in a variable table request
+pub(crate) const VAR_TABLE_ITEM_SIZE: usize = 6;
+
+pub(crate) const VAR_TABLE_REQUEST: u8 = 0x14;
+
+/// header size of the variable table response, the items start right after
+pub(crate) const VAR_TABLE_MIN_RESPONSE: usize = 43;
+
+/// size of a SZL 0x0424 record
+pub(crate) const OPERATING_MODE_RECORD_SIZE: usize = 20;
+
+/// size of the OB1 start information at the beginning of a SZL 0x0222 record,
+/// up to the cycle times
+pub(crate) const OB1_START_INFO_SIZE: usize = 12;
+
+/// size of a SZL 0x0132 index 8 record, up to the time status word
+pub(crate) const TIME_SYSTEM_RECORD_SIZE: usize = 36;
+
+/// size of a SZL 0x0011 record
+pub(crate) const MODULE_ID_RECORD_SIZE: usize = 28;
+
+/// size of a SZL 0x0037 record, up to the MAC address
+pub(crate) const ETHERNET_RECORD_SIZE: usize = 20;
+
+/// size of a SZL 0x0013 record
+pub(crate) const MEMORY_AREA_RECORD_SIZE: usize = 36;
+
 pub(crate) const PLC_STATUS_MIN_RESPONSE: usize = 45;
 
 pub(crate) const TELEGRAM_MIN_RESPONSE: usize = 19;
 
+/// largest read whose response still fits the 16 bits TPKT length, 25 bytes of headers
+pub(crate) const MAX_PROBE_READ: i32 = u16::MAX as i32 - 25;
+
+/// most items a single read or write var request may carry
+pub const MAX_VARS: usize = 20;
+
+/// size of an item in a read var request
+pub(crate) const READ_ITEM_SIZE: usize = 12;
+
+/// header size of a read var response, the items start right after
+pub(crate) const READ_MULTI_MIN_RESPONSE: usize = 21;
+
 pub(crate) const SZL_MIN_RESPONSE: usize = 205;
 
 pub(crate) const PDU_START: u8 = 0x28; // CPU start
@@ -168,12 +698,207 @@ pub(crate) const PDU_STOP: u8 = 0x29; // CPU stop
 pub(crate) const PDU_ALREADY_STARTED: u8 = 0x02; // CPU already in run mode
 pub(crate) const PDU_ALREADY_STOPPED: u8 = 0x07; // CPU already in stop mode
 
-pub(crate) struct SZLHeader {
+/// header of a system status list (SZL) response
+#[derive(Debug, Clone)]
+pub struct SZLHeader {
+    /// length of a single data record in bytes
     pub length_header: u16,
+    /// number of data records in the list
     pub number_of_data_record: u16,
 }
 
-pub(crate) struct S7SZL {
+/// system status list (SZL) as read from the PLC
+#[derive(Debug, Clone)]
+pub struct S7SZL {
     pub header: SZLHeader,
+    /// all the data records, one after the other
     pub data: Vec<u8>,
 }
+
+impl S7SZL {
+    /// iterates over the data records as described by the header
+    pub fn records(&self) -> impl Iterator<Item = &[u8]> {
+        let length = (self.header.length_header as usize).max(1);
+
+        self.data
+            .chunks_exact(length)
+            .take(self.header.number_of_data_record as usize)
+    }
+}
+
+#[test]
+fn test_loopback_read_write() {
+    use crate::client::Client;
+    use std::thread;
+
+    let (client_end, plc) = loopback();
+
+    let plc = thread::spawn(move || {
+        let negotiation = plc.recv().unwrap();
+        assert_eq!(negotiation.len(), PDU_NEGOTIATION_TELEGRAM.len());
+        assert_eq!(BigEndian::read_u16(&negotiation[23..]), 480);
+
+        let mut negotiated = vec![0u8; 27];
+        BigEndian::write_u16(&mut negotiated[25..], 240);
+        plc.reply(&negotiated).unwrap();
+
+        // read of 2 bytes from DB 888 at byte 4
+        let request = plc.recv().unwrap();
+        assert_eq!(request[17], 0x04);
+        assert_eq!(BigEndian::read_u16(&request[23..]), 2);
+        assert_eq!(BigEndian::read_u16(&request[25..]), 888);
+        assert_eq!(request[27], 0x84);
+        assert_eq!(request[30], 4 << 3);
+
+        let mut response = vec![0u8; 27];
+        response[21] = 0xFF;
+        response[25] = 0xAB;
+        response[26] = 0xCD;
+        plc.reply(&response).unwrap();
+
+        // write of 1 byte to DB 888 at byte 4
+        let request = plc.recv().unwrap();
+        assert_eq!(request[17], 0x05);
+        assert_eq!(request.len(), 36);
+        assert_eq!(request[35], 0x11);
+
+        let mut response = vec![0u8; 22];
+        response[21] = 0xFF;
+        plc.reply(&response).unwrap();
+    });
+
+    let mut cl = Client::new(client_end).unwrap();
+    let buffer = &mut vec![0u8; 2];
+
+    cl.ag_read(888, 4, 2, buffer).unwrap();
+    assert_eq!(buffer, &vec![0xAB, 0xCD]);
+
+    cl.ag_write(888, 4, 1, &mut [0x11]).unwrap();
+    plc.join().unwrap();
+}
+
+#[test]
+fn test_loopback_disconnected_peer() {
+    let (mut client_end, plc) = loopback();
+    drop(plc);
+
+    assert!(client_end.send(&[1, 2, 3]).is_err());
+}
+
+#[test]
+fn test_negotiation_info_parse() {
+    // answer of a S7-1500, 3 outstanding jobs each way and 960 bytes PDU
+    let response = [
+        0x03, 0x00, 0x00, 0x1B, 0x02, 0xF0, 0x80, 0x32, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08,
+        0x00, 0x00, 0x00, 0x00, 0xF0, 0x00, 0x00, 0x03, 0x00, 0x03, 0x03, 0xC0,
+    ];
+    assert_eq!(
+        NegotiationInfo::parse(&response).unwrap(),
+        NegotiationInfo {
+            pdu_length: 960,
+            max_amq_calling: 3,
+            max_amq_called: 3,
+        }
+    );
+
+    // error class set
+    let mut refused = response;
+    refused[17] = 0x81;
+    assert!(NegotiationInfo::parse(&refused).is_err());
+    assert!(NegotiationInfo::parse(&response[..26]).is_err());
+
+    let mut padded = response.to_vec();
+    padded.extend_from_slice(&[0, 0]);
+    assert_eq!(NegotiationInfo::parse(&padded).unwrap().pdu_length, 960);
+}
+
+#[test]
+fn test_frame_helpers() {
+    // read var response split in two data units, the second flagged as the last
+    let first = [
+        3, 0, 0, 20, 2, 0xF0, 0x00, 0x32, 3, 0, 0, 0, 1, 0, 2, 0, 6, 0, 0, 4,
+    ];
+    let last = [3, 0, 0, 13, 2, 0xF0, 0x80, 1, 0xFF, 4, 0, 16, 0xAB];
+    assert_eq!(parse_tpkt_len(&first), Some(20));
+    assert_eq!(parse_tpkt_len(&last), Some(13));
+    assert!(is_s7_data_pdu(&first));
+    // a continuation carries no S7 header
+    assert!(!is_s7_data_pdu(&last));
+
+    assert!(!is_s7_data_pdu(&DISCONNECT_REQUEST_TELEGRAM));
+    assert!(!is_s7_data_pdu(&ISO_CONNECTION_REQUEST_TELEGRAM));
+    assert!(is_s7_data_pdu(&PDU_NEGOTIATION_TELEGRAM));
+    assert_eq!(parse_tpkt_len(&PDU_NEGOTIATION_TELEGRAM), Some(25));
+
+    assert_eq!(parse_tpkt_len(&[3, 0, 0]), None);
+    assert_eq!(parse_tpkt_len(&[3, 0, 0, 3]), None);
+    assert_eq!(parse_tpkt_len(&[3, 1, 0, 7]), None);
+    assert!(!is_s7_data_pdu(&[3, 0, 0, 7, 2, 0xF0, 0x80]));
+}
+
+#[test]
+fn test_send_only_recv() {
+    let (mut client, plc) = loopback();
+
+    Transport::send_only(&mut client, &[1]).unwrap();
+    Transport::send_only(&mut client, &[2]).unwrap();
+    assert_eq!(plc.recv().unwrap(), vec![1]);
+    assert_eq!(plc.recv().unwrap(), vec![2]);
+    plc.reply(&[3]).unwrap();
+    plc.reply(&[4]).unwrap();
+    assert_eq!(Transport::recv(&mut client).unwrap(), vec![3]);
+    assert_eq!(Transport::recv(&mut client).unwrap(), vec![4]);
+
+    // split by default, every answer is kept until collected
+    struct Combined(u8);
+    impl Transport for Combined {
+        fn send(&mut self, request: &[u8]) -> Result<Vec<u8>, Error> {
+            let mut response = request.to_vec();
+            response.push(self.0);
+            Ok(response)
+        }
+        fn pdu_length(&self) -> i32 {
+            480
+        }
+        fn negotiate(&mut self) -> Result<NegotiationInfo, Error> {
+            Ok(NegotiationInfo::default())
+        }
+        fn connection_type(&self) -> Connection {
+            Connection::PG
+        }
+    }
+    let mut first = Combined(10);
+    let mut second = Combined(20);
+    first.send_only(&[1]).unwrap();
+    second.send_only(&[1]).unwrap();
+    first.send_only(&[2]).unwrap();
+    assert_eq!(first.recv().unwrap(), vec![1, 10]);
+    assert_eq!(second.recv().unwrap(), vec![1, 20]);
+    assert_eq!(first.recv().unwrap(), vec![2, 10]);
+    assert!(matches!(first.recv(), Err(Error::InvalidInput { .. })));
+    assert!(matches!(second.recv(), Err(Error::InvalidInput { .. })));
+}
+
+#[test]
+fn test_control_telegram() {
+    let program = b"P_PROGRAM";
+    for (telegram, expected) in [
+        (
+            control_telegram(PDU_START, b"", program),
+            &WARM_START_TELEGRAM[..],
+        ),
+        (
+            control_telegram(PDU_START, b"C ", program),
+            &COLD_START_TELEGRAM[..],
+        ),
+        (
+            control_telegram(PDU_START, b"A ", program),
+            &HOT_RESTART_TELEGRAM[..],
+        ),
+        (control_telegram(PDU_STOP, b"", program), &STOP_TELEGRAM[..]),
+    ] {
+        // the PDU reference is stamped when sending
+        assert_eq!(telegram[..11], expected[..11]);
+        assert_eq!(telegram[13..], expected[13..]);
+    }
+}